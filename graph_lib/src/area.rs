@@ -0,0 +1,86 @@
+use crate::{Coordinates, wrap_lon, lon_difference, coast_distance::segments_intersect, random_point::line_cross_check};
+
+/**
+ * polygon of an area on the map, e.g. of an avoid area or a penalty zone.
+ * The polygon may cross the antimeridian, but has to be narrower than 180° in longitude.
+ */
+#[derive(Clone, Debug)]
+pub struct AreaPolygon {
+    /// longitude of the first point; the polygon is saved with longitudes relative to it, so it is continuous over the antimeridian
+    reference_lon: f32,
+    polygon: Vec<Coordinates>,
+    /// [[min_lon, max_lon], [min_lat, max_lat]] relative to the reference longitude
+    bounding_box: [[f32; 2]; 2],
+}
+
+impl AreaPolygon {
+    /**
+     * polygon of (lon, lat); the ring is closed, if the last point isn't the first one
+     */
+    pub fn new(polygon: &[Coordinates]) -> AreaPolygon {
+        let reference_lon = polygon.first().map_or(0.0, |point| wrap_lon(point.0));
+        let mut local: Vec<Coordinates> = Vec::with_capacity(polygon.len() + 1);
+        for point in polygon {
+            let lon = match local.last() {
+                Some(last) => last.0 + lon_difference(wrap_lon(last.0 + reference_lon), wrap_lon(point.0)),
+                None => 0.0,
+            };
+            local.push(Coordinates(lon, point.1));
+        }
+        if let (Some(first), Some(last)) = (local.first().copied(), local.last()) {
+            if first.0 != last.0 || first.1 != last.1 {
+                local.push(first);
+            }
+        }
+        let bounding_box = local.iter().fold([[f32::MAX, f32::MIN], [f32::MAX, f32::MIN]], |[lons, lats], point| {
+            [[lons[0].min(point.0), lons[1].max(point.0)], [lats[0].min(point.1), lats[1].max(point.1)]]
+        });
+        AreaPolygon { reference_lon, polygon: local, bounding_box }
+    }
+
+    /**
+     * the polygon as (lon, lat) in -180 to 180
+     */
+    pub fn polygon(&self) -> Vec<Coordinates> {
+        self.polygon.iter().map(|point| Coordinates(wrap_lon(point.0 + self.reference_lon), point.1)).collect()
+    }
+
+    #[inline]
+    fn local_lon(&self, lon: f32) -> f32 {
+        let lon = lon_difference(self.reference_lon, lon);
+        let [min_lon, max_lon] = self.bounding_box[0];
+        // the polygon may reach further than 180° from its first point
+        if lon < min_lon && lon + 360.0 <= max_lon {
+            lon + 360.0
+        } else if lon > max_lon && lon - 360.0 >= min_lon {
+            lon - 360.0
+        } else {
+            lon
+        }
+    }
+
+    #[inline]
+    fn contains_local(&self, point: &Coordinates) -> bool {
+        let [lons, lats] = self.bounding_box;
+        point.0 >= lons[0] && point.0 <= lons[1] && point.1 >= lats[0] && point.1 <= lats[1]
+            && self.polygon.windows(2).filter(|edge| line_cross_check(&edge[0], &edge[1], point.0, point.1)).count() % 2 == 1
+    }
+
+    pub fn contains(&self, point: &Coordinates) -> bool {
+        self.contains_local(&Coordinates(self.local_lon(point.0), point.1))
+    }
+
+    /**
+     * true if the segment from start to end has an end in the area or crosses its border
+     */
+    pub fn crosses(&self, start: &Coordinates, end: &Coordinates) -> bool {
+        let start = Coordinates(self.local_lon(start.0), start.1);
+        let end = Coordinates(start.0 + lon_difference(wrap_lon(start.0 + self.reference_lon), end.0), end.1);
+        let [lons, lats] = self.bounding_box;
+        if start.0.max(end.0) < lons[0] || start.0.min(end.0) > lons[1] || start.1.max(end.1) < lats[0] || start.1.min(end.1) > lats[1] {
+            return false;
+        }
+        self.contains_local(&start) || self.contains_local(&end)
+            || self.polygon.windows(2).any(|edge| segments_intersect(&start, &end, &edge[0], &edge[1]))
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use rayon::prelude::*;
+use crate::{Graph, Coordinates, area::AreaPolygon};
+
+/**
+ * area, which routes have to avoid, e.g. a closed strait, a war risk area or a military exercise.
+ * The polygon may cross the antimeridian, but has to be narrower than 180° in longitude.
+ */
+#[derive(Clone, Debug)]
+pub struct AvoidArea {
+    pub name: String,
+    area: AreaPolygon,
+}
+
+impl AvoidArea {
+    /**
+     * area of the polygon of (lon, lat); the ring is closed, if the last point isn't the first one
+     */
+    pub fn new(name: &str, polygon: &[Coordinates]) -> AvoidArea {
+        AvoidArea { name: name.to_string(), area: AreaPolygon::new(polygon) }
+    }
+
+    /**
+     * the polygon as (lon, lat) in -180 to 180
+     */
+    pub fn polygon(&self) -> Vec<Coordinates> {
+        self.area.polygon()
+    }
+
+    pub fn contains(&self, point: &Coordinates) -> bool {
+        self.area.contains(point)
+    }
+
+    /**
+     * true if the segment from start to end has an end in the area or crosses its border
+     */
+    pub fn crosses(&self, start: &Coordinates, end: &Coordinates) -> bool {
+        self.area.crosses(start, end)
+    }
+}
+
+/**
+ * nodes, edges and areas closed at runtime on top of a loaded graph, e.g. a closed strait or a military exercise.
+ * The closed edges are resolved per edge id after every change, so the routings check them without copying the graph.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Closures {
+    areas: Vec<(usize, AvoidArea)>,
+    next_area_id: usize,
+    nodes: HashSet<usize>,
+    edges: HashSet<usize>,
+    /// per node and edge of the graph; empty if nothing is closed
+    closed_nodes: Vec<bool>,
+    closed_edges: Vec<bool>,
+}
+
+impl Closures {
+    /**
+     * closes all nodes in the area and all edges, which end in it or cross its border; returns the id of the area
+     */
+    pub fn add_area(&mut self, graph: &Graph, area: AvoidArea) -> usize {
+        let id = self.next_area_id;
+        self.next_area_id += 1;
+        self.areas.push((id, area));
+        self.update(graph);
+        id
+    }
+
+    /**
+     * opens the area again; false if there is no area with the id
+     */
+    pub fn remove_area(&mut self, graph: &Graph, id: usize) -> bool {
+        let n_areas = self.areas.len();
+        self.areas.retain(|(area_id, _)| *area_id != id);
+        let removed = self.areas.len() < n_areas;
+        if removed {
+            self.update(graph);
+        }
+        removed
+    }
+
+    /**
+     * closes the nodes and all their edges
+     */
+    pub fn close_nodes(&mut self, graph: &Graph, node_ids: &[usize]) {
+        self.nodes.extend(node_ids.iter().filter(|node_id| **node_id < graph.n_nodes()));
+        self.update(graph);
+    }
+
+    /**
+     * closes the edges in both directions
+     */
+    pub fn close_edges(&mut self, graph: &Graph, edge_ids: &[usize]) {
+        for edge_id in edge_ids.iter().filter(|edge_id| **edge_id < graph.n_edges()) {
+            let edge = &graph.edges[*edge_id];
+            self.edges.insert(*edge_id);
+            self.edges.extend(graph.get_outgoing_edges_with_ids(edge.tgt).filter(|(_, back)| back.tgt == edge.src).map(|(back_id, _)| back_id));
+        }
+        self.update(graph);
+    }
+
+    /**
+     * opens all nodes, edges and areas
+     */
+    pub fn clear(&mut self) {
+        *self = Closures { next_area_id: self.next_area_id, ..Default::default() };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.areas.is_empty() && self.nodes.is_empty() && self.edges.is_empty()
+    }
+
+    pub fn areas(&self) -> impl Iterator<Item = (usize, &AvoidArea)> {
+        self.areas.iter().map(|(id, area)| (*id, area))
+    }
+
+    #[inline]
+    pub fn is_node_closed(&self, node_id: usize) -> bool {
+        self.closed_nodes.get(node_id).is_some_and(|closed| *closed)
+    }
+
+    #[inline]
+    pub fn is_edge_closed(&self, edge_id: usize) -> bool {
+        self.closed_edges.get(edge_id).is_some_and(|closed| *closed)
+    }
+
+    pub fn n_closed_edges(&self) -> usize {
+        self.closed_edges.iter().filter(|closed| **closed).count()
+    }
+
+    fn update(&mut self, graph: &Graph) {
+        if self.is_empty() {
+            self.closed_nodes = vec![];
+            self.closed_edges = vec![];
+            return;
+        }
+        let closed_nodes: Vec<bool> = graph.nodes.par_iter().map(|node| {
+            self.nodes.contains(&node.id) || self.areas.iter().any(|(_, area)| area.contains(&Coordinates(node.lon, node.lat)))
+        }).collect();
+        self.closed_edges = graph.edges.par_iter().enumerate().map(|(edge_id, edge)| {
+            let (src, tgt) = (graph.get_node(edge.src), graph.get_node(edge.tgt));
+            closed_nodes[edge.src] || closed_nodes[edge.tgt] || self.edges.contains(&edge_id)
+                || self.areas.iter().any(|(_, area)| area.crosses(&Coordinates(src.lon, src.lat), &Coordinates(tgt.lon, tgt.lat)))
+        }).collect();
+        self.closed_nodes = closed_nodes;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Graph, Node, Edge, Coordinates, closures::{AvoidArea, Closures}};
+
+    #[test]
+    fn test_closures() {
+        // nodes on the equator from 178°E over the antimeridian to 178°W and an edge from 178°E to 178°W
+        let nodes = vec![
+            Node { id: 0, lon: 178.0, lat: 0.0 }, Node { id: 1, lon: 180.0, lat: 0.0 },
+            Node { id: 2, lon: -178.0, lat: 0.0 }, Node { id: 3, lon: 178.0, lat: 1.0 },
+        ];
+        let edges = vec![
+            Edge { src: 0, tgt: 1, dist: 222000 }, Edge { src: 0, tgt: 3, dist: 111000 },
+            Edge { src: 1, tgt: 0, dist: 222000 }, Edge { src: 1, tgt: 2, dist: 222000 },
+            Edge { src: 2, tgt: 1, dist: 222000 }, Edge { src: 3, tgt: 0, dist: 111000 },
+        ];
+        let graph = Graph::new(nodes, edges);
+        let mut closures = Closures::default();
+        assert!(!closures.is_edge_closed(0));
+
+        // an area over the antimeridian around node 1
+        let around_node = [Coordinates(179.5, -0.5), Coordinates(-179.5, -0.5), Coordinates(-179.5, 0.5), Coordinates(179.5, 0.5)];
+        let id = closures.add_area(&graph, AvoidArea::new("exercise", &around_node));
+        assert!(closures.is_node_closed(1) && !closures.is_node_closed(0));
+        assert_eq!((0..6).map(|edge_id| closures.is_edge_closed(edge_id)).collect::<Vec<bool>>(), vec![true, false, true, true, true, false]);
+
+        // an area between node 0 and 3, which contains no node
+        assert!(closures.remove_area(&graph, id));
+        closures.add_area(&graph, AvoidArea::new("strait", &[Coordinates(177.5, 0.4), Coordinates(178.5, 0.4), Coordinates(178.5, 0.6), Coordinates(177.5, 0.6)]));
+        assert_eq!(closures.n_closed_edges(), 2);
+        assert!(closures.is_edge_closed(1) && closures.is_edge_closed(5));
+
+        closures.clear();
+        closures.close_edges(&graph, &[3]);
+        assert!(closures.is_edge_closed(3) && closures.is_edge_closed(4) && !closures.is_edge_closed(0));
+        closures.close_nodes(&graph, &[3]);
+        assert!(closures.is_edge_closed(1) && closures.is_edge_closed(5));
+    }
+}
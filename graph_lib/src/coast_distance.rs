@@ -0,0 +1,182 @@
+use crate::{island::{Island, MOST_SOUTHERN_LAT_IN_SEA, is_border_edge}, Coordinates, lon_difference};
+
+const CELLS_PER_DEGREE: usize = 4;
+const N_COLUMNS: usize = 360 * CELLS_PER_DEGREE;
+const N_ROWS: usize = 180 * CELLS_PER_DEGREE;
+const METERS_PER_DEGREE: f32 = 111195.0;
+const MIN_SEARCH_RADIUS: f32 = 25000.0;
+
+/**
+ * Spatial index over all coastline segments to answer the distance to the closest coastline.
+ * Segments are saved as (island index, point index) in cells of 0.25° x 0.25°, so the index holds no references
+ * and the islands have to be given again for each query.
+ */
+pub struct CoastDistance {
+    cells: Vec<Vec<(u32, u32)>>,
+}
+
+impl CoastDistance {
+    pub fn new(islands: &[Island]) -> CoastDistance {
+        let mut cells: Vec<Vec<(u32, u32)>> = vec![vec![]; N_COLUMNS * N_ROWS];
+        for (island_i, island) in islands.iter().enumerate() {
+            let coastline = island.get_coastline();
+            for point_i in 1..coastline.len() {
+                let (start, end) = (&coastline[point_i - 1], &coastline[point_i]);
+                // edges around the southpole are only needed to close antarctica, they are no real coastline
+                if start.1 < MOST_SOUTHERN_LAT_IN_SEA && end.1 < MOST_SOUTHERN_LAT_IN_SEA { continue }
+                // edges closing rings along the antimeridian aren't either; rings of the island index don't cross it
+                if is_border_edge(start, end) || (start.0 - end.0).abs() > 180.0 { continue }
+
+                let [min_col, min_row] = cell_of(start.0.min(end.0), start.1.min(end.1));
+                let [max_col, max_row] = cell_of(start.0.max(end.0), start.1.max(end.1));
+                for col in min_col..=max_col {
+                    for row in min_row..=max_row {
+                        cells[row * N_COLUMNS + col].push((island_i as u32, point_i as u32 - 1));
+                    }
+                }
+            }
+        }
+        CoastDistance { cells }
+    }
+
+    /**
+     * distance in m to the closest coastline; if no coastline is closer than max_distance, max_distance is returned.
+     * The search window is doubled until a coastline is found within the searched radius.
+     */
+    pub fn distance_to_coast(&self, islands: &[Island], lon: f32, lat: f32, max_distance: f32) -> f32 {
+        let mut radius = MIN_SEARCH_RADIUS.min(max_distance);
+        loop {
+            let closest = self.closest_in_radius(islands, lon, lat, radius);
+            if closest <= radius || radius >= max_distance {
+                return closest.min(max_distance);
+            }
+            radius = (radius * 2.0).min(max_distance);
+        }
+    }
+
+    /**
+     * true if the segment from start to end crosses a coastline; segments over the date border are split there
+     */
+    pub fn crosses_coastline(&self, islands: &[Island], start: &Coordinates, end: &Coordinates) -> bool {
+        if (start.0 - end.0).abs() > 180.0 {
+            let (west, east) = if start.0 < end.0 { (start, end) } else { (end, start) };
+            let lat_at_border = east.1 + (west.1 - east.1) * (180.0 - east.0) / (west.0 + 360.0 - east.0);
+            return self.crosses_coastline(islands, east, &Coordinates(180.0, lat_at_border))
+                || self.crosses_coastline(islands, &Coordinates(-180.0, lat_at_border), west);
+        }
+        let [min_col, min_row] = cell_of(start.0.min(end.0), start.1.min(end.1));
+        let [max_col, max_row] = cell_of(start.0.max(end.0), start.1.max(end.1));
+        for row in min_row..=max_row {
+            for col in min_col..=max_col {
+                for (island_i, point_i) in &self.cells[row * N_COLUMNS + col] {
+                    let coastline = islands[*island_i as usize].get_coastline();
+                    if segments_intersect(start, end, &coastline[*point_i as usize], &coastline[*point_i as usize + 1]) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    fn closest_in_radius(&self, islands: &[Island], lon: f32, lat: f32, radius: f32) -> f32 {
+        let [col, row] = cell_of(lon, lat);
+        let cells_lat = (radius / METERS_PER_DEGREE * CELLS_PER_DEGREE as f32).ceil() as usize;
+        // the cell width shrinks towards the poles, so more columns are needed to cover the radius
+        let max_abs_lat = (lat.abs() + radius / METERS_PER_DEGREE).min(89.0);
+        let cells_lon = ((radius / (METERS_PER_DEGREE * max_abs_lat.to_radians().cos()) * CELLS_PER_DEGREE as f32).ceil() as usize)
+            .min(N_COLUMNS / 2);
+        let cos_lat = lat.to_radians().cos();
+
+        let mut closest = f32::MAX;
+        for check_row in row.saturating_sub(cells_lat)..(row + cells_lat + 1).min(N_ROWS) {
+            for col_offset in 0..(2 * cells_lon + 1) {
+                let check_col = (col + N_COLUMNS + col_offset - cells_lon) % N_COLUMNS;
+                for (island_i, point_i) in &self.cells[check_row * N_COLUMNS + check_col] {
+                    let coastline = islands[*island_i as usize].get_coastline();
+                    let distance = distance_to_segment(
+                        lon, lat, cos_lat, &coastline[*point_i as usize], &coastline[*point_i as usize + 1]
+                    );
+                    closest = closest.min(distance);
+                }
+            }
+        }
+        closest
+    }
+}
+
+#[inline]
+fn cell_of(lon: f32, lat: f32) -> [usize; 2] {
+    let col = (((lon + 180.0) * CELLS_PER_DEGREE as f32) as usize).min(N_COLUMNS - 1);
+    let row = (((lat + 90.0) * CELLS_PER_DEGREE as f32) as usize).min(N_ROWS - 1);
+    [col, row]
+}
+
+/**
+ * true if the segments a-b and c-d cross each other in the plane of lon and lat
+ */
+#[inline]
+pub(crate) fn segments_intersect(a: &Coordinates, b: &Coordinates, c: &Coordinates, d: &Coordinates) -> bool {
+    let orientation = |p: &Coordinates, q: &Coordinates, r: &Coordinates| (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0);
+    (orientation(c, d, a) > 0.0) != (orientation(c, d, b) > 0.0) && (orientation(a, b, c) > 0.0) != (orientation(a, b, d) > 0.0)
+}
+
+/**
+ * distance in m between the point and the segment start-end;
+ * uses an equirectangular projection around the point, which is precise enough for the short distances checked here
+ */
+#[inline]
+pub fn distance_to_segment(lon: f32, lat: f32, cos_lat: f32, start: &Coordinates, end: &Coordinates) -> f32 {
+    let project = |point: &Coordinates| {
+        let d_lon = lon_difference(lon, point.0);
+        (d_lon * cos_lat * METERS_PER_DEGREE, (point.1 - lat) * METERS_PER_DEGREE)
+    };
+    let (x1, y1) = project(start);
+    let (x2, y2) = project(end);
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (-(x1 * dx + y1 * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (x, y) = (x1 + t * dx, y1 + t * dy);
+    (x * x + y * y).sqrt()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Coordinates, island::IslandIndex, coast_distance::{CoastDistance, segments_intersect}};
+
+    #[test]
+    fn test_segments_intersect() {
+        let (a, b) = (Coordinates(0.0, 0.0), Coordinates(2.0, 2.0));
+        assert!(segments_intersect(&a, &b, &Coordinates(0.0, 2.0), &Coordinates(2.0, 0.0)));
+        // parallel, apart and only touching the line of the other segment beyond its end
+        assert!(!segments_intersect(&a, &b, &Coordinates(1.0, 0.0), &Coordinates(3.0, 2.0)));
+        assert!(!segments_intersect(&a, &b, &Coordinates(3.0, 0.0), &Coordinates(4.0, 1.0)));
+        assert!(!segments_intersect(&a, &b, &Coordinates(3.0, 2.0), &Coordinates(5.0, 0.0)));
+    }
+
+    #[test]
+    fn test_distance_to_coast() {
+        // an island of 1° x 1° at the equator with a point every 0.25°
+        let mut ring: Vec<Vec<f32>> = vec![];
+        (0..4).for_each(|i| ring.push(vec![i as f32 * 0.25, 0.0]));
+        (0..4).for_each(|i| ring.push(vec![1.0, i as f32 * 0.25]));
+        (0..4).for_each(|i| ring.push(vec![1.0 - i as f32 * 0.25, 1.0]));
+        (0..=4).for_each(|i| ring.push(vec![0.0, 1.0 - i as f32 * 0.25]));
+        let index = IslandIndex::new(&[ring]);
+        let coast_distance = CoastDistance::new(&index.islands);
+
+        // 0.1° east of the coast, farther than the first search radius and farther than the maximal distance
+        let close = coast_distance.distance_to_coast(&index.islands, 1.1, 0.5, 200000.0);
+        assert!((close - 11120.0).abs() < 50.0, "{}", close);
+        let far = coast_distance.distance_to_coast(&index.islands, 1.5, 0.5, 200000.0);
+        assert!((far - 55600.0).abs() < 100.0, "{}", far);
+        assert_eq!(coast_distance.distance_to_coast(&index.islands, 5.0, 0.5, 200000.0), 200000.0);
+
+        assert!(coast_distance.crosses_coastline(&index.islands, &Coordinates(-0.5, 0.5), &Coordinates(1.5, 0.5)));
+        assert!(!coast_distance.crosses_coastline(&index.islands, &Coordinates(-0.5, -0.5), &Coordinates(1.5, -0.5)));
+    }
+}
@@ -0,0 +1,169 @@
+use std::error::Error;
+use crate::{Node, lon_difference, wrap_lon, esri_ascii::RasterGrid, ice::parse_departure_day};
+
+pub const METERS_PER_SECOND_PER_KNOT: f32 = 1852.0 / 3600.0;
+/// speed over ground in m/s, below which an edge is not passable against the current
+const MIN_SPEED_OVER_GROUND: f32 = 0.1;
+/// length of a degree of latitude in m
+pub(crate) const METERS_PER_DEGREE: f32 = 111_195.0;
+
+/**
+ * currents of the time steps, the east (u) and north (v) component in m/s at the hours after the start of the field,
+ * each read from an ESRI ASCII grid
+ */
+#[derive(Debug)]
+pub struct CurrentField {
+    steps: Vec<(f32, RasterGrid, RasterGrid)>,
+    /// strongest current of all steps in m/s, for a lower bound of the time
+    max_speed: f32,
+    /// day of the start of the field in days since 1970-01-01, if known
+    start_day: Option<i64>,
+}
+
+impl CurrentField {
+    pub fn new(mut steps: Vec<(f32, RasterGrid, RasterGrid)>) -> CurrentField {
+        steps.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let max_speed = steps.iter()
+            .flat_map(|(_, u, v)| u.values().iter().zip(v.values()).map(|(u, v)| (u * u + v * v).sqrt()))
+            .filter(|speed| !speed.is_nan())
+            .fold(0.0, f32::max);
+        CurrentField { steps, max_speed, start_day: None }
+    }
+
+    pub fn with_start_day(self, start_day: Option<i64>) -> CurrentField {
+        CurrentField { start_day, ..self }
+    }
+
+    /**
+     * reads the grids u_{hours}.asc and v_{hours}.asc of the folder, e.g. u_0.asc, v_0.asc, u_6.asc, v_6.asc,
+     * and the date of hour 0 from start.txt, if there is one
+     */
+    pub fn read(folder: &str) -> Result<CurrentField, Box<dyn Error>> {
+        let mut steps = vec![];
+        for entry in std::fs::read_dir(folder)? {
+            let file_name = entry?.file_name().into_string().unwrap_or_default();
+            let Some(hours) = file_name.strip_prefix("u_").and_then(|name| name.strip_suffix(".asc")) else {
+                continue;
+            };
+            let u = RasterGrid::read_esri_ascii(&format!("{}/{}", folder, file_name))?;
+            let v = RasterGrid::read_esri_ascii(&format!("{}/v_{}.asc", folder, hours))?;
+            steps.push((hours.parse::<f32>()?, u, v));
+        }
+        if steps.is_empty() {
+            return Err(format!("No currents u_{{hours}}.asc in {}", folder).into());
+        }
+        Ok(CurrentField::new(steps).with_start_day(read_start_day(folder)?))
+    }
+
+    pub fn n_steps(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn max_speed(&self) -> f32 {
+        self.max_speed
+    }
+
+    pub fn start_day(&self) -> Option<i64> {
+        self.start_day
+    }
+
+    /**
+     * current (u, v) in m/s at the point and the hours after the start of the field, linear between the time steps;
+     * no current outside of the grids or without data
+     */
+    pub fn current_at(&self, lon: f32, lat: f32, hours: f32) -> (f32, f32) {
+        let at = |step: &(f32, RasterGrid, RasterGrid)| (step.1.value_at(lon, lat).unwrap_or(0.0), step.2.value_at(lon, lat).unwrap_or(0.0));
+        match steps_around(&self.steps, |step| step.0, hours) {
+            Some((previous, next, fraction)) => {
+                let ((u1, v1), (u2, v2)) = (at(previous), at(next));
+                (u1 + (u2 - u1) * fraction, v1 + (v2 - v1) * fraction)
+            },
+            None => (0.0, 0.0),
+        }
+    }
+
+    /**
+     * smallest cell size of the grids in m, the distance at which the current is sampled along an edge
+     */
+    pub fn sample_distance(&self) -> f32 {
+        self.steps.iter().map(|(_, u, v)| u.header.cell_size.min(v.header.cell_size) as f32 * METERS_PER_DEGREE).fold(f32::MAX, f32::min)
+    }
+
+    /**
+     * speed over ground in m/s on the course (east, north) as unit vector at the speed through water in m/s. The vessel keeps
+     * its course, so it steers against the cross current; None if the current is too strong for the speed.
+     */
+    pub fn speed_over_ground(&self, lon: f32, lat: f32, hours: f32, course: (f32, f32), speed: f32) -> Option<f32> {
+        let (u, v) = self.current_at(lon, lat, hours);
+        let along = u * course.0 + v * course.1;
+        let cross = v * course.0 - u * course.1;
+        if cross.abs() >= speed {
+            return None;
+        }
+        Some(along + (speed * speed - cross * cross).sqrt()).filter(|speed_over_ground| *speed_over_ground >= MIN_SPEED_OVER_GROUND)
+    }
+}
+
+/**
+ * direction from one node to the next as unit vector (east, north); None if both are at the same place
+ */
+pub fn course(from: &Node, to: &Node) -> Option<(f32, f32)> {
+    let east = lon_difference(from.lon, to.lon) * ((from.lat + to.lat) / 2.0).to_radians().cos();
+    let north = to.lat - from.lat;
+    let length = (east * east + north * north).sqrt();
+    (length > 0.0).then(|| (east / length, north / length))
+}
+
+/**
+ * point at the fraction of the way from one node to the next, over the antimeridian if that is shorter
+ */
+pub fn point_along(from: &Node, to: &Node, fraction: f32) -> (f32, f32) {
+    (wrap_lon(from.lon + lon_difference(from.lon, to.lon) * fraction), from.lat + (to.lat - from.lat) * fraction)
+}
+
+/**
+ * day of hour 0 of a field from the date like 2024-07-15 in start.txt of its folder; None without the file
+ */
+pub(crate) fn read_start_day(folder: &str) -> Result<Option<i64>, Box<dyn Error>> {
+    match std::fs::read_to_string(format!("{}/start.txt", folder)) {
+        Ok(date) => Ok(Some(parse_departure_day(&date).ok_or(format!("No date in {}/start.txt: {}", folder, date.trim()))?)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/**
+ * the time steps (sorted by their hours) before and after the hours and the fraction of the time between them;
+ * before the first and after the last step both are the same step
+ */
+pub(crate) fn steps_around<T>(steps: &[T], hours_of_step: impl Fn(&T) -> f32, hours: f32) -> Option<(&T, &T, f32)> {
+    let next = steps.partition_point(|step| hours_of_step(step) <= hours);
+    match (next.checked_sub(1).map(|previous| &steps[previous]), steps.get(next)) {
+        (Some(previous), Some(next)) => Some((previous, next, (hours - hours_of_step(previous)) / (hours_of_step(next) - hours_of_step(previous)))),
+        (Some(step), None) | (None, Some(step)) => Some((step, step, 0.0)),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{esri_ascii::RasterGrid, currents::CurrentField};
+
+    #[test]
+    fn test_current_field() {
+        // 1 m/s to the east at the start, to the north from hour 10 on; grids from 0° to 360°
+        let east = || RasterGrid::from_reader("ncols 2\nnrows 1\nxllcorner 0\nyllcorner -10\ncellsize 180\nNODATA_value -9999\n1 1\n".as_bytes()).unwrap();
+        let zero = || RasterGrid::from_reader("ncols 2\nnrows 1\nxllcorner 0\nyllcorner -10\ncellsize 180\n0 0\n".as_bytes()).unwrap();
+        let field = CurrentField::new(vec![(10.0, zero(), east()), (0.0, east(), zero())]);
+        assert_eq!(field.n_steps(), 2);
+        assert_eq!(field.max_speed(), 1.0);
+        assert_eq!(field.current_at(-20.0, 0.0, 0.0), (1.0, 0.0));
+        assert_eq!(field.current_at(-20.0, 0.0, 5.0), (0.5, 0.5));
+        assert_eq!(field.current_at(-20.0, 0.0, 20.0), (0.0, 1.0));
+        assert_eq!(field.current_at(-20.0, -20.0, 0.0), (0.0, 0.0));
+        assert_eq!(field.sample_distance(), 180.0 * 111_195.0);
+        // against the current of 1 m/s the speed over ground is 4 m/s, at 1 m/s the vessel doesn't get ahead
+        assert_eq!(field.speed_over_ground(-20.0, 0.0, 0.0, (-1.0, 0.0), 5.0), Some(4.0));
+        assert_eq!(field.speed_over_ground(-20.0, 0.0, 0.0, (-1.0, 0.0), 1.0), None);
+    }
+}
@@ -0,0 +1,162 @@
+use std::{error::Error, fs::File, io::{BufRead, BufReader, Lines}};
+
+/**
+ * size and position of an ESRI ASCII grid (.asc), whose rows go from north to south
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct GridHeader {
+    pub n_cols: usize,
+    pub n_rows: usize,
+    /// lon and lat of the south west corner of the grid
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub cell_size: f64,
+}
+
+impl GridHeader {
+    /**
+     * row (from the north) and column of the cell of the point, None outside of the grid; grids from 0° to 360° are supported as well
+     */
+    pub fn cell_of(&self, lon: f32, lat: f32) -> Option<(usize, usize)> {
+        let row_from_south = ((lat as f64 - self.min_lat) / self.cell_size).floor();
+        if row_from_south < 0.0 || row_from_south >= self.n_rows as f64 {
+            return None;
+        }
+        let row = self.n_rows - 1 - row_from_south as usize;
+        [lon as f64, lon as f64 + 360.0].iter()
+            .map(|lon| ((lon - self.min_lon) / self.cell_size).floor())
+            .find(|col| *col >= 0.0 && *col < self.n_cols as f64)
+            .map(|col| (row, col as usize))
+    }
+}
+
+/**
+ * the rows of an ESRI ASCII grid from north to south, read one after the other, so big grids don't have to be kept as text;
+ * cells without data are NaN
+ */
+pub struct GridRows<R: BufRead> {
+    lines: Lines<R>,
+    first_row: Option<String>,
+    no_data_value: Option<f32>,
+    n_cols: usize,
+    rows_left: usize,
+}
+
+impl<R: BufRead> Iterator for GridRows<R> {
+    type Item = Result<Vec<f32>, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rows_left == 0 {
+            return None;
+        }
+        self.rows_left -= 1;
+        let line = match self.first_row.take() {
+            Some(line) => line,
+            None => match self.lines.next()? {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error.into())),
+            },
+        };
+        let no_data_value = self.no_data_value;
+        let row = line.split_whitespace().take(self.n_cols)
+            .map(|value| value.parse::<f32>().map(|value| if no_data_value == Some(value) { f32::NAN } else { value }))
+            .collect::<Result<Vec<f32>, _>>();
+        Some(match row {
+            Ok(row) if row.len() == self.n_cols => Ok(row),
+            Ok(row) => Err(format!("Expected {} values in row of ESRI ASCII grid, but found {}", self.n_cols, row.len()).into()),
+            Err(error) => Err(error.into()),
+        })
+    }
+}
+
+/**
+ * reads the header ncols, nrows, xllcorner or xllcenter, yllcorner or yllcenter, cellsize and the optional NODATA_value;
+ * the rows follow with the iterator
+ */
+pub fn read_esri_ascii<R: BufRead>(reader: R) -> Result<(GridHeader, GridRows<R>), Box<dyn Error>> {
+    let mut lines = reader.lines();
+    let mut header = |key: &str| -> Result<(String, f64), Box<dyn Error>> {
+        let line = lines.next().ok_or("Unexpected end of header")??;
+        let mut split = line.split_whitespace();
+        let name = split.next().unwrap_or_default().to_lowercase();
+        if !name.starts_with(key) {
+            return Err(format!("Expected {} in header of ESRI ASCII grid, but found: {}", key, line).into());
+        }
+        Ok((name, split.next().ok_or(format!("No value in line: {}", line))?.parse::<f64>()?))
+    };
+    let n_cols = header("ncols")?.1 as usize;
+    let n_rows = header("nrows")?.1 as usize;
+    let (x_name, x) = header("xll")?;
+    let (_, y) = header("yll")?;
+    let cell_size = header("cellsize")?.1;
+    // the header line of no data is optional
+    let mut no_data_value = None;
+    let mut first_row = None;
+    let line = lines.next().ok_or("No data in ESRI ASCII grid")??;
+    if line.to_lowercase().starts_with("nodata_value") {
+        no_data_value = line.split_whitespace().nth(1).map(|value| value.parse::<f32>()).transpose()?;
+    } else {
+        first_row = Some(line);
+    }
+    // the corner is given, or the center of the south west cell
+    let (min_lon, min_lat) = if x_name == "xllcenter" { (x - cell_size / 2.0, y - cell_size / 2.0) } else { (x, y) };
+    let header = GridHeader { n_cols, n_rows, min_lon, min_lat, cell_size };
+    Ok((header, GridRows { lines, first_row, no_data_value, n_cols, rows_left: n_rows }))
+}
+
+/**
+ * values of an ESRI ASCII grid as f32, e.g. a component of the current in m/s or the ice concentration in percent; NaN without data
+ */
+#[derive(Debug)]
+pub struct RasterGrid {
+    pub header: GridHeader,
+    values: Vec<f32>,
+}
+
+impl RasterGrid {
+    pub fn read_esri_ascii(filepath: &str) -> Result<RasterGrid, Box<dyn Error>> {
+        RasterGrid::from_reader(BufReader::new(File::open(filepath)?))
+    }
+
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<RasterGrid, Box<dyn Error>> {
+        let (header, rows) = read_esri_ascii(reader)?;
+        let mut values = Vec::with_capacity(header.n_cols * header.n_rows);
+        for row in rows {
+            values.extend(row?);
+        }
+        if values.len() != header.n_cols * header.n_rows {
+            return Err(format!("Expected {} values in ESRI ASCII grid, but found {}", header.n_cols * header.n_rows, values.len()).into());
+        }
+        Ok(RasterGrid { header, values })
+    }
+
+    /**
+     * value of the cell of the point, None outside of the grid or without data
+     */
+    pub fn value_at(&self, lon: f32, lat: f32) -> Option<f32> {
+        let (row, col) = self.header.cell_of(lon, lat)?;
+        Some(self.values[row * self.header.n_cols + col]).filter(|value| !value.is_nan())
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::esri_ascii::RasterGrid;
+
+    #[test]
+    fn test_raster_grid() {
+        // 3 x 2 cells of 10° from 170°E over the antimeridian, the northern row first
+        let asc = "ncols 3\nnrows 2\nxllcenter 175\nyllcenter 5\ncellsize 10\nNODATA_value -1\n1 2 -1\n4 5.5 6\n";
+        let grid = RasterGrid::from_reader(asc.as_bytes()).unwrap();
+        assert_eq!(grid.value_at(175.0, 15.0), Some(1.0));
+        assert_eq!(grid.value_at(-175.0, 5.0), Some(5.5));
+        assert_eq!(grid.value_at(-165.0, 15.0), None);
+        assert_eq!(grid.value_at(175.0, 25.0), None);
+        assert_eq!(grid.value_at(0.0, 5.0), None);
+        assert!(RasterGrid::from_reader("ncols 2\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\n1 2\n3\n".as_bytes()).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use std::{io::{BufRead, BufReader, Write}, fs::File };
 
-use crate::{Node, Edge, Graph};
+use crate::{Node, Edge, Graph, layers::{GraphLayers, Metric, LAYERS_VERSION}};
 
 /**
  * Tries to import .bin file of graph. If not existent it will take .fmi
@@ -23,14 +23,23 @@ pub fn import_graph_from_file(filename: &str) -> Result<Graph, std::io::Error> {
  * expects edges sorted; filename contains no file extension
  */
 pub fn print_graph_to_file(nodes: &Vec<Node>, edges: &Vec<Edge>, filename: &str) {
+    print_graph_with_layers_to_file(nodes, edges, &GraphLayers::default(), filename);
+}
+
+/**
+ * Print nodes and edges to .fmi and .bin file, the layers are only saved in the .bin file
+ * expects edges sorted; filename contains no file extension
+ */
+pub fn print_graph_with_layers_to_file(nodes: &Vec<Node>, edges: &Vec<Edge>, layers: &GraphLayers, filename: &str) {
     let path = "data/graph/".to_owned() + filename;
 
-    print_bin_graph(nodes, edges, &(path.to_owned() + ".bin"));
+    print_bin_graph(nodes, edges, layers, &(path.to_owned() + ".bin"));
     print_fmi_graph(nodes, edges, &(path.to_owned() + ".fmi"));
 }
 
-fn print_bin_graph(points: &Vec<Node>, edges: &Vec<Edge>, filepath: &str) {
-    let encoded = bincode::serialize(&(points, edges)).unwrap();
+fn print_bin_graph(points: &Vec<Node>, edges: &Vec<Edge>, layers: &GraphLayers, filepath: &str) {
+    let mut encoded = bincode::serialize(&(points, edges)).unwrap();
+    encoded.extend(bincode::serialize(&(LAYERS_VERSION, layers)).unwrap());
     
     let mut f = File::create(filepath).expect("Unable to create file");
     f.write_all(encoded.as_slice()).expect("unable to write file");
@@ -42,9 +51,9 @@ fn print_bin_graph(points: &Vec<Node>, edges: &Vec<Edge>, filepath: &str) {
 fn import_graph_from_bin_file(filepath: &str) -> Result<Graph, Box<bincode::ErrorKind>> {
     
     let graph_file = File::open(filepath)?;
-    let reader = BufReader::new(graph_file);
+    let mut reader = BufReader::new(graph_file);
 
-    let decoded: Result<(Vec<Node>, Vec<Edge>), Box<bincode::ErrorKind>> = bincode::deserialize_from(reader);
+    let decoded: Result<(Vec<Node>, Vec<Edge>), Box<bincode::ErrorKind>> = bincode::deserialize_from(&mut reader);
     match decoded {
         Ok((nodes, edges)) => {
             let mut offsets = vec![0usize; nodes.len() + 1];
@@ -58,12 +67,58 @@ fn import_graph_from_bin_file(filepath: &str) -> Result<Graph, Box<bincode::Erro
                 }
             }
             offsets[nodes.len()] = edges.len();
-            return Ok(Graph { nodes, edges, offsets });
+            let layers = import_layers(&mut reader)?;
+            Ok(Graph { nodes, edges, offsets, layers })
         },
-        Err(error) => return Err(error),
+        Err(error) => Err(error),
     }
 }
 
+/**
+ * reads the layers after nodes and edges; files of older versions end after the edges.
+ * Layers of older versions are read up to the layers they had, the newer ones stay empty.
+ */
+fn import_layers(reader: &mut BufReader<File>) -> Result<GraphLayers, Box<bincode::ErrorKind>> {
+    let version: u32 = match bincode::deserialize_from(&mut *reader) {
+        Ok(version) => version,
+        Err(error) => match *error {
+            bincode::ErrorKind::Io(ref io_error) if io_error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(GraphLayers::default()),
+            _ => return Err(error),
+        },
+    };
+    if version == LAYERS_VERSION {
+        return bincode::deserialize_from(reader);
+    }
+    if version == 0 || version > LAYERS_VERSION {
+        return Err(Box::new(bincode::ErrorKind::Custom(format!("layers of version {}, but expected version {}", version, LAYERS_VERSION))));
+    }
+    // the layers are written one after the other, every version added layers at the end
+    let mut layers = GraphLayers {
+        corridors: bincode::deserialize_from(&mut *reader)?,
+        edge_corridor: bincode::deserialize_from(&mut *reader)?,
+        ..Default::default()
+    };
+    if version >= 2 {
+        layers.node_depth = bincode::deserialize_from(&mut *reader)?;
+        layers.edge_depth = bincode::deserialize_from(&mut *reader)?;
+    }
+    if version >= 3 {
+        layers.node_clearance = bincode::deserialize_from(&mut *reader)?;
+    }
+    if version >= 4 {
+        layers.node_ice_months = bincode::deserialize_from(&mut *reader)?;
+        layers.edge_ice_months = bincode::deserialize_from(&mut *reader)?;
+    }
+    if version >= 5 {
+        // metrics of version 5 have no speed and fuel consumption
+        let metrics: Vec<(String, String, Vec<u32>, f32)> = bincode::deserialize_from(&mut *reader)?;
+        layers.metrics = metrics.into_iter()
+            .map(|(name, unit, weights, min_weight_per_m)| Metric { name, unit, weights, min_weight_per_m, speed: None, tons_per_day: None })
+            .collect();
+    }
+    Ok(layers)
+}
+
 fn print_fmi_graph(points: &Vec<Node>, edges: &Vec<Edge>, filepath: &str) {
     let mut data_string = String::new();
     data_string = data_string + &points.len().to_string() + "\n";
@@ -165,6 +220,58 @@ fn import_graph_from_fmi_file(filepath :&str) -> Result<Graph, std::io::Error> {
         nodes : nodes,
         edges : edges,
         offsets: offsets,
+        layers: GraphLayers::default(),
     })
     
 }
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, io::Write};
+    use crate::{Node, Edge, layers::{GraphLayers, Corridor, WaterwayLimits, Metric}, file_interface::{print_bin_graph, import_graph_from_bin_file}};
+
+    #[test]
+    fn test_bin_graph_with_and_without_layers() {
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 1.0, lat: 0.0 }];
+        let edges = vec![Edge { src: 0, tgt: 1, dist: 111000 }, Edge { src: 1, tgt: 0, dist: 111000 }];
+        let layers = GraphLayers {
+            corridors: vec![Corridor { name: "Kiel Canal".to_string(), kind: "canal".to_string(), limits: WaterwayLimits { max_draught: Some(9.5), ..Default::default() } }],
+            edge_corridor: vec![Some(0), Some(0)],
+            node_depth: vec![12.0, f32::INFINITY],
+            edge_depth: vec![11.5, 11.5],
+            node_clearance: vec![3000.0, f32::INFINITY],
+            node_ice_months: vec![0, 0b111],
+            edge_ice_months: vec![0, 0],
+            metrics: vec![Metric::new("time", "s", vec![15000, 16000], &edges).at_speed(14.0, None)],
+        };
+        let path = std::env::temp_dir().join("ship_routing_test_layers.bin");
+        let path = path.to_str().unwrap();
+        print_bin_graph(&nodes, &edges, &layers, path);
+        let graph = import_graph_from_bin_file(path).unwrap();
+        assert_eq!(graph.layers.corridor_of_edge(1), Some(&layers.corridors[0]));
+        assert_eq!(graph.layers.node_depth, layers.node_depth);
+        assert_eq!(graph.layers.node_ice_months, layers.node_ice_months);
+        assert_eq!(graph.layers.metrics, layers.metrics);
+        assert_eq!(graph.layers.metric_index("time"), Some(0));
+
+        // graph files of older versions contain only nodes and edges
+        File::create(path).unwrap().write_all(&bincode::serialize(&(&nodes, &edges)).unwrap()).unwrap();
+        let graph = import_graph_from_bin_file(path).unwrap();
+        assert_eq!(graph.offsets, vec![0, 1, 2]);
+        assert!(graph.layers.corridors.is_empty() && graph.layers.corridor_of_edge(0).is_none());
+
+        // layers of version 4 end after the ice months, version 5 has metrics without speed
+        let version_4 = (&layers.corridors, &layers.edge_corridor, &layers.node_depth, &layers.edge_depth, &layers.node_clearance, &layers.node_ice_months, &layers.edge_ice_months);
+        let mut encoded = bincode::serialize(&(&nodes, &edges, 4u32, version_4)).unwrap();
+        File::create(path).unwrap().write_all(&encoded).unwrap();
+        let graph = import_graph_from_bin_file(path).unwrap();
+        assert_eq!((&graph.layers.node_ice_months, graph.layers.metrics.len()), (&layers.node_ice_months, 0));
+        encoded = bincode::serialize(&(&nodes, &edges, 5u32, version_4, vec![("time", "s", &layers.metrics[0].weights, layers.metrics[0].min_weight_per_m)])).unwrap();
+        File::create(path).unwrap().write_all(&encoded).unwrap();
+        let graph = import_graph_from_bin_file(path).unwrap();
+        assert_eq!(graph.layers.metrics, vec![Metric { speed: None, ..layers.metrics[0].clone() }]);
+        File::create(path).unwrap().write_all(&bincode::serialize(&(&nodes, &edges, 7u32)).unwrap()).unwrap();
+        assert!(import_graph_from_bin_file(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
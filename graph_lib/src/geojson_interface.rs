@@ -0,0 +1,336 @@
+use std::{fs::File, io::{BufReader, BufWriter, Read, Write}, fmt};
+
+use geojson::{Feature, FeatureCollection, FeatureWriter, Geometry, Value};
+use serde::de::{DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor, IgnoredAny};
+
+use crate::{Coordinates, layers::{Corridor, Waterway, WaterwayLimits}, zones::PenaltyZone};
+
+/**
+ * Reads all closed rings of a GeoJSON file as coastlines.
+ * Accepts FeatureCollections, single Features and bare geometries. Rings are taken from Polygons and MultiPolygons,
+ * closed LineStrings and MultiLineStrings are accepted as well to read files of older versions.
+ * Features are parsed one after the other while streaming through the file, so only one feature is in memory at once.
+ */
+pub fn read_coastlines_from_geojson(filepath: &str) -> Result<Vec<Vec<Vec<f32>>>, Box<dyn std::error::Error>> {
+    let reader = BufReader::new(File::open(filepath)?);
+    let rings = read_coastlines(reader)?;
+    if rings.skipped > 0 {
+        println!("Skipped {} lines in {}, which are no closed rings", rings.skipped, filepath);
+    }
+    Ok(rings.coastlines)
+}
+
+/**
+ * Writes coastlines as FeatureCollection of Polygons into the file; every coastline is one feature.
+ */
+pub fn write_coastlines_to_geojson(filepath: &str, coastlines: &[Vec<Vec<f32>>]) -> Result<(), Box<dyn std::error::Error>> {
+    let writer = BufWriter::new(File::create(filepath)?);
+    write_coastlines(writer, coastlines)
+}
+
+/**
+ * Writes lines as FeatureCollection of LineStrings with their label as property "problem", e.g. to inspect broken coastlines.
+ */
+pub fn write_lines_to_geojson(filepath: &str, lines: &[(&str, Vec<Vec<f32>>)]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut feature_writer = FeatureWriter::from_writer(BufWriter::new(File::create(filepath)?));
+    for (label, line) in lines {
+        let mut properties = serde_json::Map::new();
+        properties.insert("problem".to_string(), serde_json::Value::from(*label));
+        feature_writer.write_feature(&Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::LineString(line.iter().map(|coordinates| to_position(coordinates)).collect()))),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        })?;
+    }
+    feature_writer.finish()?;
+    feature_writer.flush()?;
+    Ok(())
+}
+
+/**
+ * names of the properties of the limits of waterways, like the OSM tags
+ */
+const LIMIT_PROPERTIES: [&str; 5] = ["width", "maxdraught", "maxbeam", "maxlength", "maxheight"];
+
+/**
+ * Writes waterways as FeatureCollection of LineStrings with the properties name, waterway (the kind) and their limits in m.
+ */
+pub fn write_waterways_to_geojson(filepath: &str, waterways: &[Waterway]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut feature_writer = FeatureWriter::from_writer(BufWriter::new(File::create(filepath)?));
+    for waterway in waterways {
+        let limits = &waterway.corridor.limits;
+        let mut properties = serde_json::Map::new();
+        properties.insert("name".to_string(), serde_json::Value::from(waterway.corridor.name.as_str()));
+        properties.insert("waterway".to_string(), serde_json::Value::from(waterway.corridor.kind.as_str()));
+        let values = [limits.width, limits.max_draught, limits.max_beam, limits.max_length, limits.max_height];
+        for (key, value) in LIMIT_PROPERTIES.iter().zip(values) {
+            if let Some(value) = value {
+                properties.insert(key.to_string(), serde_json::Value::from(value as f64));
+            }
+        }
+        feature_writer.write_feature(&Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::LineString(waterway.course.iter().map(|coordinates| to_position(coordinates)).collect()))),
+            id: None,
+            properties: Some(properties),
+            foreign_members: None,
+        })?;
+    }
+    feature_writer.finish()?;
+    feature_writer.flush()?;
+    Ok(())
+}
+
+/**
+ * Reads the LineStrings of a FeatureCollection as waterways, the format of write_waterways_to_geojson.
+ */
+pub fn read_waterways_from_geojson(filepath: &str) -> Result<Vec<Waterway>, Box<dyn std::error::Error>> {
+    read_waterways(BufReader::new(File::open(filepath)?))
+}
+
+fn read_waterways<R: Read>(reader: R) -> Result<Vec<Waterway>, Box<dyn std::error::Error>> {
+    let collection: FeatureCollection = serde_json::from_reader(reader)?;
+    Ok(collection.features.into_iter().filter_map(|feature| {
+        let course = match &feature.geometry.as_ref()?.value {
+            Value::LineString(line) => line.iter().map(|position| vec![position[0] as f32, position[1] as f32]).collect(),
+            _ => return None,
+        };
+        let string_property = |key: &str| feature.property(key).and_then(|value| value.as_str()).unwrap_or_default().to_string();
+        let [width, max_draught, max_beam, max_length, max_height] = LIMIT_PROPERTIES
+            .map(|key| feature.property(key).and_then(|value| value.as_f64()).map(|value| value as f32));
+        Some(Waterway {
+            corridor: Corridor {
+                name: string_property("name"),
+                kind: string_property("waterway"),
+                limits: WaterwayLimits { width, max_draught, max_beam, max_length, max_height },
+            },
+            course,
+        })
+    }).collect())
+}
+
+/**
+ * Reads the Polygons and MultiPolygons of a FeatureCollection as penalty zones with the properties name, multiplier (default 1)
+ * and penalty in m (default 0); every polygon is one zone, holes are ignored.
+ */
+pub fn read_zones_from_geojson(filepath: &str) -> Result<Vec<PenaltyZone>, Box<dyn std::error::Error>> {
+    read_zones(BufReader::new(File::open(filepath)?))
+}
+
+fn read_zones<R: Read>(reader: R) -> Result<Vec<PenaltyZone>, Box<dyn std::error::Error>> {
+    let collection: FeatureCollection = serde_json::from_reader(reader)?;
+    Ok(collection.features.iter().flat_map(|feature| {
+        let polygons = match feature.geometry.as_ref().map(|geometry| &geometry.value) {
+            Some(Value::Polygon(polygon)) => vec![polygon],
+            Some(Value::MultiPolygon(polygons)) => polygons.iter().collect(),
+            _ => vec![],
+        };
+        let name = feature.property("name").and_then(|value| value.as_str()).unwrap_or_default();
+        let multiplier = feature.property("multiplier").and_then(|value| value.as_f64()).unwrap_or(1.0) as f32;
+        let penalty = feature.property("penalty").and_then(|value| value.as_f64()).unwrap_or(0.0) as u32;
+        polygons.into_iter().filter_map(move |polygon| {
+            let ring: Vec<Coordinates> = polygon.first()?.iter().map(|position| Coordinates(position[0] as f32, position[1] as f32)).collect();
+            Some(PenaltyZone::new(name, &ring, multiplier, penalty))
+        })
+    }).collect())
+}
+
+fn read_coastlines<R: Read>(reader: R) -> Result<RingCollector, serde_json::Error> {
+    let mut rings = RingCollector { coastlines: vec![], skipped: 0 };
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    deserializer.deserialize_map(GeoJsonVisitor { rings: &mut rings })?;
+    deserializer.end()?;
+    Ok(rings)
+}
+
+fn write_coastlines<W: Write>(writer: W, coastlines: &[Vec<Vec<f32>>]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut feature_writer = FeatureWriter::from_writer(writer);
+    for coastline in coastlines {
+        let ring = coastline.iter().map(|coordinates| to_position(coordinates)).collect();
+        feature_writer.write_feature(&Feature {
+            bbox: None,
+            geometry: Some(Geometry::new(Value::Polygon(vec![ring]))),
+            id: None,
+            properties: None,
+            foreign_members: None,
+        })?;
+    }
+    feature_writer.finish()?;
+    feature_writer.flush()?;
+    Ok(())
+}
+
+/**
+ * rounded to 7 decimals like in OSM, which is the precision of the f32 values
+ */
+fn to_position(coordinates: &[f32]) -> Vec<f64> {
+    coordinates.iter().map(|value| (*value as f64 * 1e7).round() / 1e7).collect()
+}
+
+/**
+ * collects the closed rings and counts the skipped lines, which are not closed
+ */
+struct RingCollector {
+    coastlines: Vec<Vec<Vec<f32>>>,
+    skipped: usize,
+}
+
+impl RingCollector {
+    fn add_geometry(&mut self, value: &Value) {
+        match value {
+            Value::Polygon(rings) => rings.iter().for_each(|ring| self.add_ring(ring)),
+            Value::MultiPolygon(polygons) => polygons.iter().flatten().for_each(|ring| self.add_ring(ring)),
+            Value::LineString(line) => self.add_ring(line),
+            Value::MultiLineString(lines) => lines.iter().for_each(|line| self.add_ring(line)),
+            Value::GeometryCollection(geometries) => geometries.iter().for_each(|geometry| self.add_geometry(&geometry.value)),
+            Value::Point(_) | Value::MultiPoint(_) => (),
+        }
+    }
+
+    fn add_ring(&mut self, ring: &[Vec<f64>]) {
+        if ring.len() < 4 || ring.first() != ring.last() {
+            self.skipped += 1;
+            return;
+        }
+        self.coastlines.push(ring.iter().map(|position| vec![position[0] as f32, position[1] as f32]).collect());
+    }
+}
+
+/**
+ * Visits the top level object. Features are handed to the collector one by one.
+ * For bare geometries, type and coordinates can appear in any order, so they are put together at the end.
+ */
+struct GeoJsonVisitor<'a> {
+    rings: &'a mut RingCollector,
+}
+
+impl<'de> Visitor<'de> for GeoJsonVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a GeoJSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut geometry_object = serde_json::Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "features" => map.next_value_seed(FeaturesSeed { rings: self.rings })?,
+                "geometry" => {
+                    if let Some(geometry) = map.next_value::<Option<Geometry>>()? {
+                        self.rings.add_geometry(&geometry.value);
+                    }
+                },
+                "type" | "coordinates" | "geometries" => {
+                    geometry_object.insert(key, map.next_value()?);
+                },
+                _ => { map.next_value::<IgnoredAny>()?; },
+            }
+        }
+        if geometry_object.contains_key("coordinates") || geometry_object.contains_key("geometries") {
+            let geometry = Geometry::from_json_object(geometry_object).map_err(serde::de::Error::custom)?;
+            self.rings.add_geometry(&geometry.value);
+        }
+        Ok(())
+    }
+}
+
+struct FeaturesSeed<'a> {
+    rings: &'a mut RingCollector,
+}
+
+impl<'de> DeserializeSeed<'de> for FeaturesSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FeaturesSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of GeoJSON features")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(feature) = seq.next_element::<Feature>()? {
+            if let Some(geometry) = feature.geometry {
+                self.rings.add_geometry(&geometry.value);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{geojson_interface::{read_coastlines, write_coastlines, read_waterways, read_zones}, layers::WaterwayLimits};
+
+    #[test]
+    fn test_read_feature_collection() {
+        let geojson = r#"{
+            "type": "FeatureCollection",
+            "bbox": [-10.0, -10.0, 10.0, 10.0],
+            "features": [
+                {"type": "Feature", "properties": {"name": "a", "tags": [1, 2]},
+                 "geometry": {"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 0]], [[0.2, 0.1], [0.8, 0.1], [0.8, 0.7], [0.2, 0.1]]]}},
+                {"geometry": {"coordinates": [[[[2, 2], [3, 2], [3, 3], [2, 2]]], [[[4, 4], [5, 4], [5, 5], [4, 4]]]], "type": "MultiPolygon"},
+                 "type": "Feature", "properties": null}
+            ]
+        }"#;
+        let rings = read_coastlines(geojson.as_bytes()).unwrap();
+        assert_eq!(rings.coastlines.len(), 4);
+        assert_eq!(rings.skipped, 0);
+        assert_eq!(rings.coastlines[1][2], vec![0.8, 0.7]);
+        assert_eq!(rings.coastlines[3][0], vec![4.0, 4.0]);
+    }
+
+    #[test]
+    fn test_read_old_multilinestring() {
+        let geojson = r#"{"coordinates":[[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]], [[5.0, 5.0], [6.0, 6.0]]],"type":"MultiLineString"}"#;
+        let rings = read_coastlines(geojson.as_bytes()).unwrap();
+        assert_eq!(rings.coastlines.len(), 1);
+        assert_eq!(rings.skipped, 1);
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let coastlines = vec![vec![vec![12.3, -45.678_9], vec![13.0, -45.0], vec![12.5, -44.0], vec![12.3, -45.678_9]]];
+        let mut buffer: Vec<u8> = vec![];
+        write_coastlines(&mut buffer, &coastlines).unwrap();
+        let rings = read_coastlines(buffer.as_slice()).unwrap();
+        assert_eq!(rings.coastlines, coastlines);
+    }
+
+    #[test]
+    fn test_read_waterways() {
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"name": "Kiel Canal", "waterway": "canal", "maxdraught": 9.5, "maxheight": 40},
+             "geometry": {"type": "LineString", "coordinates": [[9.14, 53.89], [9.6, 54.1], [10.14, 54.36]]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [0, 0]}}
+        ]}"#;
+        let waterways = read_waterways(geojson.as_bytes()).unwrap();
+        assert_eq!(waterways.len(), 1);
+        assert_eq!(waterways[0].corridor.kind, "canal");
+        assert_eq!(waterways[0].corridor.limits, WaterwayLimits { max_draught: Some(9.5), max_height: Some(40.0), ..Default::default() });
+        assert_eq!(waterways[0].course[2], vec![10.14, 54.36]);
+    }
+
+    #[test]
+    fn test_read_zones() {
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {"name": "High Risk Area", "multiplier": 1.5},
+             "geometry": {"type": "MultiPolygon", "coordinates": [[[[43, 11], [60, 11], [60, 20], [43, 11]]], [[[43, 0], [60, 0], [60, 10], [43, 0]]]]}},
+            {"type": "Feature", "properties": {"name": "North Sea ECA", "penalty": 20000},
+             "geometry": {"type": "Polygon", "coordinates": [[[-5, 48], [10, 48], [10, 62], [-5, 62], [-5, 48]]]}}
+        ]}"#;
+        let zones = read_zones(geojson.as_bytes()).unwrap();
+        assert_eq!(zones.len(), 3);
+        assert_eq!((zones[1].name.as_str(), zones[1].multiplier, zones[1].penalty), ("High Risk Area", 1.5, 0));
+        assert_eq!((zones[2].multiplier, zones[2].penalty), (1.0, 20000));
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt::Display;
+use crate::{Graph, Node, Coordinates};
+
+pub const MONTH_NAMES: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"
+];
+
+/**
+ * seas with seasonal ice and their bounding box [min_lon, min_lat, max_lon, max_lat], to name the ice zones;
+ * the first matching sea is taken
+ */
+const ICE_SEAS: [(&str, [f32; 4]); 16] = [
+    ("Gulf of Bothnia", [17.0, 60.0, 26.0, 66.0]),
+    ("White Sea", [32.0, 63.5, 44.5, 68.7]),
+    ("Barents Sea", [15.0, 68.0, 60.0, 82.0]),
+    ("Kara Sea", [60.0, 68.0, 100.0, 82.0]),
+    ("Laptev Sea", [100.0, 70.0, 140.0, 82.0]),
+    ("East Siberian Sea", [140.0, 68.0, 180.0, 80.0]),
+    ("Chukchi Sea", [-180.0, 65.5, -156.0, 78.0]),
+    ("Beaufort Sea", [-156.0, 68.0, -123.0, 78.0]),
+    ("Canadian Arctic Archipelago", [-123.0, 67.0, -75.0, 83.0]),
+    ("Baffin Bay", [-75.0, 60.0, -50.0, 79.0]),
+    ("Hudson Bay", [-95.0, 51.0, -75.0, 66.0]),
+    ("Greenland Sea", [-30.0, 65.0, 15.0, 82.0]),
+    ("Bering Sea", [162.0, 52.0, 180.0, 65.5]),
+    ("Bering Sea", [-180.0, 52.0, -157.0, 65.5]),
+    ("Sea of Okhotsk", [135.0, 43.0, 162.0, 62.0]),
+    ("Southern Ocean", [-180.0, -90.0, 180.0, -55.0]),
+];
+
+/**
+ * bit of the month (1 to 12) in the ice months of nodes and edges
+ */
+#[inline]
+pub fn month_bit(month: u32) -> u16 {
+    1 << (month - 1)
+}
+
+/**
+ * month 1 to 12 of a departure, given as month or as date like 2024-07-15
+ */
+pub fn parse_departure_month(departure: &str) -> Option<u32> {
+    let departure = departure.trim();
+    let month = match departure.split_once('-') {
+        Some((_, rest)) => rest.split('-').next()?.parse::<u32>().ok()?,
+        None => departure.parse::<u32>().ok()?,
+    };
+    (1..=12).contains(&month).then_some(month)
+}
+
+/**
+ * day of a departure date like 2024-07-15 in days since 1970-01-01, None for a month only
+ */
+pub fn parse_departure_day(departure: &str) -> Option<i64> {
+    let mut parts = departure.trim().splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // days from the civil date, with years starting in March
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146_097 + day_of_era - 719_468)
+}
+
+/**
+ * name of the sea with seasonal ice at the coordinates, if known
+ */
+pub fn sea_name(coordinates: &Coordinates) -> Option<&'static str> {
+    ICE_SEAS.iter()
+        .find(|(_, [min_lon, min_lat, max_lon, max_lat])| {
+            coordinates.0 >= *min_lon && coordinates.0 <= *max_lon && coordinates.1 >= *min_lat && coordinates.1 <= *max_lat
+        })
+        .map(|(name, _)| *name)
+}
+
+/**
+ * part of a path over consecutive edges, which are covered by ice
+ */
+#[derive(Debug)]
+pub struct IceZone {
+    pub start: Coordinates,
+    pub end: Coordinates,
+    /// in m
+    pub length: u32,
+}
+
+impl Display for IceZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let center = Coordinates((self.start.0 + self.end.0) / 2.0, (self.start.1 + self.end.1) / 2.0);
+        match sea_name(&self.start).or(sea_name(&self.end)) {
+            Some(name) => write!(f, "{} ({:.0} km)", name, self.length as f32 / 1000.0),
+            None => write!(f, "ice around {} ({:.0} km)", center, self.length as f32 / 1000.0),
+        }
+    }
+}
+
+/**
+ * true if the node is covered by ice in the month (1 to 12); nodes without ice data are free
+ */
+pub fn node_in_ice(graph: &Graph, node_id: usize, month: u32) -> bool {
+    graph.layers.node_ice_months.get(node_id).is_some_and(|months| months & month_bit(month) != 0)
+}
+
+/**
+ * true if the edge is covered by ice in the month (1 to 12); edges without ice data are free
+ */
+pub fn edge_in_ice(graph: &Graph, edge_id: usize, month: u32) -> bool {
+    graph.layers.edge_ice_months.get(edge_id).is_some_and(|months| months & month_bit(month) != 0)
+}
+
+/**
+ * zones of consecutive edges of the path, which are covered by ice in the month (1 to 12)
+ */
+pub fn ice_zones_on_path(graph: &Graph, path: &[Node], month: u32) -> Vec<IceZone> {
+    let mut zones: Vec<IceZone> = vec![];
+    let mut in_zone = false;
+    for nodes in path.windows(2) {
+        match graph.edge_between(nodes[0].id, nodes[1].id) {
+            Some((edge_id, edge)) if edge_in_ice(graph, edge_id, month) => {
+                let end = Coordinates(nodes[1].lon, nodes[1].lat);
+                match zones.last_mut() {
+                    Some(zone) if in_zone => {
+                        zone.end = end;
+                        zone.length += edge.dist;
+                    },
+                    _ => zones.push(IceZone { start: Coordinates(nodes[0].lon, nodes[0].lat), end, length: edge.dist }),
+                }
+                in_zone = true;
+            },
+            _ => in_zone = false,
+        }
+    }
+    zones
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Graph, Node, Edge, layers::GraphLayers, query::QueryOptions, ice::{parse_departure_month, parse_departure_day, ice_zones_on_path, month_bit}};
+
+    #[test]
+    fn test_ice_zones() {
+        assert_eq!(parse_departure_month("2024-07-15"), Some(7));
+        assert_eq!(parse_departure_month("3"), Some(3));
+        assert_eq!(parse_departure_month("13"), None);
+        assert_eq!(parse_departure_day("1970-01-01"), Some(0));
+        assert_eq!(parse_departure_day("2024-03-01"), Some(19_783));
+        assert_eq!(parse_departure_day("3"), None);
+
+        // a way through the Kara Sea from node 0 to 3, which is covered by ice in February between node 1 and 3
+        let nodes = vec![
+            Node { id: 0, lon: 60.0, lat: 72.0 }, Node { id: 1, lon: 65.0, lat: 74.0 },
+            Node { id: 2, lon: 70.0, lat: 75.0 }, Node { id: 3, lon: 75.0, lat: 75.0 },
+        ];
+        let edges = vec![
+            Edge { src: 0, tgt: 1, dist: 250000 }, Edge { src: 1, tgt: 0, dist: 250000 }, Edge { src: 1, tgt: 2, dist: 200000 },
+            Edge { src: 2, tgt: 1, dist: 200000 }, Edge { src: 2, tgt: 3, dist: 150000 }, Edge { src: 3, tgt: 2, dist: 150000 },
+        ];
+        let ice = month_bit(1) | month_bit(2);
+        let layers = GraphLayers { edge_ice_months: vec![0, 0, ice, ice, ice, ice], ..Default::default() };
+        let graph = Graph::with_layers(nodes, edges, layers);
+
+        let zones = ice_zones_on_path(&graph, &graph.nodes, 2);
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0].length, 350000);
+        assert_eq!(zones[0].to_string(), "Kara Sea (350 km)");
+        assert!(ice_zones_on_path(&graph, &graph.nodes, 7).is_empty());
+
+        let options = QueryOptions::default().with_month(2);
+        assert!(options.edge_allowed(&graph, 0));
+        assert!(!options.edge_allowed(&graph, 2));
+        assert!(options.avoided_ice(&graph, &graph.nodes).unwrap().contains("Kara Sea"));
+        assert!(QueryOptions::default().with_month(8).edge_allowed(&graph, 2));
+    }
+}
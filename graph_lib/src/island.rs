@@ -1,7 +1,8 @@
-use std::{collections::HashSet, time::SystemTime, fs};
+use std::{collections::HashSet, time::SystemTime, fs::File, io::{BufReader, BufWriter}};
 use lombok::Getter;
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
-use crate::{Coordinates, random_point::point_in_polygon_test};
+use serde::{Serialize, Deserialize};
+use crate::{Coordinates, lon_difference, wrap_lon, random_point::point_in_polygon_test, geojson_interface::read_coastlines_from_geojson};
 
 pub const MOST_SOUTHERN_LAT_IN_SEA: f32 = -78.02;
 
@@ -10,17 +11,28 @@ pub const MOST_SOUTHERN_LAT_IN_SEA: f32 = -78.02;
 pub const GRID_DIVISIONS: [usize; 36] = [3,9,16,22,28,33,39,44,49,53,57,61,64,67,69,70,71,72,72,71,70,69,67,64,61,57,53,49,44,39,33,28,22,16,9,3];
 const GRID_DISTANCE: f32 = 180.0 / GRID_DIVISIONS.len() as f32;
 
+/// files of the coastlines, sorted from big to small
+const COASTLINE_FILES: [&str; 4] = ["continents", "big_islands", "islands", "small_islands"];
+
+/**
+ * version of the island index file, increased with every change of Island or GridCell
+ */
+const ISLAND_INDEX_VERSION: u32 = 3;
+
 /**
- * enum to define if a grid cell is completely filled with water, completely filled with land or contains water and land
+ * enum to define if a grid cell is completely filled with water, completely filled with land or contains water and land;
+ * islands are given by their index in the islands of the IslandIndex.
+ * A point is on land, if it is inside of an odd number of rings, so ISLANDS contains also rings, which cover the cell completely,
+ * e.g. a continent around a lake
  */
-#[derive(Clone)]
-pub enum GridCell<'a> {
+#[derive(Clone, Serialize, Deserialize)]
+pub enum GridCell {
     WATER,
-    LAND(&'a Island),
-    ISLANDS(Vec<&'a Island>),
+    LAND(usize),
+    ISLANDS(Vec<usize>),
 }
 
-impl std::fmt::Debug for GridCell<'_> {
+impl std::fmt::Debug for GridCell {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             GridCell::LAND(_) => write!(f, "LAND"),
@@ -30,19 +42,194 @@ impl std::fmt::Debug for GridCell<'_> {
     }
 }
 
+impl GridCell {
+    /**
+     * adds a ring, which crosses the cell
+     */
+    fn with_touching(self, island_index: usize) -> GridCell {
+        match self {
+            GridCell::WATER => GridCell::ISLANDS(vec![island_index]),
+            GridCell::LAND(covering) => GridCell::ISLANDS(vec![covering, island_index]),
+            GridCell::ISLANDS(mut islands) => {
+                islands.push(island_index);
+                GridCell::ISLANDS(islands)
+            },
+        }
+    }
+
+    /**
+     * adds a ring, which covers the cell completely; inside of another ring, e.g. for an island in a lake, both rings have to be checked
+     */
+    fn with_covering(self, island_index: usize) -> GridCell {
+        match self {
+            GridCell::WATER => GridCell::LAND(island_index),
+            cell => cell.with_touching(island_index),
+        }
+    }
+}
+
 #[inline]
 pub fn grid_cell_of_coordinate(lon: f32, lat: f32) -> [usize; 2] {
     // northpole is row 0, southpole is last row
     // not using -90 and -180 to avoid index out of bounds in our grid
     let grid_row = (-(lat - 89.999) * GRID_DIVISIONS.len() as f32 / 180.0) as usize;
-    let cell_in_row = (-(lon - 179.999) * GRID_DIVISIONS[grid_row] as f32 / 360.0) as usize;
+    let cell_in_row = (-(wrap_lon(lon) - 179.999) * GRID_DIVISIONS[grid_row] as f32 / 360.0) as usize;
     return [grid_row, cell_in_row];
 }
 
+/**
+ * true for edges along the antimeridian, which close rings split at the antimeridian, like the edges of antarctica around the southpole;
+ * they are no real coastline
+ */
+#[inline]
+pub fn is_border_edge(start: &Coordinates, end: &Coordinates) -> bool {
+    start.0.abs() == 180.0 && end.0.abs() == 180.0
+}
+
+/**
+ * Splits a ring, which crosses the antimeridian, into rings east and west of it, which are closed along the antimeridian.
+ * So longitudes are continuous within every ring, which the grid and the point in polygon test expect.
+ * A ring around a pole crosses the antimeridian once; it is closed over the pole first, like antarctica in the OSM data.
+ * Rings are expected counterclockwise around land and clockwise around holes. Rings without crossing are returned unchanged.
+ */
+pub fn split_at_antimeridian(ring: &[Vec<f32>]) -> Vec<Vec<Vec<f32>>> {
+    let crossing = |start: &[f32], end: &[f32]| (start[0] - end[0]).abs() > 180.0
+        && !is_border_edge(&Coordinates(start[0], start[1]), &Coordinates(end[0], end[1]));
+    let mut points: Vec<Vec<f32>> = ring.to_vec();
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    let n_crossings = (0..points.len()).filter(|i| crossing(&points[*i], &points[(i + 1) % points.len()])).count();
+    if n_crossings == 0 {
+        return vec![ring.to_vec()];
+    }
+
+    if n_crossings % 2 == 1 {
+        // going west around the pole has the land of the ring on the left, so in the south
+        let lons = unwrapped_lons(&points);
+        let going_west = lons[points.len()] < lons[0];
+        let pole = if going_west { -90.0 } else { 90.0 };
+        let i = (0..points.len()).find(|i| crossing(&points[*i], &points[(i + 1) % points.len()])).unwrap();
+        let (start, end) = (&points[i], &points[(i + 1) % points.len()]);
+        let lat = lat_at_antimeridian(start, end);
+        let (start_border, end_border) = (180.0f32.copysign(start[0]), 180.0f32.copysign(end[0]));
+        let mut closing = vec![vec![start_border, lat]];
+        closing.extend(points_along_antimeridian(start_border, lat, pole));
+        closing.push(vec![start_border, pole]);
+        closing.push(vec![end_border, pole]);
+        closing.extend(points_along_antimeridian(end_border, pole, lat));
+        closing.push(vec![end_border, lat]);
+        points.splice(i + 1..i + 1, closing);
+        if n_crossings == 1 {
+            points.push(points[0].clone());
+            return vec![points];
+        }
+    }
+
+    // chains between the crossings, which start and end at the antimeridian
+    let n = points.len();
+    let first = (0..n).find(|i| crossing(&points[*i], &points[(i + 1) % n])).unwrap();
+    let entry = |i: usize| {
+        let (start, end) = (&points[i], &points[(i + 1) % n]);
+        vec![180.0f32.copysign(end[0]), lat_at_antimeridian(start, end)]
+    };
+    let mut chains: Vec<Vec<Vec<f32>>> = vec![];
+    let mut chain = vec![entry(first)];
+    for k in 0..n {
+        let i = (first + 1 + k) % n;
+        if chain.last() != Some(&points[i]) {
+            chain.push(points[i].clone());
+        }
+        if crossing(&points[i], &points[(i + 1) % n]) {
+            let exit = vec![180.0f32.copysign(points[i][0]), lat_at_antimeridian(&points[i], &points[(i + 1) % n])];
+            if chain.last() != Some(&exit) {
+                chain.push(exit);
+            }
+            chains.push(std::mem::take(&mut chain));
+            chain = vec![entry(i)];
+        }
+    }
+
+    // counterclockwise rings follow the eastern border of the map northwards and the western one southwards
+    let lons = unwrapped_lons(&points);
+    let counterclockwise = (0..n).map(|i| lons[i] * points[(i + 1) % n][1] - lons[i + 1] * points[i][1]).sum::<f32>() > 0.0;
+    let next_chain = |end: &Vec<f32>| -> Option<usize> {
+        let northwards = (end[0] > 0.0) == counterclockwise;
+        chains.iter().enumerate()
+            .filter(|(_, chain)| chain[0][0] == end[0] && if northwards { chain[0][1] >= end[1] } else { chain[0][1] <= end[1] })
+            .min_by(|(_, a), (_, b)| (a[0][1] - end[1]).abs().total_cmp(&(b[0][1] - end[1]).abs()))
+            .map(|(j, _)| j)
+    };
+
+    let mut used = vec![false; chains.len()];
+    let mut rings = vec![];
+    for first_chain in 0..chains.len() {
+        if used[first_chain] {
+            continue;
+        }
+        used[first_chain] = true;
+        let mut ring = chains[first_chain].clone();
+        loop {
+            let end = ring.last().unwrap().clone();
+            match next_chain(&end) {
+                Some(j) if !used[j] => {
+                    ring.extend(points_along_antimeridian(end[0], end[1], chains[j][0][1]));
+                    ring.extend(chains[j].iter().cloned());
+                    used[j] = true;
+                },
+                _ => {
+                    if end[0] == ring[0][0] {
+                        ring.extend(points_along_antimeridian(end[0], end[1], ring[0][1]));
+                    }
+                    ring.push(ring[0].clone());
+                    break;
+                },
+            }
+        }
+        rings.push(ring);
+    }
+    rings
+}
+
+/**
+ * longitudes of the ring, which continue over the antimeridian instead of jumping, e.g. 179, 181 instead of 179, -179;
+ * the ring is given without the closing point, which is added at the end, so rings around a pole end 360° away from the start
+ */
+fn unwrapped_lons(points: &[Vec<f32>]) -> Vec<f32> {
+    let mut lons = vec![points[0][0]];
+    for i in 1..=points.len() {
+        let (start, end) = (&points[i - 1], &points[i % points.len()]);
+        let step = if is_border_edge(&Coordinates(start[0], start[1]), &Coordinates(end[0], end[1])) {
+            end[0] - start[0]
+        } else {
+            lon_difference(start[0], end[0])
+        };
+        lons.push(lons[i - 1] + step);
+    }
+    lons
+}
+
+/**
+ * latitude, where the edge from start to end crosses the antimeridian
+ */
+fn lat_at_antimeridian(start: &[f32], end: &[f32]) -> f32 {
+    let to_border = 180.0f32.copysign(start[0]) - start[0];
+    start[1] + (end[1] - start[1]) * to_border / lon_difference(start[0], end[0])
+}
+
+/**
+ * points every degree between the latitudes on the antimeridian, without the ends;
+ * the grid only knows the cells with points of a coastline, so the closing edges need points like real coastlines
+ */
+fn points_along_antimeridian(border: f32, from_lat: f32, to_lat: f32) -> Vec<Vec<f32>> {
+    let n_steps = (to_lat - from_lat).abs().ceil() as usize;
+    (1..n_steps).map(|step| vec![border, from_lat + (to_lat - from_lat) * step as f32 / n_steps as f32]).collect()
+}
+
 /**
  * bounding box: [[min_lon, max_lon], [min_lat, max_lat]]
  */
-#[derive(Getter)]
+#[derive(Getter, Serialize, Deserialize)]
 pub struct Island {
     coastline: Vec<Coordinates>,
     bounding_box: [[f32; 2]; 2],
@@ -135,7 +322,10 @@ impl Island {
         }
     }
 
-    pub fn add_to_grid<'a>(&'a self, island_grid: &mut Vec<Vec<GridCell<'a>>>) {
+    /**
+     * adds the island with its index in the islands to all grid cells, which it touches or covers
+     */
+    pub fn add_to_grid(&self, island_index: usize, island_grid: &mut [Vec<GridCell>]) {
         for (row_index, grid_row) in self.grid_cells_touched.iter().enumerate() {
             if grid_row.len() > 0 {
                 let (mut max, mut min) = (0 as usize, usize::MAX);
@@ -144,11 +334,8 @@ impl Island {
                     if *cell_in_row >= island_grid[row_index].len() {
                         println!("{:?} for row {}, row: {:?}, island: {:?}", island_grid, row_index, grid_row, self);
                     }
-                    match island_grid[row_index][*cell_in_row] {
-                        GridCell::WATER => island_grid[row_index][*cell_in_row] = GridCell::ISLANDS(vec![&self]),
-                        GridCell::LAND(_) => (), // occurs only for polygons in polygons, so islands in the caspian sea
-                        GridCell::ISLANDS(ref mut islands) => islands.push(&self)
-                    }
+                    let cell = std::mem::replace(&mut island_grid[row_index][*cell_in_row], GridCell::WATER);
+                    island_grid[row_index][*cell_in_row] = cell.with_touching(island_index);
                     max = usize::max(max, *cell_in_row);
                     min = usize::min(min, *cell_in_row);
                 }
@@ -156,10 +343,11 @@ impl Island {
                 // check if cells are completely in polygon...
                 for cell in min+1..max {
                     if !grid_row.contains(&cell) {
-                        let lon_center = 180.0 - cell as f32 * 360.0 / GRID_DIVISIONS[row_index] as f32;
-                        let lat_center = 90.0 - row_index as f32 * GRID_DISTANCE;
+                        let lon_center = 180.0 - (cell as f32 + 0.5) * 360.0 / GRID_DIVISIONS[row_index] as f32;
+                        let lat_center = 90.0 - (row_index as f32 + 0.5) * GRID_DISTANCE;
                         if point_in_polygon_test(lon_center, lat_center, &self) {
-                            island_grid[row_index][cell] = GridCell::LAND(&self);
+                            let covered = std::mem::replace(&mut island_grid[row_index][cell], GridCell::WATER);
+                            island_grid[row_index][cell] = covered.with_covering(island_index);
                         }
                     }
                 }
@@ -168,6 +356,87 @@ impl Island {
     }
 }
 
+/**
+ * Islands and continents with the grid of cells to find the islands of a point fast.
+ * Building it for all coastlines takes long, so it is saved in a bin file next to the GeoJSONs of the coastlines.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct IslandIndex {
+    pub islands: Vec<Island>,
+    pub grid: Vec<Vec<GridCell>>,
+}
+
+impl IslandIndex {
+    pub fn new(coastlines: &[Vec<Vec<f32>>]) -> IslandIndex {
+        let islands: Vec<Island> = coastlines.par_iter()
+            .flat_map_iter(|coastline| split_at_antimeridian(coastline))
+            .map(Island::new)
+            .collect();
+        let mut grid: Vec<Vec<GridCell>> = GRID_DIVISIONS.iter().map(|e| vec![GridCell::WATER; *e]).collect();
+        islands.iter().enumerate().for_each(|(island_index, island)| island.add_to_grid(island_index, &mut grid));
+        IslandIndex { islands, grid }
+    }
+
+    /**
+     * reads the index of the coastlines with the prefix from its bin file; if it doesn't exist or is older than the GeoJSONs,
+     * the index is built from the GeoJSONs and written into the bin file
+     */
+    pub fn of_prefix(prefix: &str) -> IslandIndex {
+        let path = island_index_path(prefix);
+        if island_index_up_to_date(prefix) {
+            match IslandIndex::read(&path) {
+                Ok(index) => return index,
+                Err(error) => println!("Unable to read island index {}, build it again: {}", path, error),
+            }
+        }
+        let index = IslandIndex::new(&read_geojsons(prefix));
+        if let Err(error) = index.write(&path) {
+            println!("Unable to write island index {}: {}", path, error);
+        }
+        index
+    }
+
+    /**
+     * true if the index of the prefix can be read or built from the GeoJSONs
+     */
+    pub fn exists(prefix: &str) -> bool {
+        let exists = |path: &str| std::fs::exists(path).unwrap_or(false);
+        exists(&island_index_path(prefix))
+            || COASTLINE_FILES.iter().all(|filename| exists(&format!("./data/geojson/{}_{}.json", prefix, filename)))
+    }
+
+    fn read(path: &str) -> Result<IslandIndex, Box<bincode::ErrorKind>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != ISLAND_INDEX_VERSION {
+            return Err(Box::new(bincode::ErrorKind::Custom(format!("island index of version {}, but expected version {}", version, ISLAND_INDEX_VERSION))));
+        }
+        bincode::deserialize_from(reader)
+    }
+
+    fn write(&self, path: &str) -> Result<(), Box<bincode::ErrorKind>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(&mut writer, &ISLAND_INDEX_VERSION)?;
+        bincode::serialize_into(writer, self)
+    }
+}
+
+fn island_index_path(prefix: &str) -> String {
+    format!("./data/geojson/{}_islands.bin", prefix)
+}
+
+/**
+ * true if the bin file of the island index is newer than all GeoJSONs of the coastlines
+ */
+fn island_index_up_to_date(prefix: &str) -> bool {
+    let modified = |path: &str| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    match modified(&island_index_path(prefix)) {
+        Some(index_modified) => COASTLINE_FILES.iter()
+            .all(|filename| modified(&format!("./data/geojson/{}_{}.json", prefix, filename)).is_some_and(|modified| modified <= index_modified)),
+        None => false,
+    }
+}
+
 fn max(v1: f32, v2: f32) -> f32 {
     if v1 > v2 {
         return v1;
@@ -182,31 +451,13 @@ fn max(v1: f32, v2: f32) -> f32 {
  * islands splitted in different files to speedup reading and writing
  */
 pub fn read_geojsons(prefix: &str) -> Vec<Vec<Vec<f32>>> {
-    let mut coastlines: Vec<Vec<Vec<f32>>> =  ["continents", "big_islands", "islands", "small_islands"]
+    let mut coastlines: Vec<Vec<Vec<f32>>> =  COASTLINE_FILES
         .par_iter()
         .map(|filename| {
             let now = SystemTime::now();
             let filepath = format!("./data/geojson/{}.json", prefix.to_owned() + "_" + filename);
-            let geojson_str = fs::read_to_string(&filepath)
-                .expect(&format!("Unable to read JSON file {}", &filepath));
-            let geojson_str = &geojson_str[18..];
-            let coastlines_part: Vec<Vec<Vec<f32>>> = geojson_str.split("[[").into_iter().map(|island_str| {
-                if island_str.len() > 5 {
-                    island_str.split('[').into_iter().map(|coordinates| {
-                        let mut coordinates_split = coordinates.split(&[',', ']', ' '][..]);
-                        let mut coordinates = vec![];
-                        while coordinates.len() < 2 {
-                            let number = coordinates_split.nth(0);
-                            if number != Some("") {
-                                coordinates.push(number.unwrap().parse::<f32>().unwrap())
-                            }
-                        }
-                        return coordinates;
-                    }).collect()
-                } else {
-                    vec![]
-                }
-            }).collect();
+            let coastlines_part: Vec<Vec<Vec<f32>>> = read_coastlines_from_geojson(&filepath)
+                .unwrap_or_else(|error| panic!("Unable to read JSON file {}: {}", &filepath, error));
             println!(
                 "Parsing {} finished after {} sek",
                 filepath,
@@ -224,4 +475,118 @@ pub fn read_geojsons(prefix: &str) -> Vec<Vec<Vec<f32>>> {
 
         coastlines.sort_by(|a, b| b.len().cmp(&a.len()));
         return coastlines;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::{island::{IslandIndex, GridCell, split_at_antimeridian, grid_cell_of_coordinate}, random_point::point_on_land_test, coast_distance::CoastDistance};
+
+    /**
+     * closed square ring with a point every degree, because the grid only knows cells with points of the coastline like real ones
+     */
+    fn square(min_lon: f32, min_lat: f32, size: usize) -> Vec<Vec<f32>> {
+        let mut ring: Vec<Vec<f32>> = vec![];
+        let max = size as f32;
+        (0..size).for_each(|i| ring.push(vec![min_lon + i as f32, min_lat]));
+        (0..size).for_each(|i| ring.push(vec![min_lon + max, min_lat + i as f32]));
+        (0..size).for_each(|i| ring.push(vec![min_lon + max - i as f32, min_lat + max]));
+        (0..=size).for_each(|i| ring.push(vec![min_lon, min_lat + max - i as f32]));
+        ring
+    }
+
+    #[test]
+    fn test_island_index_from_file() {
+        let index = IslandIndex::new(&[square(0.0, 0.0, 20)]);
+        let path = std::env::temp_dir().join("ship_routing_test_islands.bin");
+        let path = path.to_str().unwrap();
+        index.write(path).unwrap();
+        let index = IslandIndex::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(index.islands.len(), 1);
+        assert!(index.grid.iter().flatten().any(|cell| matches!(cell, GridCell::LAND(0))));
+        assert!(point_on_land_test(10.0, 10.0, &index));
+        assert!(point_on_land_test(1.0, 19.0, &index));
+        assert!(!point_on_land_test(-10.0, 10.0, &index));
+        assert!(!point_on_land_test(10.0, 25.0, &index));
+    }
+
+    #[test]
+    fn test_lake_with_island() {
+        // a continent with a lake, which has an island; the lake covers grid cells of the continent completely
+        let mut lake = square(5.0, 5.0, 20);
+        lake.reverse();
+        let index = IslandIndex::new(&[square(0.0, 0.0, 30), lake, square(12.0, 12.0, 6)]);
+
+        assert!(point_on_land_test(2.0, 2.0, &index));
+        assert!(!point_on_land_test(7.0, 7.0, &index));
+        assert!(!point_on_land_test(22.0, 22.0, &index));
+        assert!(point_on_land_test(15.0, 15.0, &index));
+        assert!(point_on_land_test(27.0, 15.0, &index));
+        assert!(!point_on_land_test(40.0, 15.0, &index));
+    }
+
+    #[test]
+    fn test_island_over_antimeridian() {
+        // land from 178°E to 178°W with a notch from 179°E to 178°W between 2° and 4°N, so it crosses the antimeridian 4 times
+        let lons = |from: f32, to: f32, lat: f32| -> Vec<Vec<f32>> {
+            let step = if to > from { 1.0 } else { -1.0 };
+            (0..=((to - from).abs() as usize)).map(|i| vec![from + step * i as f32, lat]).collect()
+        };
+        let lats = |lon: f32, from: f32, to: f32| -> Vec<Vec<f32>> {
+            let step = if to > from { 1.0 } else { -1.0 };
+            (0..=((to - from).abs() as usize)).map(|i| vec![lon, from + step * i as f32]).collect()
+        };
+        let mut ring = vec![vec![178.0, 0.0], vec![179.0, 0.0]];
+        ring.extend(lons(-179.0, -178.0, 0.0));
+        ring.extend(lats(-178.0, 1.0, 2.0));
+        ring.push(vec![-179.0, 2.0]);
+        ring.extend(lats(179.0, 2.0, 4.0));
+        ring.extend(lons(-179.0, -178.0, 4.0));
+        ring.extend(lats(-178.0, 5.0, 6.0));
+        ring.push(vec![-179.0, 6.0]);
+        ring.extend(lons(179.0, 178.0, 6.0));
+        ring.extend(lats(178.0, 5.0, 0.0));
+
+        let rings = split_at_antimeridian(&ring);
+        assert_eq!(rings.len(), 3);
+        assert!(rings.iter().all(|ring| ring.iter().all(|point| point[0] >= 178.0) || ring.iter().all(|point| point[0] <= -178.0)));
+        assert!(rings.iter().all(|ring| ring.first() == ring.last()));
+
+        let index = IslandIndex::new(&[ring]);
+        assert!(point_on_land_test(179.5, 1.0, &index));
+        assert!(point_on_land_test(178.5, 3.0, &index));
+        assert!(!point_on_land_test(179.5, 3.0, &index));
+        assert!(point_on_land_test(-179.5, 1.0, &index));
+        assert!(!point_on_land_test(-179.5, 3.0, &index));
+        assert!(point_on_land_test(-179.5, 5.0, &index));
+        assert!(!point_on_land_test(177.5, 3.0, &index));
+        assert!(!point_on_land_test(0.0, 3.0, &index));
+        // longitudes beyond 180°, e.g. of a point moved east over the antimeridian, are looked up in the cells west of it
+        assert_eq!(grid_cell_of_coordinate(180.1, 1.0), grid_cell_of_coordinate(-179.9, 1.0));
+        assert_ne!(grid_cell_of_coordinate(179.9, 1.0), grid_cell_of_coordinate(-179.9, 1.0));
+        assert!(point_on_land_test(180.5, 1.0, &index));
+        assert!(!point_on_land_test(180.5, 3.0, &index));
+
+        // the edges along the antimeridian are no coastline
+        let coast_distance = CoastDistance::new(&index.islands);
+        assert!(coast_distance.distance_to_coast(&index.islands, 179.9, 1.0, 1e6) > 50000.0);
+    }
+
+    #[test]
+    fn test_ring_around_pole() {
+        // a coastline around the southpole at 70°S, going west with the land on the left, crossing the antimeridian once
+        let mut ring: Vec<Vec<f32>> = (0..360).map(|i| vec![179.5 - i as f32, -70.0]).collect();
+        ring.push(ring[0].clone());
+
+        let rings = split_at_antimeridian(&ring);
+        assert_eq!(rings.len(), 1);
+        assert!(rings[0].iter().any(|point| point[1] == -90.0));
+
+        let index = IslandIndex::new(&[ring]);
+        assert!(point_on_land_test(0.0, -75.0, &index));
+        assert!(point_on_land_test(179.9, -75.0, &index));
+        assert!(point_on_land_test(-179.9, -75.0, &index));
+        assert!(!point_on_land_test(0.0, -65.0, &index));
+        assert!(!point_on_land_test(179.9, -65.0, &index));
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::OnceLock;
+use rayon::prelude::*;
+use crate::{Coordinates, island::IslandIndex, coast_distance::CoastDistance, random_point::point_on_land_test};
+
+/**
+ * maximal distance in m, which is searched for the closest coastline
+ */
+pub const MAX_COAST_DISTANCE: f32 = 1000000.0;
+
+/**
+ * Answers if coordinates are in water or on land, based on the islands and their grid.
+ * The index for the distance to the coast is only built with the first query, which needs it.
+ */
+pub struct LandMask {
+    island_index: IslandIndex,
+    coast_distance: OnceLock<CoastDistance>,
+}
+
+impl LandMask {
+    pub fn new(island_index: IslandIndex) -> LandMask {
+        LandMask { island_index, coast_distance: OnceLock::new() }
+    }
+
+    /**
+     * land mask of the coastlines with the prefix, None if there are neither GeoJSONs nor an island index of it
+     */
+    pub fn of_prefix(prefix: &str) -> Option<LandMask> {
+        if !IslandIndex::exists(prefix) {
+            return None;
+        }
+        Some(LandMask::new(IslandIndex::of_prefix(prefix)))
+    }
+
+    pub fn is_water(&self, coordinates: &Coordinates) -> bool {
+        !point_on_land_test(coordinates.0, coordinates.1, &self.island_index)
+    }
+
+    /**
+     * checks many points in parallel, in the order of the points
+     */
+    pub fn are_water(&self, points: &[Coordinates]) -> Vec<bool> {
+        points.par_iter().map(|point| self.is_water(point)).collect()
+    }
+
+    /**
+     * true if one of the ends is on land or the direct line between them crosses a coastline
+     */
+    pub fn segment_crosses_land(&self, start: &Coordinates, end: &Coordinates) -> bool {
+        !self.is_water(start) || !self.is_water(end)
+            || self.coast_distance().crosses_coastline(&self.island_index.islands, start, end)
+    }
+
+    /**
+     * distance in m to the closest coastline, at most MAX_COAST_DISTANCE
+     */
+    pub fn distance_to_coast(&self, coordinates: &Coordinates) -> f32 {
+        self.coast_distance().distance_to_coast(&self.island_index.islands, coordinates.0, coordinates.1, MAX_COAST_DISTANCE)
+    }
+
+    fn coast_distance(&self) -> &CoastDistance {
+        self.coast_distance.get_or_init(|| CoastDistance::new(&self.island_index.islands))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Coordinates, island::IslandIndex, land_mask::LandMask};
+
+    #[test]
+    fn test_land_mask() {
+        // island from lon 0 to 2 and lat 0 to 2 with a point every 0.1°
+        let mut square: Vec<Vec<f32>> = vec![];
+        let steps = |from: f32, to: f32| (0..20).map(move |i| from + (to - from) * i as f32 / 20.0);
+        steps(0.0, 2.0).for_each(|lon| square.push(vec![lon, 0.0]));
+        steps(0.0, 2.0).for_each(|lat| square.push(vec![2.0, lat]));
+        steps(2.0, 0.0).for_each(|lon| square.push(vec![lon, 2.0]));
+        steps(2.0, 0.0).for_each(|lat| square.push(vec![0.0, lat]));
+        square.push(vec![0.0, 0.0]);
+        let land_mask = LandMask::new(IslandIndex::new(&[square]));
+
+        assert!(!land_mask.is_water(&Coordinates(1.0, 1.0)));
+        assert_eq!(land_mask.are_water(&[Coordinates(1.0, 1.0), Coordinates(3.0, 1.0)]), vec![false, true]);
+        assert!(land_mask.segment_crosses_land(&Coordinates(-1.0, 1.0), &Coordinates(3.0, 1.0)));
+        assert!(!land_mask.segment_crosses_land(&Coordinates(-1.0, 3.0), &Coordinates(3.0, 3.0)));
+        assert!(!land_mask.segment_crosses_land(&Coordinates(179.0, 1.0), &Coordinates(-179.0, 1.0)));
+        let distance = land_mask.distance_to_coast(&Coordinates(3.0, 1.0));
+        assert!((distance - 111195.0).abs() < 500.0, "distance {}", distance);
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{Serialize, Deserialize};
+use crate::Edge;
+
+/**
+ * version of the layers in the bin file of a graph, increased with every change of GraphLayers
+ */
+pub const LAYERS_VERSION: u32 = 6;
+
+/**
+ * Additional data of a graph per node or per edge, in the order of the nodes and edges.
+ * In the bin file, the layers are saved after the nodes and edges with their version;
+ * files without layers (of older versions) get empty layers. An empty layer means the data doesn't exist for the graph.
+ */
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct GraphLayers {
+    /// canals and rivers, which are part of the graph
+    pub corridors: Vec<Corridor>,
+    /// index of the corridor of every edge, None for edges in open water
+    pub edge_corridor: Vec<Option<u32>>,
+    /// water depth in m at every node, infinity if unknown
+    pub node_depth: Vec<f32>,
+    /// minimal water depth in m along every edge, infinity if unknown
+    pub edge_depth: Vec<f32>,
+    /// distance in m of every node to the closest coastline, infinity if unknown or farther than the maximal distance checked
+    pub node_clearance: Vec<f32>,
+    /// months in which every node is covered by ice, bit 0 for January
+    pub node_ice_months: Vec<u16>,
+    /// months in which every edge is covered by ice somewhere along it, bit 0 for January
+    pub edge_ice_months: Vec<u16>,
+    /// further weights of the edges, e.g. time or fuel; the length in m of the edges is the default metric
+    pub metrics: Vec<Metric>,
+}
+
+impl GraphLayers {
+    pub fn corridor_of_edge(&self, edge_id: usize) -> Option<&Corridor> {
+        self.edge_corridor.get(edge_id).copied().flatten().map(|corridor| &self.corridors[corridor as usize])
+    }
+
+    /**
+     * layers for a subset of nodes and edges, given by their old ids in the new order
+     */
+    pub fn select(&self, node_ids: &[usize], edge_ids: &[usize]) -> GraphLayers {
+        GraphLayers {
+            corridors: self.corridors.clone(),
+            edge_corridor: select_layer(&self.edge_corridor, edge_ids),
+            node_depth: select_layer(&self.node_depth, node_ids),
+            edge_depth: select_layer(&self.edge_depth, edge_ids),
+            node_clearance: select_layer(&self.node_clearance, node_ids),
+            node_ice_months: select_layer(&self.node_ice_months, node_ids),
+            edge_ice_months: select_layer(&self.edge_ice_months, edge_ids),
+            metrics: self.metrics.iter().map(|metric| Metric { weights: select_layer(&metric.weights, edge_ids), ..metric.clone() }).collect(),
+        }
+    }
+
+    pub fn metric_index(&self, name: &str) -> Option<usize> {
+        self.metrics.iter().position(|metric| metric.name == name)
+    }
+
+    /**
+     * adds the metric or replaces the one with the same name
+     */
+    pub fn set_metric(&mut self, metric: Metric) {
+        match self.metric_index(&metric.name) {
+            Some(index) => self.metrics[index] = metric,
+            None => self.metrics.push(metric),
+        }
+    }
+}
+
+/**
+ * name of the default metric, the length of the edges in m
+ */
+pub const DISTANCE_METRIC: &str = "distance";
+
+/**
+ * named weight of every edge in parallel to the edges, e.g. the time in s at a given speed or the fuel in kg
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Metric {
+    pub name: String,
+    pub unit: String,
+    pub weights: Vec<u32>,
+    /// smallest weight per m of all edges, so the direct distance times it is a lower bound of the weight, e.g. for A*
+    pub min_weight_per_m: f32,
+    /// speed in knots and fuel consumption in tons per day, at which time and fuel are calculated, to scale them for other vessels
+    pub speed: Option<f32>,
+    pub tons_per_day: Option<f32>,
+}
+
+impl Metric {
+    pub fn new(name: &str, unit: &str, weights: Vec<u32>, edges: &[Edge]) -> Metric {
+        let min_weight_per_m = weights.iter().zip(edges)
+            .filter(|(_, edge)| edge.dist > 0)
+            .map(|(weight, edge)| *weight as f32 / edge.dist as f32)
+            .fold(f32::INFINITY, f32::min);
+        Metric {
+            name: name.to_string(), unit: unit.to_string(), weights, min_weight_per_m: if min_weight_per_m.is_finite() { min_weight_per_m } else { 0.0 },
+            speed: None, tons_per_day: None,
+        }
+    }
+
+    /**
+     * the metric calculated at the speed in knots and, for fuel, with the consumption in tons per day
+     */
+    pub fn at_speed(self, speed: f32, tons_per_day: Option<f32>) -> Metric {
+        Metric { speed: Some(speed), tons_per_day, ..self }
+    }
+
+    /**
+     * the weight with its unit; times in hours and fuel in tons
+     */
+    pub fn format(&self, weight: u32) -> String {
+        match self.unit.as_str() {
+            "s" => format!("{} {:.1} h", self.name, weight as f32 / 3600.0),
+            "kg" => format!("{} {:.1} t", self.name, weight as f32 / 1000.0),
+            unit => format!("{} {} {}", self.name, weight, unit),
+        }
+    }
+}
+
+fn select_layer<T: Copy>(layer: &[T], ids: &[usize]) -> Vec<T> {
+    if layer.is_empty() {
+        vec![]
+    } else {
+        ids.iter().map(|id| layer[*id]).collect()
+    }
+}
+
+/**
+ * limits of a canal or river for ships in m, None if unknown
+ */
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq)]
+pub struct WaterwayLimits {
+    pub width: Option<f32>,
+    pub max_draught: Option<f32>,
+    pub max_beam: Option<f32>,
+    pub max_length: Option<f32>,
+    /// clearance of bridges
+    pub max_height: Option<f32>,
+}
+
+/**
+ * navigable canal or river, which is connected to the ocean by a chain of corridor edges
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Corridor {
+    pub name: String,
+    /// value of the OSM tag waterway, e.g. canal or river
+    pub kind: String,
+    pub limits: WaterwayLimits,
+}
+
+/**
+ * canal or river with its course (lon, lat), as it is imported before it is added to a graph
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Waterway {
+    pub corridor: Corridor,
+    pub course: Vec<Vec<f32>>,
+}
@@ -1,10 +1,26 @@
 pub mod file_interface;
 pub mod random_point;
 pub mod island;
+pub mod coast_distance;
+pub mod geojson_interface;
+pub mod region;
+pub mod layers;
+pub mod query;
+pub mod land_mask;
+pub mod ice;
+pub mod area;
+pub mod closures;
+pub mod zones;
+pub mod currents;
+pub mod weather;
+pub mod voyage;
+pub mod vessel;
+pub mod esri_ascii;
 pub mod test_polygon_test;
 
 use std::{f32::consts::PI, fmt::{Display, Formatter, Debug}, collections::HashMap};
 use serde::{Serialize, Deserialize};
+use layers::GraphLayers;
 use cli_clipboard;
 
 /**
@@ -52,18 +68,54 @@ pub fn distance_between(lon1:f32, lat1:f32, lon2:f32, lat2:f32) -> f32 {
 }
 
 /**
- * undirected graph, saved in vectors of nodes, edges and offsets
+ * longitude wrapped into -180 to 180, e.g. after adding an offset to a longitude near the antimeridian
+ */
+#[inline]
+pub fn wrap_lon(lon: f32) -> f32 {
+    if (-180.0..=180.0).contains(&lon) { lon } else { (lon + 180.0).rem_euclid(360.0) - 180.0 }
+}
+
+/**
+ * difference in longitude from one point to another in -180 to 180, so the shorter way over the antimeridian is taken
+ */
+#[inline]
+pub fn lon_difference(from: f32, to: f32) -> f32 {
+    let d_lon = to - from;
+    if d_lon > 180.0 { d_lon - 360.0 } else if d_lon < -180.0 { d_lon + 360.0 } else { d_lon }
+}
+
+/**
+ * undirected graph, saved in vectors of nodes, edges and offsets; additional data per node or edge is in the layers
  */
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Graph {
     pub nodes: Vec<Node>,
     pub edges: Vec<Edge>,
     pub offsets: Vec<usize>,
+    pub layers: GraphLayers,
 }
 
 impl Graph {
+    /**
+     * builds the offsets for the given nodes and edges; expects edges sorted by src
+     */
+    pub fn new(nodes: Vec<Node>, edges: Vec<Edge>) -> Graph {
+        let mut offsets = vec![0usize; nodes.len() + 1];
+        for edge in &edges {
+            offsets[edge.src + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+        Graph { nodes, edges, offsets, layers: GraphLayers::default() }
+    }
+
+    pub fn with_layers(nodes: Vec<Node>, edges: Vec<Edge>, layers: GraphLayers) -> Graph {
+        Graph { layers, ..Graph::new(nodes, edges) }
+    }
+
     pub fn clone(&self) -> Graph {
-        Graph { nodes: self.nodes.clone(), edges: self.edges.clone(), offsets: self.offsets.clone() }
+        Graph { nodes: self.nodes.clone(), edges: self.edges.clone(), offsets: self.offsets.clone(), layers: self.layers.clone() }
     }
     
     /**
@@ -101,6 +153,18 @@ impl Graph {
         &self.edges[self.offsets[node_id]..self.offsets[node_id + 1]]
     }
 
+    /// Get all outgoing edges of a particular node with their ids, which are the index in the layers
+    pub fn get_outgoing_edges_with_ids(&self, node_id: usize) -> impl Iterator<Item = (usize, &Edge)> {
+        (self.offsets[node_id]..self.offsets[node_id + 1]).zip(self.get_outgoing_edges(node_id))
+    }
+
+    /**
+     * id and edge of the shortest edge from one node to the other, e.g. between consecutive nodes of a path
+     */
+    pub fn edge_between(&self, src: usize, tgt: usize) -> Option<(usize, &Edge)> {
+        self.get_outgoing_edges_with_ids(src).filter(|(_, edge)| edge.tgt == tgt).min_by_key(|(_, edge)| edge.dist)
+    }
+
     /**
      * generates a subgraph, which contains only the nodes with given ids
      * needed for development and testing
@@ -115,17 +179,20 @@ impl Graph {
             map_old_id_to_new_id.insert(*node_id, i);
         }
 
+        let old_node_ids = node_list.clone();
+        let mut old_edge_ids: Vec<usize> = vec![];
         let mut offset_counter = 0;
         for (new_id, old_id) in node_list.into_iter().enumerate() {
             let old_node = self.get_node(old_id);
             new_nodes.push(Node { id: new_id, lon: old_node.lon, lat: old_node.lat });
             let old_edges = self.get_outgoing_edges(old_id);
             new_edges.extend(old_edges.iter().map(|edge| Edge {src: new_id, tgt: map_old_id_to_new_id[&edge.tgt], dist: edge.dist}));
+            old_edge_ids.extend(self.offsets[old_id]..self.offsets[old_id + 1]);
             new_offsets.push(offset_counter);
             offset_counter += old_edges.len();
         }
 
-        return Graph { nodes: new_nodes, edges: new_edges, offsets: new_offsets }
+        Graph { nodes: new_nodes, edges: new_edges, offsets: new_offsets, layers: self.layers.select(&old_node_ids, &old_edge_ids) }
     }
 
     /**
@@ -0,0 +1,352 @@
+use std::sync::Arc;
+use crate::{Graph, Edge, Node, layers::DISTANCE_METRIC, ice::{MONTH_NAMES, edge_in_ice, ice_zones_on_path}, closures::Closures, zones::PenaltyZones, vessel::{VesselProfile, limits_of_corridor}};
+
+/**
+ * penalty factor, which practically excludes nodes close to the coast: they are only used if every other way is 100 times longer,
+ * e.g. in straits narrower than the clearance or in a port
+ */
+pub const EXCLUDE_PENALTY: f32 = 100.0;
+
+/**
+ * penalty factor, which lets routes keep the clearance, as long as the detour is less than 3 times the way along the coast
+ */
+pub const DEFAULT_CLEARANCE_PENALTY: f32 = 3.0;
+
+/**
+ * restrictions of a routing query; by default all edges can be used
+ */
+#[derive(Clone, Debug)]
+pub struct QueryOptions {
+    /// minimal water depth in m, i.e. draft of the vessel plus safety margin
+    pub min_depth: Option<f32>,
+    /// minimal distance to the coast in m
+    pub min_clearance: Option<f32>,
+    /// factor for the length of edges from or to nodes closer to the coast than the minimal clearance
+    pub clearance_penalty: f32,
+    /// month of departure from 1 to 12; edges covered by ice in this month are not used
+    pub month: Option<u32>,
+    /// nodes, edges and areas closed at runtime; shared with the caller, so the graph isn't copied
+    pub closures: Option<Arc<Closures>>,
+    /// zones, in which edges cost more, like piracy high risk areas or Emission Control Areas
+    pub zones: Option<Arc<PenaltyZones>>,
+    /// index of the metric in the layers of the graph, whose weights are used instead of the length of the edges
+    pub metric: Option<usize>,
+    /// vessel, for which the metric is scaled
+    pub vessel: Option<Arc<VesselProfile>>,
+    /// per corridor of the graph true if the vessel doesn't fit through it; empty without a vessel
+    pub closed_corridors: Vec<bool>,
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        QueryOptions {
+            min_depth: None, min_clearance: None, clearance_penalty: EXCLUDE_PENALTY, month: None, closures: None, zones: None, metric: None,
+            vessel: None, closed_corridors: vec![],
+        }
+    }
+}
+
+impl QueryOptions {
+    pub fn with_draft(draft: f32, safety_margin: f32) -> QueryOptions {
+        QueryOptions { min_depth: Some(draft + safety_margin), ..Default::default() }
+    }
+
+    /**
+     * keeps the clearance to the coast; nodes which are closer cost the length of their edges times the penalty factor
+     */
+    pub fn with_clearance(mut self, min_clearance: f32, penalty: f32) -> QueryOptions {
+        self.min_clearance = Some(min_clearance);
+        self.clearance_penalty = penalty.max(1.0);
+        self
+    }
+
+    /**
+     * avoids edges, which are covered by ice in the month of departure (1 to 12); the month holds for the whole voyage,
+     * even if it arrives in the next month
+     */
+    pub fn with_month(mut self, month: u32) -> QueryOptions {
+        self.month = Some(month);
+        self
+    }
+
+    /**
+     * avoids the closed nodes, edges and areas
+     */
+    pub fn with_closures(mut self, closures: Arc<Closures>) -> QueryOptions {
+        self.closures = Some(closures);
+        self
+    }
+
+    /**
+     * multiplies the length of edges in the penalty zones and adds the penalties of their borders
+     */
+    pub fn with_zones(mut self, zones: Arc<PenaltyZones>) -> QueryOptions {
+        self.zones = Some(zones);
+        self
+    }
+
+    /**
+     * restricts the edges to the vessel: its draft plus the safety margin is the minimal depth, unless a draft is set already,
+     * and canals and rivers, whose limits it exceeds, are not used; the metrics are scaled to the vessel
+     */
+    pub fn with_vessel(mut self, graph: &Graph, vessel: Arc<VesselProfile>, safety_margin: f32) -> QueryOptions {
+        self.min_depth = self.min_depth.or(Some(vessel.draft + safety_margin));
+        self.closed_corridors = graph.layers.corridors.iter().map(|corridor| !vessel.fits(&limits_of_corridor(corridor))).collect();
+        self.vessel = Some(vessel);
+        self
+    }
+
+    /**
+     * names of the canals and rivers, which are too small for the vessel
+     */
+    pub fn closed_corridor_names<'a>(&self, graph: &'a Graph) -> Vec<&'a str> {
+        graph.layers.corridors.iter().zip(&self.closed_corridors)
+            .filter(|(_, closed)| **closed)
+            .map(|(corridor, _)| corridor.name.as_str())
+            .collect()
+    }
+
+    /**
+     * optimizes the metric of the graph with the name, e.g. time or fuel; distance is the length of the edges
+     */
+    pub fn with_metric(mut self, graph: &Graph, name: &str) -> Result<QueryOptions, String> {
+        if name == DISTANCE_METRIC {
+            self.metric = None;
+            return Ok(self);
+        }
+        match graph.layers.metric_index(name) {
+            Some(index) => {
+                self.metric = Some(index);
+                Ok(self)
+            },
+            None => {
+                let names: Vec<&str> = graph.layers.metrics.iter().map(|metric| metric.name.as_str()).collect();
+                Err(format!("Unknown metric {}, the graph has {}", name, [DISTANCE_METRIC].iter().chain(&names).copied().collect::<Vec<&str>>().join(", ")))
+            },
+        }
+    }
+
+    /**
+     * the same options, but ignoring the ice
+     */
+    pub fn without_ice(&self) -> QueryOptions {
+        QueryOptions { month: None, ..self.clone() }
+    }
+
+    /**
+     * checks the layers of the graph and the closures for the edge; edges without data in a layer are allowed
+     */
+    #[inline]
+    pub fn edge_allowed(&self, graph: &Graph, edge_id: usize) -> bool {
+        let deep_enough = match self.min_depth {
+            Some(min_depth) => graph.layers.edge_depth.get(edge_id).is_none_or(|depth| *depth >= min_depth),
+            None => true,
+        };
+        deep_enough && self.month.is_none_or(|month| !edge_in_ice(graph, edge_id, month))
+            && self.closures.as_ref().is_none_or(|closures| !closures.is_edge_closed(edge_id))
+            && (self.closed_corridors.is_empty() || graph.layers.edge_corridor.get(edge_id).copied().flatten().is_none_or(|corridor| !self.closed_corridors[corridor as usize]))
+    }
+
+    /**
+     * length of the edge for the routing, None if the edge is not allowed.
+     * The penalties are the same in both directions, but the weights of a metric may not be, e.g. against a current.
+     * The multipliers of the penalty zones apply to the weights of a metric as well, the penalties of their borders are in m,
+     * so they only apply to the distance.
+     */
+    #[inline]
+    pub fn edge_cost(&self, graph: &Graph, edge_id: usize, edge: &Edge) -> Option<u32> {
+        if !self.edge_allowed(graph, edge_id) {
+            return None;
+        }
+        let weight = match self.metric {
+            Some(metric) => graph.layers.metrics[metric].weights[edge_id],
+            None => edge.dist,
+        };
+        let cost = match self.min_clearance {
+            Some(min_clearance) if self.too_close(graph, edge.src, min_clearance) || self.too_close(graph, edge.tgt, min_clearance) => {
+                (weight as f32 * self.clearance_penalty).min(u32::MAX as f32) as u32
+            },
+            _ => weight,
+        };
+        match &self.zones {
+            Some(zones) if self.metric.is_none() => Some(zones.cost(edge_id, cost)),
+            Some(zones) => Some(zones.multiplied(edge_id, cost)),
+            None => Some(cost),
+        }
+    }
+
+    #[inline]
+    fn too_close(&self, graph: &Graph, node_id: usize, min_clearance: f32) -> bool {
+        graph.layers.node_clearance.get(node_id).is_some_and(|clearance| *clearance < min_clearance)
+    }
+
+    /**
+     * true if no edge is restricted or more expensive and the length of the edges is optimized
+     */
+    pub fn is_unrestricted(&self) -> bool {
+        self.metric.is_none() && self.min_depth.is_none() && self.min_clearance.is_none() && self.month.is_none()
+            && self.closures.as_ref().is_none_or(|closures| closures.is_empty())
+            && self.zones.as_ref().is_none_or(|zones| zones.is_empty())
+            && !self.closed_corridors.contains(&true)
+    }
+
+    /**
+     * true if the graph has ice data and a month is given, so ice can be avoided
+     */
+    pub fn avoids_ice(&self, graph: &Graph) -> bool {
+        self.month.is_some() && !graph.layers.edge_ice_months.is_empty()
+    }
+
+    /**
+     * factor for the direct distance in m to get a lower bound of the costs, i.e. the heuristic of A*;
+     * penalties only make the costs higher
+     */
+    pub fn heuristic_factor(&self, graph: &Graph) -> f32 {
+        self.metric.map_or(1.0, |metric| graph.layers.metrics[metric].min_weight_per_m)
+    }
+
+    /**
+     * sum of the weights of the metric along the path with the unit, scaled to the vessel; None for the default metric
+     */
+    pub fn metric_of_path(&self, graph: &Graph, path: &[Node]) -> Option<String> {
+        let metric = &graph.layers.metrics[self.metric?];
+        let weight: u32 = path.windows(2).filter_map(|nodes| {
+            graph.get_outgoing_edges_with_ids(nodes[0].id)
+                .filter(|(_, edge)| edge.tgt == nodes[1].id)
+                .map(|(edge_id, _)| metric.weights[edge_id])
+                .min()
+        }).sum();
+        let scale = self.vessel.as_ref().map_or(1.0, |vessel| vessel.metric_scale(metric));
+        Some(metric.format((weight as f32 * scale).round() as u32))
+    }
+
+    /**
+     * true if the costs of the routing are not the lengths of the edges
+     */
+    pub fn has_penalty(&self) -> bool {
+        self.metric.is_some() || (self.min_clearance.is_some() && self.clearance_penalty > 1.0) || self.zones.as_ref().is_some_and(|zones| !zones.is_empty())
+    }
+
+    /**
+     * warning if the path passes nodes closer to the coast than the minimal clearance, e.g. in narrow straits
+     */
+    pub fn clearance_warning(&self, graph: &Graph, path: &[Node]) -> Option<String> {
+        let min_clearance = self.min_clearance?;
+        let too_close: Vec<f32> = path.iter()
+            .filter_map(|node| graph.layers.node_clearance.get(node.id))
+            .filter(|clearance| **clearance < min_clearance)
+            .copied()
+            .collect();
+        if too_close.is_empty() {
+            return None;
+        }
+        let closest = too_close.iter().copied().fold(f32::INFINITY, f32::min);
+        Some(format!(
+            "The route passes {} nodes closer than {:.0} m to the coast, the closest in {:.0} m",
+            too_close.len(), min_clearance, closest
+        ))
+    }
+
+    /**
+     * explanation of the ice zones, which the route without the ice restriction would pass in the month of departure
+     */
+    pub fn avoided_ice(&self, graph: &Graph, unrestricted_path: &[Node]) -> Option<String> {
+        let month = self.month?;
+        let zones = ice_zones_on_path(graph, unrestricted_path, month);
+        if zones.is_empty() {
+            return None;
+        }
+        let zones: Vec<String> = zones.iter().map(|zone| zone.to_string()).collect();
+        Some(format!("Avoided ice in {}: {}", MONTH_NAMES[month as usize - 1], zones.join(", ")))
+    }
+}
+
+/**
+ * length of the path in m; the sum of the shortest edges between the nodes of the path
+ */
+pub fn path_distance(graph: &Graph, path: &[Node]) -> u32 {
+    path.windows(2).map(|nodes| graph.edge_between(nodes[0].id, nodes[1].id).map_or(0, |(_, edge)| edge.dist)).sum()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use crate::{Graph, Node, Edge, Coordinates, layers::{GraphLayers, Metric, Corridor, WaterwayLimits}, query::QueryOptions, zones::{PenaltyZone, PenaltyZones}, vessel::default_vessel_profiles};
+
+    #[test]
+    fn test_edge_cost_with_clearance() {
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 0.1, lat: 0.0 }, Node { id: 2, lon: 0.2, lat: 0.0 }];
+        let edges = vec![
+            Edge { src: 0, tgt: 1, dist: 100 }, Edge { src: 1, tgt: 0, dist: 100 },
+            Edge { src: 1, tgt: 2, dist: 100 }, Edge { src: 2, tgt: 1, dist: 100 },
+        ];
+        let layers = GraphLayers { node_clearance: vec![5000.0, 5000.0, 800.0], ..Default::default() };
+        let graph = Graph::with_layers(nodes, edges, layers);
+
+        let options = QueryOptions::default().with_clearance(3704.0, 4.0);
+        assert_eq!(options.edge_cost(&graph, 0, &graph.edges[0]), Some(100));
+        assert_eq!(options.edge_cost(&graph, 2, &graph.edges[2]), Some(400));
+        assert_eq!(options.edge_cost(&graph, 3, &graph.edges[3]), Some(400));
+        assert!(options.clearance_warning(&graph, &graph.nodes[0..2]).is_none());
+        assert!(options.clearance_warning(&graph, &graph.nodes).unwrap().contains("1 nodes"));
+        assert_eq!(QueryOptions::default().edge_cost(&graph, 2, &graph.edges[2]), Some(100));
+    }
+
+    #[test]
+    fn test_edge_cost_with_metric() {
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 0.1, lat: 0.0 }];
+        let edges = vec![Edge { src: 0, tgt: 1, dist: 11000 }, Edge { src: 1, tgt: 0, dist: 11000 }];
+        // the time in s at 10 kn, against the current from 1 to 0
+        let metric = Metric::new("time", "s", vec![2138, 2500], &edges);
+        let graph = Graph::with_layers(nodes, edges, GraphLayers { metrics: vec![metric], ..Default::default() });
+
+        let options = QueryOptions::default().with_metric(&graph, "time").unwrap();
+        assert_eq!(options.edge_cost(&graph, 1, &graph.edges[1]), Some(2500));
+        assert!((options.heuristic_factor(&graph) - 2138.0 / 11000.0).abs() < 1e-6);
+        assert_eq!(options.metric_of_path(&graph, &graph.nodes).unwrap(), "time 0.6 h");
+        assert!(options.has_penalty() && !options.is_unrestricted());
+
+        let distance = QueryOptions::default().with_metric(&graph, "distance").unwrap();
+        assert_eq!(distance.edge_cost(&graph, 1, &graph.edges[1]), Some(11000));
+        assert!(distance.metric_of_path(&graph, &graph.nodes).is_none());
+
+        // the multipliers of penalty zones apply to the metric, their penalties in m only to the distance
+        let polygon = [Coordinates(-1.0, -1.0), Coordinates(1.0, -1.0), Coordinates(1.0, 1.0), Coordinates(-1.0, 1.0)];
+        let zones = Arc::new(PenaltyZones::new(&graph, vec![PenaltyZone::new("ECA", &polygon, 2.0, 0)]));
+        assert_eq!(distance.clone().with_zones(zones.clone()).edge_cost(&graph, 1, &graph.edges[1]), Some(22000));
+        assert_eq!(options.clone().with_zones(zones).edge_cost(&graph, 1, &graph.edges[1]), Some(5000));
+        let border = [Coordinates(0.05, -1.0), Coordinates(1.0, -1.0), Coordinates(1.0, 1.0), Coordinates(0.05, 1.0)];
+        let zones = Arc::new(PenaltyZones::new(&graph, vec![PenaltyZone::new("ECA", &border, 1.0, 1000)]));
+        assert_eq!(distance.with_zones(zones.clone()).edge_cost(&graph, 1, &graph.edges[1]), Some(12000));
+        assert_eq!(options.with_zones(zones).edge_cost(&graph, 1, &graph.edges[1]), Some(2500));
+        assert_eq!(QueryOptions::default().with_metric(&graph, "fuel").unwrap_err(), "Unknown metric fuel, the graph has distance, time");
+    }
+
+    #[test]
+    fn test_edge_allowed_for_vessel() {
+        // the edges between node 1 and 2 are in a canal with a draught of 12 m
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 0.1, lat: 0.0 }, Node { id: 2, lon: 0.2, lat: 0.0 }];
+        let edges = vec![
+            Edge { src: 0, tgt: 1, dist: 11000 }, Edge { src: 1, tgt: 0, dist: 11000 },
+            Edge { src: 1, tgt: 2, dist: 11000 }, Edge { src: 2, tgt: 1, dist: 11000 },
+        ];
+        let canal = Corridor { name: "Canal".to_string(), kind: "canal".to_string(), limits: WaterwayLimits { max_draught: Some(12.0), ..Default::default() } };
+        let time = Metric::new("time", "s", vec![2138; 4], &edges).at_speed(10.0, None);
+        let layers = GraphLayers { corridors: vec![canal], edge_corridor: vec![None, None, Some(0), Some(0)], metrics: vec![time], ..Default::default() };
+        let graph = Graph::with_layers(nodes, edges, layers);
+        let profiles = default_vessel_profiles();
+
+        let feeder = QueryOptions::default().with_vessel(&graph, Arc::new(profiles[0].clone()), 1.0);
+        assert_eq!(feeder.min_depth, Some(9.5));
+        assert!(feeder.edge_allowed(&graph, 2) && !feeder.is_unrestricted());
+        assert!(feeder.closed_corridor_names(&graph).is_empty());
+        // the time at the 14 kn of the feeder instead of 10 kn
+        let with_metric = feeder.with_metric(&graph, "time").unwrap();
+        assert_eq!(with_metric.metric_of_path(&graph, &graph.nodes).unwrap(), "time 0.8 h");
+
+        let suezmax = QueryOptions::with_draft(18.0, 0.0).with_vessel(&graph, Arc::new(profiles[3].clone()), 1.0);
+        assert_eq!(suezmax.min_depth, Some(18.0));
+        assert!(suezmax.edge_allowed(&graph, 0) && !suezmax.edge_allowed(&graph, 2) && !suezmax.edge_allowed(&graph, 3));
+        assert_eq!(suezmax.closed_corridor_names(&graph), vec!["Canal"]);
+    }
+}
@@ -1,18 +1,29 @@
 use rand::Rng;
-use crate::{island::{Island, grid_cell_of_coordinate, GridCell, MOST_SOUTHERN_LAT_IN_SEA}, Coordinates};
+use crate::{island::{Island, IslandIndex, grid_cell_of_coordinate, GridCell, MOST_SOUTHERN_LAT_IN_SEA}, Coordinates, wrap_lon, region::Region};
 
 /**
  * generates a random point in water, uniformly distributed
  */
 #[inline]
-pub fn random_point_in_water<R: Rng + ?Sized>(rng: &mut R, island_grid: &Vec<Vec<GridCell>>) -> (f32, f32) {
+pub fn random_point_in_water<R: Rng + ?Sized>(rng: &mut R, island_index: &IslandIndex) -> (f32, f32) {
     let mut point = random_point_on_sphere(rng);
-    while point_on_land_test(point.0, point.1, island_grid) {
+    while point_on_land_test(point.0, point.1, island_index) {
         point = random_point_on_sphere(rng);
     }
     return point;
 }
 
+/**
+ * generates a random point in water inside of the region, uniformly distributed
+ */
+pub fn random_point_in_water_in_region<R: Rng + ?Sized>(rng: &mut R, island_index: &IslandIndex, region: &Region) -> (f32, f32) {
+    let mut point = region.random_point(rng);
+    while point_on_land_test(point.0, point.1, island_index) {
+        point = region.random_point(rng);
+    }
+    point
+}
+
 
 /**
  * generates a random 3D vector, returns in lat, lon and length
@@ -59,26 +70,25 @@ pub fn random_point_on_sphere<R: Rng + ?Sized>(rng: &mut R) -> (f32, f32) {
  * According to our measurements of the coastlines with the table from https://dataverse.jpl.nasa.gov/dataset.xhtml?persistentId=hdl:2014/41271 there is a maximum error of single meters.
  * From the given point we check how many coastlines are crossed going straight north.
  * If it is even, we are in the sea. If odd, we are on land.
+ * The same holds for the number of rings around the point, so holes like lakes or inland seas are water and islands in them land.
  * Note: Antartica avoids -180 to 180 edge, so coastline goes to the southpole and around it.
  */
-pub(crate) fn point_on_land_test(lon: f32, lat: f32, island_grid: &Vec<Vec<GridCell>>) -> bool {
+pub(crate) fn point_on_land_test(lon: f32, lat: f32, island_index: &IslandIndex) -> bool {
     // no point in water is more south than -78.02
     if lat < MOST_SOUTHERN_LAT_IN_SEA {return true}
+    // the rings are split at the antimeridian, so a longitude beyond it is checked against the rings on the other side
+    let lon = wrap_lon(lon);
 
     let [grid_row, cell_in_row] = grid_cell_of_coordinate(lon, lat);
 
     // println!("(lon, lat): {},{} makes {},{}", lon, lat, grid_row, cell_in_row);
-    match island_grid[grid_row][cell_in_row] {
+    match island_index.grid[grid_row][cell_in_row] {
         GridCell::WATER => false,
         GridCell::LAND(_) => true,
-        GridCell::ISLANDS(ref islands) => {
-            for island in islands {
-                if point_in_polygon_test(lon, lat, island) {
-                    return true;
-                }
-            }
-            return false;
-        },
+        // inside of a lake the point is in two rings, on an island in the lake in three
+        GridCell::ISLANDS(ref islands) => islands.iter()
+            .filter(|island| point_in_polygon_test(lon, lat, &island_index.islands[**island]))
+            .count() % 2 == 1,
     }
 }
 
@@ -134,7 +144,7 @@ pub(crate) fn point_in_polygon_test(lon: f32, lat: f32, island: &Island) -> bool
  * checks if point with lon lat crosses edge between start and end by going north
  */
 #[inline]
-fn line_cross_check(start: &Coordinates, end: &Coordinates, lon: f32, lat: f32) -> bool {
+pub(crate) fn line_cross_check(start: &Coordinates, end: &Coordinates, lon: f32, lat: f32) -> bool {
     if (start.0 > lon) != (end.0 > lon) {
         // check if given lon of point is between start and end point of edge
         if (start.1 > lat) && (end.1 > lat) {
@@ -0,0 +1,129 @@
+use std::f32::consts::PI;
+use rand::Rng;
+use crate::{Coordinates, geojson_interface::{read_coastlines_from_geojson, write_coastlines_to_geojson}, random_point::line_cross_check};
+
+/**
+ * Area of the ocean a graph is generated for, given by a closed polygon (lon, lat).
+ * Saved next to the coastlines of a regional import as {prefix}_region.json.
+ */
+pub struct Region {
+    polygon: Vec<Coordinates>,
+    bounding_box: [[f32; 2]; 2],
+}
+
+impl Region {
+    pub fn new(polygon: Vec<Vec<f32>>) -> Region {
+        let mut polygon: Vec<Coordinates> = polygon.iter().map(Coordinates::from_vec).collect();
+        if polygon.first().map(|point| (point.0, point.1)) != polygon.last().map(|point| (point.0, point.1)) {
+            polygon.push(polygon[0]);
+        }
+        let bounding_box = polygon.iter().fold([[180.0f32, -180.0], [90.0, -90.0]], |[[min_lon, max_lon], [min_lat, max_lat]], point| {
+            [[min_lon.min(point.0), max_lon.max(point.0)], [min_lat.min(point.1), max_lat.max(point.1)]]
+        });
+        Region { polygon, bounding_box }
+    }
+
+    pub fn from_bbox(min_lon: f32, min_lat: f32, max_lon: f32, max_lat: f32) -> Region {
+        Region::new(vec![
+            vec![min_lon, min_lat], vec![max_lon, min_lat], vec![max_lon, max_lat], vec![min_lon, max_lat], vec![min_lon, min_lat]
+        ])
+    }
+
+    /**
+     * uses the first polygon of the GeoJSON file
+     */
+    pub fn from_geojson(filepath: &str) -> Result<Region, Box<dyn std::error::Error>> {
+        let polygon = read_coastlines_from_geojson(filepath)?.into_iter().next()
+            .ok_or(format!("No polygon found in {}", filepath))?;
+        Ok(Region::new(polygon))
+    }
+
+    /**
+     * reads the region of an import prefix, if the import was restricted to a region;
+     * an error if the region file exists but can't be read, instead of falling back to the whole world
+     */
+    pub fn of_import(prefix: &str) -> Result<Option<Region>, Box<dyn std::error::Error>> {
+        let filepath = format!("./data/geojson/{}_region.json", prefix);
+        if !std::path::Path::new(&filepath).exists() {
+            return Ok(None);
+        }
+        Ok(Some(Region::from_geojson(&filepath)?))
+    }
+
+    /**
+     * removes the region of an earlier import with the same prefix, the new import covers the whole world
+     */
+    pub fn remove_of_import(prefix: &str) -> std::io::Result<()> {
+        match std::fs::remove_file(format!("./data/geojson/{}_region.json", prefix)) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn write_geojson(&self, prefix: &str) -> Result<(), Box<dyn std::error::Error>> {
+        write_coastlines_to_geojson(&format!("./data/geojson/{}_region.json", prefix), &[self.get_polygon()])
+    }
+
+    pub fn get_polygon(&self) -> Vec<Vec<f32>> {
+        self.polygon.iter().map(|point| vec![point.0, point.1]).collect()
+    }
+
+    /**
+     * [[min_lon, max_lon], [min_lat, max_lat]]
+     */
+    pub fn get_bounding_box(&self) -> [[f32; 2]; 2] {
+        self.bounding_box
+    }
+
+    pub fn contains(&self, lon: f32, lat: f32) -> bool {
+        let [[min_lon, max_lon], [min_lat, max_lat]] = self.bounding_box;
+        if lon < min_lon || lon > max_lon || lat < min_lat || lat > max_lat {
+            return false;
+        }
+        self.polygon.windows(2).filter(|edge| line_cross_check(&edge[0], &edge[1], lon, lat)).count() % 2 == 1
+    }
+
+    /**
+     * share of the earth surface, to scale the number of nodes of a graph
+     */
+    pub fn area_fraction(&self) -> f32 {
+        // area on the sphere of the polygon, like in Chamberlain & Duquette: Some Algorithms for Polygons on a Sphere
+        let area: f32 = self.polygon.windows(2).map(|edge| {
+            (edge[1].0 - edge[0].0).to_radians() * (edge[0].1.to_radians().sin() + edge[1].1.to_radians().sin())
+        }).sum();
+        (area.abs() / 2.0 / (4.0 * PI)).min(1.0)
+    }
+
+    /**
+     * uniformly distributed random point on the sphere in the region
+     */
+    pub fn random_point<R: Rng + ?Sized>(&self, rng: &mut R) -> (f32, f32) {
+        let [[min_lon, max_lon], [min_lat, max_lat]] = self.bounding_box;
+        let (min_sin, max_sin) = (min_lat.to_radians().sin(), max_lat.to_radians().sin());
+        loop {
+            let lon = rng.gen_range(min_lon..=max_lon);
+            // uniform in the sinus of the latitude, to be uniform on the sphere
+            let lat = rng.gen_range(min_sin..=max_sin).asin().to_degrees();
+            if self.contains(lon, lat) {
+                return (lon, lat);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::region::Region;
+
+    #[test]
+    fn test_region_contains_and_area() {
+        let region = Region::new(vec![vec![0.0, 0.0], vec![10.0, 0.0], vec![0.0, 10.0]]);
+        assert!(region.contains(2.0, 2.0));
+        assert!(!region.contains(6.0, 6.0));
+        assert!(!region.contains(-1.0, 2.0));
+
+        // a quarter of the northern hemisphere
+        let quarter = Region::from_bbox(0.0, 0.0, 90.0, 90.0);
+        assert!((quarter.area_fraction() - 0.125).abs() < 1e-4);
+    }
+}
@@ -2,7 +2,7 @@ use std::time::SystemTime;
 
 use rand::{rngs::StdRng, SeedableRng};
 
-use crate::{island::{Island, GRID_DIVISIONS, GridCell, read_geojsons}, random_point::{point_on_land_test, random_point_on_sphere}};
+use crate::{island::IslandIndex, random_point::{point_on_land_test, random_point_on_sphere}};
 
 /**
  * A function to test the some static point in polygon tests
@@ -11,38 +11,30 @@ use crate::{island::{Island, GRID_DIVISIONS, GridCell, read_geojsons}, random_po
  */
 pub fn static_polygon_tests(import_prefix: &str) {
 
-    println!("1/?: Read GeoJSONs parallel ...");
+    println!("1/?: Read island index or build it from GeoJSONs ...");
     let now = SystemTime::now();
-    let coastlines: Vec<Vec<Vec<f32>>> = read_geojsons(import_prefix);
+    let island_index = IslandIndex::of_prefix(import_prefix);
     println!("1/?: Finished in {} sek", now.elapsed().unwrap().as_secs());
-
-
-    println!("2/?: Precalculations for islands/continents ...");
-    let now = SystemTime::now();
-    let islands: Vec<Island> = coastlines.iter().map(|e| Island::new(e.to_owned())).collect();
-    let mut island_grid: Vec<Vec<GridCell>> = GRID_DIVISIONS.iter().map(|e| vec![GridCell::WATER; *e]).collect();
-    islands.iter().for_each(|island| island.add_to_grid(&mut island_grid));
-    println!("2/?: Finished precalculations in {} sek", now.elapsed().unwrap().as_secs());
     
     // unmeasured test beforehand to fill caches or similar
-    point_on_land_test(0.0, 0.0, &island_grid);
+    point_on_land_test(0.0, 0.0, &island_index);
     let now = SystemTime::now();
-    println!("Point on land (Atlantic): {}", point_on_land_test(0.0, 0.0, &island_grid)); // Atlantic
+    println!("Point on land (Atlantic): {}", point_on_land_test(0.0, 0.0, &island_index)); // Atlantic
     println!("Finished test in {} micros", now.elapsed().unwrap().as_micros());
     let now = SystemTime::now();
-    println!("Point on land (US): {}", point_on_land_test(-104.2758092369033, 34.117786526143604, &island_grid)); //US
+    println!("Point on land (US): {}", point_on_land_test(-104.2758092369033, 34.117786526143604, &island_index)); //US
     println!("Finished test in {} micros", now.elapsed().unwrap().as_micros());
     let now = SystemTime::now();
-    println!("Point on land (Arktis): {}", point_on_land_test(-27.24044854389621, 70.01752410356319, &island_grid)); // North of Grönland
+    println!("Point on land (Arktis): {}", point_on_land_test(-27.24044854389621, 70.01752410356319, &island_index)); // North of Grönland
     println!("Finished test in {} micros", now.elapsed().unwrap().as_micros());
     let now = SystemTime::now();
-    println!("Point on land (Antarctica): {}", point_on_land_test(71.55, -74.1878186, &island_grid)); //Antarctica
+    println!("Point on land (Antarctica): {}", point_on_land_test(71.55, -74.1878186, &island_index)); //Antarctica
     println!("Finished test in {} micros", now.elapsed().unwrap().as_micros());
     let now = SystemTime::now();
-    println!("Point on land (Mid of pacific): {}", point_on_land_test(-144.1294183409396, -47.75776979131451, &island_grid));
+    println!("Point on land (Mid of pacific): {}", point_on_land_test(-144.1294183409396, -47.75776979131451, &island_index));
     println!("Finished test in {} micros", now.elapsed().unwrap().as_micros());
     let now = SystemTime::now();
-    println!("Point on land (Russia): {}", point_on_land_test(82.35471714457248, 52.4548566256728, &island_grid));
+    println!("Point on land (Russia): {}", point_on_land_test(82.35471714457248, 52.4548566256728, &island_index));
     println!("Finished test in {} micros", now.elapsed().unwrap().as_micros());
 
     let now = SystemTime::now();
@@ -59,7 +51,7 @@ pub fn static_polygon_tests(import_prefix: &str) {
     while counter < 1000 {
         (lon, lat) = random_point_on_sphere(&mut rng);
         let now = SystemTime::now();
-        if !point_on_land_test(lon, lat, &island_grid) {
+        if !point_on_land_test(lon, lat, &island_index) {
             points_in_water.push([lon, lat]);
         } else {
             points_on_land.push([lon, lat])
@@ -0,0 +1,157 @@
+use std::error::Error;
+use serde::{Serialize, Deserialize};
+use crate::layers::{Corridor, WaterwayLimits, Metric};
+
+/**
+ * limits of the large canals by their names in OSM, which often lack them:
+ * the Panama Canal with the Neopanamax locks and the Suez Canal with the Suezmax draught and the Suez Canal Bridge
+ */
+const CANAL_LIMITS: [(&[&str], WaterwayLimits); 2] = [
+    (&["Panama Canal", "Canal de Panamá"], WaterwayLimits { width: None, max_draught: Some(15.2), max_beam: Some(51.25), max_length: Some(366.0), max_height: Some(57.91) }),
+    (&["Suez Canal", "قناة السويس"], WaterwayLimits { width: None, max_draught: Some(20.1), max_beam: Some(77.5), max_length: None, max_height: Some(68.0) }),
+];
+
+/// width of a waterway per beam of a vessel, which it needs to steer and to pass others
+const MIN_WIDTH_PER_BEAM: f32 = 2.0;
+
+/**
+ * vessel with its dimensions in m; speeds in knots
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VesselProfile {
+    pub name: String,
+    /// service speed
+    pub speed: f32,
+    pub draft: f32,
+    pub beam: f32,
+    pub length: f32,
+    /// height above the waterline, which has to pass below bridges
+    pub air_draft: f32,
+    /// points (speed, fuel consumption in tons per day) of a piecewise linear curve, sorted by the speed
+    #[serde(default)]
+    pub fuel_curve: Vec<(f32, f32)>,
+}
+
+impl VesselProfile {
+    /**
+     * true if the vessel fits through a waterway with the limits; the width has to be twice the beam, see MIN_WIDTH_PER_BEAM,
+     * while the maximal beam of locks is a hard limit. Unknown limits don't restrict it.
+     */
+    pub fn fits(&self, limits: &WaterwayLimits) -> bool {
+        [(limits.width, self.beam * MIN_WIDTH_PER_BEAM), (limits.max_draught, self.draft), (limits.max_beam, self.beam), (limits.max_length, self.length), (limits.max_height, self.air_draft)]
+            .iter()
+            .all(|(limit, dimension)| limit.is_none_or(|limit| *dimension <= limit))
+    }
+
+    /**
+     * fuel consumption in tons per day at the speed, linear between the points of the curve and with the cube of the speed
+     * beyond them; None without a curve
+     */
+    pub fn fuel_per_day(&self, speed: f32) -> Option<f32> {
+        let (first, last) = (self.fuel_curve.first()?, self.fuel_curve.last()?);
+        if speed <= first.0 || speed >= last.0 {
+            let (curve_speed, tons) = if speed <= first.0 { first } else { last };
+            return Some(tons * (speed / curve_speed).powi(3));
+        }
+        let i = self.fuel_curve.iter().position(|(curve_speed, _)| *curve_speed > speed)?;
+        let ((v1, t1), (v2, t2)) = (self.fuel_curve[i - 1], self.fuel_curve[i]);
+        Some(t1 + (t2 - t1) * (speed - v1) / (v2 - v1))
+    }
+
+    /**
+     * factor from the weights of the metric to this vessel at its service speed: time with the ratio of the speeds,
+     * fuel with the consumption per distance; 1 for other metrics or without a fuel curve
+     */
+    pub fn metric_scale(&self, metric: &Metric) -> f32 {
+        let Some(speed) = metric.speed.filter(|_| self.speed > 0.0) else {
+            return 1.0;
+        };
+        match (metric.tons_per_day, self.fuel_per_day(self.speed)) {
+            (Some(tons_per_day), Some(vessel_tons_per_day)) => speed / self.speed * vessel_tons_per_day / tons_per_day,
+            (Some(_), None) => 1.0,
+            (None, _) => speed / self.speed,
+        }
+    }
+}
+
+/**
+ * limits of the corridor; limits, which aren't tagged in OSM, are taken from the known limits of the large canals,
+ * if the corridor is a canal with one of their names
+ */
+pub fn limits_of_corridor(corridor: &Corridor) -> WaterwayLimits {
+    let known = CANAL_LIMITS.iter().find(|(names, _)| corridor.kind == "canal" && names.contains(&corridor.name.trim()));
+    match known {
+        Some((_, known)) => WaterwayLimits {
+            width: corridor.limits.width.or(known.width),
+            max_draught: corridor.limits.max_draught.or(known.max_draught),
+            max_beam: corridor.limits.max_beam.or(known.max_beam),
+            max_length: corridor.limits.max_length.or(known.max_length),
+            max_height: corridor.limits.max_height.or(known.max_height),
+        },
+        None => corridor.limits,
+    }
+}
+
+/**
+ * reads the profiles of a JSON file with an array of profiles
+ */
+pub fn read_vessel_profiles(filepath: &str) -> Result<Vec<VesselProfile>, Box<dyn Error>> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(filepath)?)?)
+}
+
+/**
+ * typical vessels from a feeder to a VLCC, if no profiles are given
+ */
+pub fn default_vessel_profiles() -> Vec<VesselProfile> {
+    let profile = |name: &str, speed: f32, draft: f32, beam: f32, length: f32, air_draft: f32, fuel_curve: &[(f32, f32)]| VesselProfile {
+        name: name.to_string(), speed, draft, beam, length, air_draft, fuel_curve: fuel_curve.to_vec(),
+    };
+    vec![
+        profile("Feeder", 14.0, 8.5, 23.0, 150.0, 35.0, &[(10.0, 12.0), (14.0, 25.0), (18.0, 50.0)]),
+        profile("Panamax", 14.0, 12.0, 32.3, 294.0, 57.9, &[(10.0, 20.0), (14.0, 45.0), (18.0, 90.0)]),
+        profile("Neopanamax", 16.0, 15.2, 51.2, 366.0, 57.9, &[(12.0, 60.0), (16.0, 110.0), (20.0, 200.0)]),
+        profile("Suezmax", 14.0, 20.0, 50.0, 275.0, 60.0, &[(10.0, 25.0), (14.0, 50.0), (16.0, 70.0)]),
+        profile("VLCC", 13.0, 22.0, 60.0, 330.0, 70.0, &[(10.0, 35.0), (13.0, 65.0), (16.0, 110.0)]),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Edge, layers::{Corridor, WaterwayLimits, Metric}, vessel::{VesselProfile, default_vessel_profiles, limits_of_corridor}};
+
+    #[test]
+    fn test_vessel_profiles() {
+        let profiles = default_vessel_profiles();
+        let json = serde_json::to_string(&profiles).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<VesselProfile>>(&json).unwrap(), profiles);
+        let without_curve: VesselProfile = serde_json::from_str(r#"{"name": "Tug", "speed": 10, "draft": 4, "beam": 10, "length": 30, "air_draft": 15}"#).unwrap();
+        assert!(without_curve.fuel_per_day(10.0).is_none());
+
+        // OSM limits come first, the missing ones are known for the large canals
+        let panama = Corridor { name: "Panama Canal".to_string(), kind: "canal".to_string(), limits: WaterwayLimits { max_height: Some(60.0), ..Default::default() } };
+        let limits = limits_of_corridor(&panama);
+        assert_eq!((limits.max_draught, limits.max_height), (Some(15.2), Some(60.0)));
+        let fits: Vec<&str> = profiles.iter().filter(|profile| profile.fits(&limits)).map(|profile| profile.name.as_str()).collect();
+        assert_eq!(fits, vec!["Feeder", "Panamax", "Neopanamax"]);
+        let suez = Corridor { name: "Suez Canal".to_string(), kind: "canal".to_string(), limits: WaterwayLimits::default() };
+        assert!(!profiles[4].fits(&limits_of_corridor(&suez)) && profiles[3].fits(&limits_of_corridor(&suez)));
+        // only canals with the exact name, not e.g. a river or a feeder canal named after them
+        let suez_river = Corridor { kind: "river".to_string(), ..suez.clone() };
+        assert!(profiles[4].fits(&limits_of_corridor(&suez_river)));
+        let old_panama = Corridor { name: "Old Panama Canal".to_string(), ..panama.clone() };
+        assert_eq!(limits_of_corridor(&old_panama).max_draught, None);
+        // a river of 50 m is wide enough for a beam of 23 m, but not for 32.3 m
+        let river = Corridor { name: "Rhine".to_string(), kind: "river".to_string(), limits: WaterwayLimits { width: Some(50.0), ..Default::default() } };
+        assert!(profiles[0].fits(&limits_of_corridor(&river)) && !profiles[1].fits(&limits_of_corridor(&river)));
+
+        let feeder = &profiles[0];
+        assert_eq!(feeder.fuel_per_day(12.0), Some(18.5));
+        assert_eq!(feeder.fuel_per_day(20.0), Some(50.0 * (20.0f32 / 18.0).powi(3)));
+        let edges = [Edge { src: 0, tgt: 1, dist: 1000 }];
+        let time = Metric::new("time", "s", vec![100], &edges).at_speed(7.0, None);
+        assert_eq!(feeder.metric_scale(&time), 0.5);
+        let fuel = Metric::new("fuel", "kg", vec![100], &edges).at_speed(7.0, Some(10.0));
+        assert_eq!(feeder.metric_scale(&fuel), 1.25);
+        assert_eq!(feeder.metric_scale(&Metric::new("risk", "m", vec![100], &edges)), 1.0);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{Node, currents::{CurrentField, course, point_along}, weather::{Conditions, WeatherField, SeaKeeping}};
+
+/**
+ * a vessel under way: its speed through water, its departure and the fields, which change the time of the edges.
+ * With a departure day and a field, which knows the day of its start, the departure is the hour of that day;
+ * otherwise it is the hours after the start of the field.
+ */
+#[derive(Clone, Debug)]
+pub struct Voyage<'a> {
+    /// speed through water in m/s in calm sea
+    pub speed: f32,
+    pub departure_hours: f32,
+    /// day of the departure in days since 1970-01-01, see ice::parse_departure_day
+    pub departure_day: Option<i64>,
+    pub currents: Option<&'a CurrentField>,
+    pub weather: Option<(&'a WeatherField, SeaKeeping)>,
+}
+
+impl<'a> Voyage<'a> {
+    pub fn new(speed: f32, departure_hours: f32) -> Voyage<'a> {
+        Voyage { speed, departure_hours, departure_day: None, currents: None, weather: None }
+    }
+
+    pub fn with_departure_day(self, departure_day: Option<i64>) -> Voyage<'a> {
+        Voyage { departure_day, ..self }
+    }
+
+    pub fn with_currents(self, currents: &'a CurrentField) -> Voyage<'a> {
+        Voyage { currents: Some(currents), ..self }
+    }
+
+    pub fn with_weather(self, weather: &'a WeatherField, sea_keeping: SeaKeeping) -> Voyage<'a> {
+        Voyage { weather: Some((weather, sea_keeping)), ..self }
+    }
+
+    /**
+     * upper bound of the speed over ground in m/s, for a lower bound of the time; the weather only slows the vessel down
+     */
+    pub fn max_speed_over_ground(&self) -> f32 {
+        self.speed + self.currents.map_or(0.0, |currents| currents.max_speed())
+    }
+
+    /**
+     * weather at the node at the seconds after the departure; None without a weather field
+     */
+    pub fn conditions_at(&self, node: &Node, seconds: u32) -> Option<Conditions> {
+        self.weather.as_ref().map(|(weather, _)| weather.conditions_at(node.lon, node.lat, self.hours_after_start(weather.start_day(), seconds as f32)))
+    }
+
+    /**
+     * time in s from one node to the next over the distance in m, when leaving at the seconds after the departure.
+     * The edge is split into legs of the cell size of the current and weather grids; on every leg the speed is reduced by the
+     * waves in its middle and the current there is corrected for, both at the time the vessel starts the leg, see
+     * CurrentField::speed_over_ground. None if the current is too strong or the waves are too high on any leg,
+     * at the source when leaving or at the target when arriving.
+     */
+    pub fn traversal_time(&self, from: &Node, to: &Node, dist: u32, seconds: u32) -> Option<u32> {
+        let is_safe = |node: &Node, seconds: u32| match &self.weather {
+            Some((_, sea_keeping)) => sea_keeping.is_safe(&self.conditions_at(node, seconds).unwrap_or_default()),
+            None => true,
+        };
+        if !is_safe(from, seconds) {
+            return None;
+        }
+        let Some(course) = course(from, to) else {
+            return Some((dist as f32 / self.speed).round() as u32);
+        };
+        let sample_distance = self.currents.map_or(f32::MAX, |currents| currents.sample_distance())
+            .min(self.weather.as_ref().map_or(f32::MAX, |(weather, _)| weather.sample_distance()));
+        let n_legs = (dist as f32 / sample_distance).ceil().max(1.0) as u32;
+        let leg_dist = dist as f32 / n_legs as f32;
+        let mut time = 0.0;
+        for leg in 0..n_legs {
+            let (lon, lat) = point_along(from, to, (leg as f32 + 0.5) / n_legs as f32);
+            let seconds = seconds as f32 + time;
+            let mut speed = self.speed;
+            if let Some((weather, sea_keeping)) = &self.weather {
+                let conditions = weather.conditions_at(lon, lat, self.hours_after_start(weather.start_day(), seconds));
+                if !sea_keeping.is_safe(&conditions) {
+                    return None;
+                }
+                speed *= 1.0 - sea_keeping.speed_loss(&conditions, course);
+            }
+            let speed_over_ground = match self.currents {
+                Some(currents) => currents.speed_over_ground(lon, lat, self.hours_after_start(currents.start_day(), seconds), course, speed)?,
+                None => speed,
+            };
+            time += leg_dist / speed_over_ground;
+        }
+        let time = time.round() as u32;
+        is_safe(to, seconds + time).then_some(time)
+    }
+
+    /**
+     * hours after the start of a field, which starts at the day, at the seconds after the departure
+     */
+    fn hours_after_start(&self, start_day: Option<i64>, seconds: f32) -> f32 {
+        let days = match (self.departure_day, start_day) {
+            (Some(departure_day), Some(start_day)) => (departure_day - start_day) as f32,
+            _ => 0.0,
+        };
+        days * 24.0 + self.departure_hours + seconds / 3600.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Node, esri_ascii::RasterGrid, currents::CurrentField, voyage::Voyage, weather::{WeatherGrid, WeatherField, SeaKeeping}};
+
+    #[test]
+    fn test_storm_in_the_middle_of_an_edge() {
+        // waves of 8 m only at the grid point in the middle of the edge, the ends are calm
+        let text = "lon,lat,wind_u,wind_v,wave_height\n0,0,0,0,0\n0.5,0,0,0,8\n1,0,0,0,0\n";
+        let weather = WeatherField::new(vec![(0.0, WeatherGrid::from_reader(text.as_bytes()).unwrap())]);
+        let (from, to) = (Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 1.0, lat: 0.0 });
+        let voyage = Voyage::new(5.0, 0.0).with_weather(&weather, SeaKeeping::default());
+        assert_eq!(voyage.traversal_time(&from, &to, 111_195, 0), None);
+        // a vessel, which copes with the storm, is slowed down in the middle
+        let voyage = Voyage::new(5.0, 0.0).with_weather(&weather, SeaKeeping::default().with_max_wave_height(8.0));
+        assert!(voyage.traversal_time(&from, &to, 111_195, 0).unwrap() > 111_195 / 5);
+        assert_eq!(Voyage::new(5.0, 0.0).traversal_time(&from, &to, 111_195, 0), Some(22_239));
+    }
+
+    #[test]
+    fn test_traversal_time_with_currents() {
+        // 1 m/s to the east at the start, to the north from hour 10 on; grids from 0° to 360°
+        let east = || RasterGrid::from_reader("ncols 2\nnrows 1\nxllcorner 0\nyllcorner -10\ncellsize 180\nNODATA_value -9999\n1 1\n".as_bytes()).unwrap();
+        let zero = || RasterGrid::from_reader("ncols 2\nnrows 1\nxllcorner 0\nyllcorner -10\ncellsize 180\n0 0\n".as_bytes()).unwrap();
+        let field = CurrentField::new(vec![(10.0, zero(), east()), (0.0, east(), zero())]);
+        let (west, east_node) = (Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 0.1, lat: 0.0 });
+        let voyage = |speed: f32| Voyage::new(speed, 0.0).with_currents(&field);
+        assert_eq!(voyage(5.0).traversal_time(&west, &east_node, 12000, 0), Some(2000));
+        assert_eq!(voyage(5.0).traversal_time(&east_node, &west, 12000, 0), Some(3000));
+        assert_eq!(voyage(1.0).traversal_time(&east_node, &west, 12000, 0), None);
+        assert_eq!(voyage(5.0).traversal_time(&west, &west, 12000, 0), Some(2400));
+
+        // with the current of 1 m/s across the edge from hour 10 on the speed over ground is sqrt(5² - 1²),
+        // also a day after the start of the field, when the voyage starts a day later
+        let across = Some((12000.0 / 24f32.sqrt()).round() as u32);
+        assert_eq!(voyage(5.0).traversal_time(&west, &east_node, 12000, 36_000), across);
+        let field = field.with_start_day(Some(100));
+        let next_day = Voyage::new(5.0, 0.0).with_departure_day(Some(101)).with_currents(&field);
+        assert_eq!(next_day.traversal_time(&west, &east_node, 12000, 0), across);
+
+        // the current is sampled in the middle of three legs of 4 km, only the first one without current
+        let halves = RasterGrid::from_reader("ncols 2\nnrows 1\nxllcorner 0\nyllcorner -0.025\ncellsize 0.05\n0 2\n".as_bytes()).unwrap();
+        let field = CurrentField::new(vec![(0.0, halves, zero())]);
+        let voyage = Voyage::new(5.0, 0.0).with_currents(&field);
+        assert_eq!(voyage.traversal_time(&west, &east_node, 12000, 0), Some((4000.0f32 / 5.0 + 8000.0 / 7.0).round() as u32));
+    }
+}
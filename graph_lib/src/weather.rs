@@ -0,0 +1,272 @@
+use std::{error::Error, fs::File, io::{BufRead, BufReader}};
+use crate::currents::{steps_around, read_start_day, METERS_PER_DEGREE};
+
+/// deviation of a grid point from the regular grid in steps, up to which it is still on the grid
+const MAX_GRID_DEVIATION: f32 = 0.01;
+
+/**
+ * wind (u, v) in m/s and significant wave height in m at a point and time; calm outside of the grids
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Conditions {
+    pub wind_u: f32,
+    pub wind_v: f32,
+    pub wave_height: f32,
+}
+
+impl Conditions {
+    pub fn wind_speed(&self) -> f32 {
+        (self.wind_u * self.wind_u + self.wind_v * self.wind_v).sqrt()
+    }
+
+    fn interpolate(&self, next: &Conditions, fraction: f32) -> Conditions {
+        let between = |a: f32, b: f32| a + (b - a) * fraction;
+        Conditions {
+            wind_u: between(self.wind_u, next.wind_u),
+            wind_v: between(self.wind_v, next.wind_v),
+            wave_height: between(self.wave_height, next.wave_height),
+        }
+    }
+}
+
+/**
+ * the weather of one forecast time on a regular grid of lon and lat
+ */
+#[derive(Debug)]
+pub struct WeatherGrid {
+    n_cols: usize,
+    n_rows: usize,
+    /// lon and lat of the south west grid point
+    min_lon: f32,
+    min_lat: f32,
+    step_lon: f32,
+    step_lat: f32,
+    values: Vec<Option<Conditions>>,
+}
+
+impl WeatherGrid {
+    pub fn read_csv(filepath: &str) -> Result<WeatherGrid, Box<dyn Error>> {
+        WeatherGrid::from_reader(BufReader::new(File::open(filepath)?))
+    }
+
+    /**
+     * reads a CSV with the header lon,lat,wind_u,wind_v,wave_height (in any order) and one line per grid point;
+     * the grid points have to be regular, missing points have no data
+     */
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<WeatherGrid, Box<dyn Error>> {
+        let mut lines = reader.lines();
+        let header = lines.next().ok_or("Empty weather grid")??;
+        let columns: Vec<&str> = header.split(',').map(|column| column.trim()).collect();
+        let column = |name: &str| columns.iter().position(|column| *column == name).ok_or(format!("No column {} in weather grid", name));
+        let indices = [column("lon")?, column("lat")?, column("wind_u")?, column("wind_v")?, column("wave_height")?];
+
+        let mut points = vec![];
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            let value = |i: usize| -> Result<f32, Box<dyn Error>> {
+                Ok(fields.get(indices[i]).ok_or(format!("Missing value in line: {}", line))?.parse::<f32>()?)
+            };
+            points.push([value(0)?, value(1)?, value(2)?, value(3)?, value(4)?]);
+        }
+        if points.is_empty() {
+            return Err("No grid points in weather grid".into());
+        }
+        // jittered coordinates would give a tiny step and a huge grid, so every point has to be on the grid
+        let axis = |i: usize, name: &str| -> Result<(f32, f32, usize), Box<dyn Error>> {
+            let mut values: Vec<f32> = points.iter().map(|point| point[i]).collect();
+            values.sort_by(f32::total_cmp);
+            values.dedup();
+            if values.len() == 1 {
+                return Ok((values[0], 1.0, 1));
+            }
+            let step = values.windows(2).map(|pair| pair[1] - pair[0]).fold(f32::MAX, f32::min);
+            let irregular = values.iter().find(|value| {
+                let steps = (*value - values[0]) / step;
+                (steps - steps.round()).abs() > MAX_GRID_DEVIATION
+            });
+            if let Some(value) = irregular {
+                return Err(format!("Weather grid is not regular: {} {} is not on the grid of {} from {}", name, value, step, values[0]).into());
+            }
+            // points may be missing, but not most lines of the grid
+            let n = ((values[values.len() - 1] - values[0]) / step).round() as usize + 1;
+            if n > 2 * values.len() {
+                return Err(format!("Weather grid is not regular: {} values of {} on a grid of {} lines with a step of {}", values.len(), name, n, step).into());
+            }
+            Ok((values[0], step, n))
+        };
+        let (min_lon, step_lon, n_cols) = axis(0, "lon")?;
+        let (min_lat, step_lat, n_rows) = axis(1, "lat")?;
+        let mut values = vec![None; n_cols * n_rows];
+        for [lon, lat, wind_u, wind_v, wave_height] in points {
+            let col = ((lon - min_lon) / step_lon).round() as usize;
+            let row = ((lat - min_lat) / step_lat).round() as usize;
+            values[row * n_cols + col] = Some(Conditions { wind_u, wind_v, wave_height });
+        }
+        Ok(WeatherGrid { n_cols, n_rows, min_lon, min_lat, step_lon, step_lat, values })
+    }
+
+    /**
+     * conditions of the closest grid point; grids from 0° to 360° are supported as well
+     */
+    pub fn conditions_at(&self, lon: f32, lat: f32) -> Option<Conditions> {
+        let row = ((lat - self.min_lat) / self.step_lat).round();
+        if row < 0.0 || row >= self.n_rows as f32 {
+            return None;
+        }
+        [lon, lon + 360.0].iter()
+            .map(|lon| ((lon - self.min_lon) / self.step_lon).round())
+            .find(|col| *col >= 0.0 && *col < self.n_cols as f32)
+            .and_then(|col| self.values[row as usize * self.n_cols + col as usize])
+    }
+}
+
+/**
+ * the weather forecast of the time steps at the hours after the start of the field
+ */
+#[derive(Debug)]
+pub struct WeatherField {
+    steps: Vec<(f32, WeatherGrid)>,
+    /// day of the start of the field in days since 1970-01-01, if known
+    start_day: Option<i64>,
+}
+
+impl WeatherField {
+    pub fn new(mut steps: Vec<(f32, WeatherGrid)>) -> WeatherField {
+        steps.sort_by(|a, b| a.0.total_cmp(&b.0));
+        WeatherField { steps, start_day: None }
+    }
+
+    pub fn with_start_day(self, start_day: Option<i64>) -> WeatherField {
+        WeatherField { start_day, ..self }
+    }
+
+    /**
+     * reads the grids {hours}.csv of the folder, e.g. 0.csv, 6.csv, 12.csv, and the date of hour 0 from start.txt, if there is one
+     */
+    pub fn read(folder: &str) -> Result<WeatherField, Box<dyn Error>> {
+        let mut steps = vec![];
+        for entry in std::fs::read_dir(folder)? {
+            let file_name = entry?.file_name().into_string().unwrap_or_default();
+            let Some(hours) = file_name.strip_suffix(".csv").and_then(|hours| hours.parse::<f32>().ok()) else {
+                continue;
+            };
+            steps.push((hours, WeatherGrid::read_csv(&format!("{}/{}", folder, file_name))?));
+        }
+        if steps.is_empty() {
+            return Err(format!("No weather grids {{hours}}.csv in {}", folder).into());
+        }
+        Ok(WeatherField::new(steps).with_start_day(read_start_day(folder)?))
+    }
+
+    pub fn n_steps(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn start_day(&self) -> Option<i64> {
+        self.start_day
+    }
+
+    /**
+     * smallest step of the grids in m, the distance at which the weather is sampled along an edge
+     */
+    pub fn sample_distance(&self) -> f32 {
+        self.steps.iter().map(|(_, grid)| grid.step_lon.min(grid.step_lat) * METERS_PER_DEGREE).fold(f32::MAX, f32::min)
+    }
+
+    /**
+     * conditions at the point and the hours after the start of the field, linear between the time steps
+     */
+    pub fn conditions_at(&self, lon: f32, lat: f32, hours: f32) -> Conditions {
+        let at = |step: &(f32, WeatherGrid)| step.1.conditions_at(lon, lat).unwrap_or_default();
+        match steps_around(&self.steps, |step| step.0, hours) {
+            Some((previous, next, fraction)) => at(previous).interpolate(&at(next), fraction),
+            None => Conditions::default(),
+        }
+    }
+}
+
+/**
+ * how a vessel copes with the weather: its loss of speed in waves and the highest waves it may go through
+ */
+#[derive(Clone, Debug)]
+pub struct SeaKeeping {
+    /// points (wave height in m, fraction of the speed lost) of a piecewise linear curve, sorted by the wave height
+    pub speed_loss: Vec<(f32, f32)>,
+    pub max_wave_height: f32,
+}
+
+impl Default for SeaKeeping {
+    fn default() -> Self {
+        SeaKeeping { speed_loss: vec![(0.0, 0.0), (2.0, 0.05), (4.0, 0.15), (6.0, 0.35), (8.0, 0.6)], max_wave_height: 6.0 }
+    }
+}
+
+impl SeaKeeping {
+    pub fn with_max_wave_height(self, max_wave_height: f32) -> SeaKeeping {
+        SeaKeeping { max_wave_height, ..self }
+    }
+
+    pub fn is_safe(&self, conditions: &Conditions) -> bool {
+        conditions.wave_height <= self.max_wave_height
+    }
+
+    /**
+     * fraction of the speed lost in the conditions on the course (east, north) as unit vector.
+     * The curve holds for head seas; the waves are assumed to come with the wind, so with following seas the loss is halved.
+     */
+    pub fn speed_loss(&self, conditions: &Conditions, course: (f32, f32)) -> f32 {
+        let height = conditions.wave_height;
+        let loss = match self.speed_loss.iter().position(|(wave_height, _)| *wave_height > height) {
+            Some(0) => self.speed_loss[0].1,
+            Some(i) => {
+                let ((h1, l1), (h2, l2)) = (self.speed_loss[i - 1], self.speed_loss[i]);
+                l1 + (l2 - l1) * (height - h1) / (h2 - h1)
+            },
+            None => self.speed_loss.last().map_or(0.0, |(_, loss)| *loss),
+        };
+        // 1 if the wind blows against the course, -1 if it blows from behind
+        let wind_speed = conditions.wind_speed();
+        let against = if wind_speed > 0.0 { -(conditions.wind_u * course.0 + conditions.wind_v * course.1) / wind_speed } else { 1.0 };
+        (loss * (0.75 + 0.25 * against)).clamp(0.0, 0.95)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::weather::{Conditions, WeatherGrid, WeatherField, SeaKeeping};
+
+    #[test]
+    fn test_weather() {
+        // a grid of 2 x 2 points with 1° between them, the north east point has no data
+        let grid = |wave_height: f32| {
+            let text = format!(
+                "lon,lat,wave_height,wind_u,wind_v\n0,0,{0},-10,0\n1,0,{0},-10,0\n0,1,{0},-10,0\n", wave_height
+            );
+            WeatherGrid::from_reader(text.as_bytes()).unwrap()
+        };
+        let field = WeatherField::new(vec![(12.0, grid(6.0)), (0.0, grid(2.0))]);
+        assert_eq!(field.n_steps(), 2);
+        assert_eq!(field.conditions_at(0.2, 0.9, 0.0), Conditions { wind_u: -10.0, wind_v: 0.0, wave_height: 2.0 });
+        assert_eq!(field.conditions_at(0.2, 0.9, 6.0).wave_height, 4.0);
+        assert_eq!(field.conditions_at(1.0, 1.0, 6.0), Conditions::default());
+        assert_eq!(field.conditions_at(5.0, 0.0, 6.0), Conditions::default());
+        assert!(WeatherGrid::from_reader("lon,lat,wind_u\n0,0,1\n".as_bytes()).is_err());
+        // points off the grid or jittered points, which would make a step of 0.001°, are rejected
+        assert!(WeatherGrid::from_reader("lon,lat,wind_u,wind_v,wave_height\n0,0,0,0,1\n1,0,0,0,1\n2.3,0,0,0,1\n".as_bytes()).is_err());
+        assert!(WeatherGrid::from_reader("lon,lat,wind_u,wind_v,wave_height\n0,0,0,0,1\n0.001,0,0,0,1\n1,0,0,0,1\n".as_bytes()).is_err());
+        assert_eq!(field.sample_distance(), 111_195.0);
+
+        let sea_keeping = SeaKeeping::default();
+        let conditions = field.conditions_at(0.0, 0.0, 6.0);
+        // the wind comes from the east
+        assert!((sea_keeping.speed_loss(&conditions, (1.0, 0.0)) - 0.15).abs() < 1e-6);
+        assert!((sea_keeping.speed_loss(&conditions, (-1.0, 0.0)) - 0.075).abs() < 1e-6);
+        assert!((sea_keeping.speed_loss(&conditions, (0.0, 1.0)) - 0.1125).abs() < 1e-6);
+        assert!(sea_keeping.is_safe(&conditions));
+        assert!(!sea_keeping.with_max_wave_height(3.0).is_safe(&conditions));
+    }
+}
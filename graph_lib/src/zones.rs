@@ -0,0 +1,161 @@
+use rayon::prelude::*;
+use crate::{Graph, Node, Coordinates, lon_difference, wrap_lon, area::AreaPolygon, geojson_interface::read_zones_from_geojson};
+
+/// highest multiplier of a zone, so the costs of long edges stay far below the maximal distance of the routings
+const MAX_MULTIPLIER: f32 = 100.0;
+
+/**
+ * region, which may be used, but is expensive, e.g. a piracy high risk area, an Emission Control Area or congested waters
+ */
+#[derive(Clone, Debug)]
+pub struct PenaltyZone {
+    pub name: String,
+    pub area: AreaPolygon,
+    /// factor for the length of edges inside of the zone; at least 1, so the distance stays a lower bound for A*, and at most MAX_MULTIPLIER
+    pub multiplier: f32,
+    /// cost in m added to every edge, which crosses the border of the zone, e.g. for switching the fuel at the border of an ECA
+    pub penalty: u32,
+}
+
+impl PenaltyZone {
+    /**
+     * zone of the polygon of (lon, lat); multipliers out of range or invalid are limited to 1 to MAX_MULTIPLIER
+     */
+    pub fn new(name: &str, polygon: &[Coordinates], multiplier: f32, penalty: u32) -> PenaltyZone {
+        let multiplier = if multiplier.is_nan() { 1.0 } else { multiplier.clamp(1.0, MAX_MULTIPLIER) };
+        PenaltyZone { name: name.to_string(), area: AreaPolygon::new(polygon), multiplier, penalty }
+    }
+}
+
+/**
+ * the zones of an edge: the zones, which contain its center, the product of their multipliers and the penalties of the borders it crosses
+ */
+#[derive(Clone, Debug)]
+struct ZonedEdge {
+    zones: Vec<usize>,
+    multiplier: f32,
+    penalty: u32,
+}
+
+/**
+ * penalty zones resolved per edge of a graph; edges, which aren't in or at a zone, have None
+ */
+#[derive(Clone, Debug, Default)]
+pub struct PenaltyZones {
+    zones: Vec<PenaltyZone>,
+    edges: Vec<Option<ZonedEdge>>,
+}
+
+impl PenaltyZones {
+    pub fn new(graph: &Graph, zones: Vec<PenaltyZone>) -> PenaltyZones {
+        let edges = graph.edges.par_iter().map(|edge| {
+            let (src, tgt) = (graph.get_node(edge.src), graph.get_node(edge.tgt));
+            let (start, end) = (Coordinates(src.lon, src.lat), Coordinates(tgt.lon, tgt.lat));
+            let center = Coordinates(wrap_lon(src.lon + lon_difference(src.lon, tgt.lon) / 2.0), (src.lat + tgt.lat) / 2.0);
+            let mut zoned_edge = ZonedEdge { zones: vec![], multiplier: 1.0, penalty: 0 };
+            for (i, zone) in zones.iter().enumerate().filter(|(_, zone)| zone.area.crosses(&start, &end)) {
+                if zone.area.contains(&center) {
+                    zoned_edge.zones.push(i);
+                    zoned_edge.multiplier *= zone.multiplier;
+                }
+                if zone.area.contains(&start) != zone.area.contains(&end) {
+                    zoned_edge.penalty = zoned_edge.penalty.saturating_add(zone.penalty);
+                }
+            }
+            (!zoned_edge.zones.is_empty() || zoned_edge.penalty > 0).then_some(zoned_edge)
+        }).collect();
+        PenaltyZones { zones, edges }
+    }
+
+    /**
+     * reads the zones of a GeoJSON file, see read_zones_from_geojson, and resolves them for the graph
+     */
+    pub fn of_geojson(graph: &Graph, filepath: &str) -> Result<PenaltyZones, Box<dyn std::error::Error>> {
+        Ok(PenaltyZones::new(graph, read_zones_from_geojson(filepath)?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    pub fn zones(&self) -> &[PenaltyZone] {
+        &self.zones
+    }
+
+    /**
+     * cost of the edge with the multipliers and penalties of its zones; the same in both directions
+     */
+    #[inline]
+    pub fn cost(&self, edge_id: usize, cost: u32) -> u32 {
+        match self.edges.get(edge_id).and_then(Option::as_ref) {
+            Some(zoned_edge) => self.multiplied(edge_id, cost).saturating_add(zoned_edge.penalty),
+            None => cost,
+        }
+    }
+
+    /**
+     * cost of the edge with the multipliers of its zones, but without the penalties in m, e.g. for the weights of a metric
+     */
+    #[inline]
+    pub fn multiplied(&self, edge_id: usize, cost: u32) -> u32 {
+        match self.edges.get(edge_id).and_then(Option::as_ref) {
+            Some(zoned_edge) => (cost as f32 * zoned_edge.multiplier).min(u32::MAX as f32) as u32,
+            None => cost,
+        }
+    }
+
+    /**
+     * distance in m of the path inside of every zone, which it passes, by the name of the zone in the order of the zones
+     */
+    pub fn distances_in_zones(&self, graph: &Graph, path: &[Node]) -> Vec<(String, u32)> {
+        let mut distances: Vec<(String, u32)> = vec![];
+        for nodes in path.windows(2) {
+            let Some((zoned_edge, dist)) = graph.edge_between(nodes[0].id, nodes[1].id).and_then(|(edge_id, edge)| self.edges.get(edge_id)?.as_ref().map(|zoned_edge| (zoned_edge, edge.dist))) else {
+                continue;
+            };
+            for zone in &zoned_edge.zones {
+                let name = &self.zones[*zone].name;
+                match distances.iter_mut().find(|(other, _)| other == name) {
+                    Some((_, distance)) => *distance += dist,
+                    None => distances.push((name.clone(), dist)),
+                }
+            }
+        }
+        distances.sort_by_key(|(name, _)| self.zones.iter().position(|zone| &zone.name == name));
+        distances
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Graph, Node, Edge, Coordinates, zones::{PenaltyZone, PenaltyZones, MAX_MULTIPLIER}};
+
+    #[test]
+    fn test_penalty_zones() {
+        // a chain of nodes on the equator, the zone covers the edges between node 1 and 3
+        let nodes: Vec<Node> = (0..5).map(|i| Node { id: i, lon: i as f32, lat: 0.0 }).collect();
+        let edges: Vec<Edge> = (0..5usize).flat_map(|i| {
+            let neighbours = [i.checked_sub(1), Some(i + 1).filter(|j| *j < 5)];
+            neighbours.into_iter().flatten().map(move |j| Edge { src: i, tgt: j, dist: 100 })
+        }).collect();
+        let graph = Graph::new(nodes, edges);
+        let polygon = [Coordinates(0.9, -1.0), Coordinates(3.1, -1.0), Coordinates(3.1, 1.0), Coordinates(0.9, 1.0)];
+        let zones = PenaltyZones::new(&graph, vec![PenaltyZone::new("ECA", &polygon, 2.0, 50)]);
+
+        // edges 0-1 cross the border, 1-2 are inside
+        let edge_ids: Vec<usize> = [(0, 1), (1, 0), (1, 2), (3, 4)].iter()
+            .map(|(src, tgt)| graph.get_outgoing_edges_with_ids(*src).find(|(_, edge)| edge.tgt == *tgt).unwrap().0)
+            .collect();
+        assert_eq!(zones.cost(edge_ids[0], 100), 150);
+        assert_eq!(zones.cost(edge_ids[1], 100), 150);
+        assert_eq!(zones.cost(edge_ids[2], 100), 200);
+        assert_eq!(zones.cost(edge_ids[3], 100), 150);
+        assert_eq!(zones.distances_in_zones(&graph, &graph.nodes), vec![("ECA".to_string(), 200)]);
+        assert!(zones.distances_in_zones(&graph, &graph.nodes[0..2]).is_empty());
+
+        // multipliers are limited, so long edges don't overflow the distances of the routings
+        assert_eq!(PenaltyZone::new("ECA", &polygon, 1e30, 0).multiplier, MAX_MULTIPLIER);
+        assert_eq!(PenaltyZone::new("ECA", &polygon, f32::NAN, 0).multiplier, 1.0);
+        assert_eq!(PenaltyZone::new("ECA", &polygon, 0.5, 0).multiplier, 1.0);
+    }
+}
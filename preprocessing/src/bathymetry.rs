@@ -0,0 +1,157 @@
+use std::{error::Error, fs::File, io::BufReader, time::SystemTime};
+use graph_lib::{Graph, lon_difference, wrap_lon, file_interface::{import_graph_from_file, print_graph_with_layers_to_file}, esri_ascii::{GridHeader, read_esri_ascii}};
+use rayon::prelude::*;
+
+/**
+ * Grid of elevations in m (negative below sea level) like GEBCO, in rows from north to south.
+ * Elevations are saved as i16 to keep big grids in memory; cells without data have NO_DATA.
+ */
+pub struct DepthGrid {
+    pub header: GridHeader,
+    elevations: Vec<i16>,
+}
+
+const NO_DATA: i16 = i16::MIN;
+
+impl DepthGrid {
+    /**
+     * Reads an ESRI ASCII grid (.asc), e.g. exported from GEBCO. If the factor is bigger than 1, blocks of factor x factor cells
+     * are merged into one cell with the shallowest depth, which needs less memory and keeps the depth limits conservative.
+     */
+    pub fn read_esri_ascii(filepath: &str, factor: usize) -> Result<DepthGrid, Box<dyn Error>> {
+        let (header, rows) = read_esri_ascii(BufReader::new(File::open(filepath)?))?;
+        let factor = factor.max(1);
+        let (n_cols, n_rows) = (header.n_cols.div_ceil(factor), header.n_rows.div_ceil(factor));
+        let mut grid = DepthGrid {
+            header: GridHeader {
+                n_cols,
+                n_rows,
+                min_lat: header.min_lat + (header.n_rows as f64 - (n_rows * factor) as f64) * header.cell_size,
+                cell_size: header.cell_size * factor as f64,
+                ..header
+            },
+            elevations: vec![NO_DATA; n_cols * n_rows],
+        };
+        for (row, values) in rows.enumerate() {
+            let target_row = row / factor;
+            for (col, value) in values?.into_iter().enumerate() {
+                if value.is_nan() {
+                    continue;
+                }
+                let cell = &mut grid.elevations[target_row * n_cols + col / factor];
+                let elevation = value.round().clamp(NO_DATA as f32 + 1.0, i16::MAX as f32) as i16;
+                // the shallowest depth is the highest elevation
+                if *cell == NO_DATA || elevation > *cell {
+                    *cell = elevation;
+                }
+            }
+        }
+        Ok(grid)
+    }
+
+    /**
+     * value of the cell at the point, None outside of the grid or without data
+     */
+    pub fn value_at(&self, lon: f32, lat: f32) -> Option<i16> {
+        let (row, col) = self.header.cell_of(lon, lat)?;
+        Some(self.elevations[row * self.header.n_cols + col]).filter(|value| *value != NO_DATA)
+    }
+
+    /**
+     * water depth in m at the point; 0 on land and infinity outside of the grid or without data
+     */
+    pub fn depth_at(&self, lon: f32, lat: f32) -> f32 {
+        match self.value_at(lon, lat) {
+            Some(elevation) => (-elevation as f32).max(0.0),
+            None => f32::INFINITY,
+        }
+    }
+
+    /**
+     * minimal depth along the great circle segment, sampled in steps of the cell size
+     */
+    pub fn min_depth_along(&self, lon1: f32, lat1: f32, lon2: f32, lat2: f32) -> f32 {
+        // edges over the date border
+        let d_lon = lon_difference(lon1, lon2);
+        let d_lat = lat2 - lat1;
+        let n_steps = (d_lon.abs().max(d_lat.abs()) as f64 / self.header.cell_size).ceil().max(1.0) as usize;
+        (0..=n_steps).map(|step| {
+            let fraction = step as f32 / n_steps as f32;
+            self.depth_at(wrap_lon(lon1 + d_lon * fraction), lat1 + d_lat * fraction)
+        }).fold(f32::INFINITY, f32::min)
+    }
+
+    /**
+     * depth of all nodes and minimal depth along all edges of the graph
+     */
+    pub fn depth_layers(&self, graph: &Graph) -> (Vec<f32>, Vec<f32>) {
+        let node_depth = graph.nodes.par_iter().map(|node| self.depth_at(node.lon, node.lat)).collect();
+        let edge_depth = graph.edges.par_iter().map(|edge| {
+            let (src, tgt) = (graph.get_node(edge.src), graph.get_node(edge.tgt));
+            self.min_depth_along(src.lon, src.lat, tgt.lon, tgt.lat)
+        }).collect();
+        (node_depth, edge_depth)
+    }
+}
+
+/**
+ * adds the water depth of a bathymetry grid to the nodes and edges of the graph and writes the graph again
+ */
+pub fn add_bathymetry(graph_name: &str, grid_path: &str, factor: usize) -> Result<(), Box<dyn Error>> {
+    println!("1/3: Read graph and bathymetry grid ...");
+    let now = SystemTime::now();
+    let mut graph = import_graph_from_file(graph_name)?;
+    let grid = DepthGrid::read_esri_ascii(grid_path, factor)?;
+    println!("1/3: Grid of {} x {} cells with {}° per cell", grid.header.n_cols, grid.header.n_rows, grid.header.cell_size);
+    println!("1/3: Finished in {} sek", now.elapsed()?.as_secs());
+
+    println!("2/3: Calculate depth of nodes and edges ...");
+    let now = SystemTime::now();
+    let (node_depth, edge_depth) = grid.depth_layers(&graph);
+    let unknown = node_depth.iter().filter(|depth| depth.is_infinite()).count();
+    let dry = node_depth.iter().filter(|depth| **depth == 0.0).count();
+    println!("2/3: {} nodes without depth, {} nodes on land or dry", unknown, dry);
+    graph.layers.node_depth = node_depth;
+    graph.layers.edge_depth = edge_depth;
+    println!("2/3: Finished in {} sek", now.elapsed()?.as_secs());
+
+    println!("3/3: Write graph ...");
+    let now = SystemTime::now();
+    print_graph_with_layers_to_file(&graph.nodes, &graph.edges, &graph.layers, graph_name);
+    println!("3/3: Finished in {} sek", now.elapsed()?.as_secs());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bathymetry::DepthGrid;
+
+    #[test]
+    fn test_read_and_sample_grid() {
+        // 4 x 2 cells of 1° from (0, 0), the northern row first; a shoal of 5 m in the middle of the southern row
+        let asc = "ncols 4\nnrows 2\nxllcorner 0\nyllcorner 0\ncellsize 1\nNODATA_value -9999\n\
+            -100 -100 -9999 120\n-80 -5 -90 -95\n";
+        let path = std::env::temp_dir().join("ship_routing_test_grid.asc");
+        std::fs::write(&path, asc).unwrap();
+        let grid = DepthGrid::read_esri_ascii(path.to_str().unwrap(), 1).unwrap();
+        assert_eq!(grid.depth_at(0.5, 1.5), 100.0);
+        assert_eq!(grid.depth_at(2.5, 1.5), f32::INFINITY);
+        assert_eq!(grid.depth_at(3.5, 1.5), 0.0);
+        assert_eq!(grid.depth_at(0.5, 0.5), 80.0);
+        assert_eq!(grid.depth_at(5.0, 0.5), f32::INFINITY);
+        assert_eq!(grid.min_depth_along(0.5, 0.5, 2.5, 0.5), 5.0);
+        // a grid from 0° to 360° is sampled west of the prime meridian as well
+        let east = "ncols 2\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 180\n-10 -20\n";
+        let east_path = std::env::temp_dir().join("ship_routing_test_grid_360.asc");
+        std::fs::write(&east_path, east).unwrap();
+        let grid_360 = DepthGrid::read_esri_ascii(east_path.to_str().unwrap(), 1).unwrap();
+        assert_eq!(grid_360.depth_at(-90.0, 45.0), 20.0);
+        std::fs::remove_file(east_path).unwrap();
+
+        // merged cells keep the shallowest depth
+        let grid = DepthGrid::read_esri_ascii(path.to_str().unwrap(), 2).unwrap();
+        assert_eq!(grid.depth_at(0.5, 0.5), 5.0);
+        assert_eq!(grid.depth_at(2.5, 1.5), 0.0);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,411 @@
+use std::{collections::BinaryHeap, cmp::Reverse, io::{self, Write}, f32::consts::PI, fmt::Display, sync::atomic::{AtomicUsize, Ordering}};
+
+use graph_lib::{Node, Edge, Graph};
+use rand::{rngs::StdRng, SeedableRng, Rng};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator, ParallelSliceMut};
+
+/// mean earth radius in m, like in distance_between
+const EARTH_RADIUS: f32 = 6371000.0;
+
+/**
+ * generated node with its maximal connection distance, which depends on the local node density
+ */
+#[derive(Clone, Copy)]
+pub struct OceanPoint {
+    pub node: Node,
+    pub max_distance: u32,
+}
+
+/**
+ * defines to which of the nodes in connection distance a node is connected
+ */
+pub enum ConnectionStrategy {
+    /// closest neighbour in each of the four lat/lon quadrants
+    Quadrants,
+    /// the k closest neighbours
+    KNearest(usize),
+    /// Yao graph: closest neighbour in each of the given number of cones
+    Yao(usize),
+    /// all neighbours in connection distance
+    Radius,
+}
+
+impl ConnectionStrategy {
+    /**
+     * parses quadrants, knn:{k}, yao:{cones} or radius
+     */
+    pub fn from_param(param: &str) -> Result<ConnectionStrategy, String> {
+        let (name, number) = match param.split_once(':') {
+            Some((name, number)) => (name, Some(number.parse::<usize>().map_err(|_| format!("{} is no valid number", number))?)),
+            None => (param, None),
+        };
+        match (name, number) {
+            ("quadrants", None) => Ok(ConnectionStrategy::Quadrants),
+            ("knn", Some(k)) if k > 0 => Ok(ConnectionStrategy::KNearest(k)),
+            ("yao", Some(cones)) if cones >= 3 => Ok(ConnectionStrategy::Yao(cones)),
+            ("radius", None) => Ok(ConnectionStrategy::Radius),
+            _ => Err(format!("Connection strategy {} not known, use quadrants, knn:{{k}}, yao:{{cones}} or radius", param)),
+        }
+    }
+
+    /**
+     * selects the neighbours of k out of the possible neighbours; returns pairs of node id and distance
+     */
+    fn select_neighbours(&self, k: &Node, max_distance: u32, possible_neighbours: &[OceanPoint]) -> Vec<(usize, u32)> {
+        let in_range = possible_neighbours.iter()
+            .map(|point| &point.node)
+            .filter(|l| k.id != l.id)
+            .map(|l| (l, k.distance_to_node(l).ceil() as u32))
+            .filter(|(_, distance)| *distance < max_distance);
+
+        match self {
+            ConnectionStrategy::Quadrants => closest_per_cone(in_range, 4, |l| {
+                // east and west over the antimeridian too, north and south relative to the meridian of k
+                let (x, y) = local_offset(k, l);
+                if x > 0.0 && y > 0.0 {             //NORTH EAST
+                    Some(0)
+                } else if x > 0.0 && y < 0.0 {      // SOUTH EAST
+                    Some(1)
+                } else if x < 0.0 && y < 0.0 {      // SOUTH WEST
+                    Some(2)
+                } else if x < 0.0 && y > 0.0 {      // NORTH WEST
+                    Some(3)
+                } else {
+                    None
+                }
+            }),
+            ConnectionStrategy::KNearest(n_neighbours) => {
+                let mut neighbours: Vec<(usize, u32)> = in_range.map(|(l, distance)| (l.id, distance)).collect();
+                neighbours.sort_by_key(|(id, distance)| (*distance, *id));
+                neighbours.truncate(*n_neighbours);
+                neighbours
+            },
+            ConnectionStrategy::Yao(n_cones) => closest_per_cone(in_range, *n_cones, |l| {
+                let (x, y) = local_offset(k, l);
+                let angle = y.atan2(x) + PI;   // 0 to 2 PI, counterclockwise from west
+                Some(((angle / (2.0 * PI / *n_cones as f32)) as usize).min(n_cones - 1))
+            }),
+            ConnectionStrategy::Radius => in_range.map(|(l, distance)| (l.id, distance)).collect(),
+        }
+    }
+}
+
+impl Display for ConnectionStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionStrategy::Quadrants => write!(f, "quadrants"),
+            ConnectionStrategy::KNearest(k) => write!(f, "knn:{}", k),
+            ConnectionStrategy::Yao(cones) => write!(f, "yao:{}", cones),
+            ConnectionStrategy::Radius => write!(f, "radius"),
+        }
+    }
+}
+
+/**
+ * keeps the closest node of each cone, where cone_of gives the cone of a node or None if it is in no cone
+ */
+fn closest_per_cone<'a>(
+    in_range: impl Iterator<Item = (&'a Node, u32)>, n_cones: usize, cone_of: impl Fn(&Node) -> Option<usize>
+) -> Vec<(usize, u32)> {
+    let mut closest: Vec<Option<(usize, u32)>> = vec![None; n_cones];
+    for (l, distance) in in_range {
+        if let Some(cone) = cone_of(l) {
+            if closest[cone].is_none_or(|(_, best)| distance < best) {
+                closest[cone] = Some((l.id, distance));
+            }
+        }
+    }
+    closest.into_iter().flatten().collect()
+}
+
+/**
+ * offset of l from k to the east and north in the tangent plane of the unit sphere at k, so that the angle is the direction
+ * on the surface; with 3D vectors it holds near the poles too, where the meridians converge, and over the antimeridian
+ */
+#[inline]
+fn local_offset(k: &Node, l: &Node) -> (f32, f32) {
+    let (k_lon, k_lat) = ((k.lon as f64).to_radians(), (k.lat as f64).to_radians());
+    let (l_lon, l_lat) = ((l.lon as f64).to_radians(), (l.lat as f64).to_radians());
+    let l_vector = [l_lat.cos() * l_lon.cos(), l_lat.cos() * l_lon.sin(), l_lat.sin()];
+    // the unit vectors to the east and north are orthogonal to k, so the offset is the projection of l onto them
+    let east = [-k_lon.sin(), k_lon.cos(), 0.0];
+    let north = [-k_lat.sin() * k_lon.cos(), -k_lat.sin() * k_lon.sin(), k_lat.cos()];
+    let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    (dot(l_vector, east) as f32, dot(l_vector, north) as f32)
+}
+
+/**
+ * gives ids to the nodes and connects them by the given strategy; the edges are symmetric.
+ * The neighbour search runs in parallel for every grid column. The edges of all columns are merged and sorted afterwards,
+ * so the result doesn't depend on the number of threads.
+ */
+pub fn connect_graph(mut graph_grid: Vec<Vec<Vec<OceanPoint>>>, strategy: &ConnectionStrategy) -> (Vec<Node>, Vec<Edge>) {
+    let mut points: Vec<Node> = Vec::new();
+    let mut id = 0;
+
+    // set ids according to the grid, so from west to east and then from north to south
+    for grid_column in graph_grid.iter_mut() {
+        for grid_cell in grid_column.iter_mut() {
+            for point in grid_cell.iter_mut() {
+                point.node.id = id;
+                points.push(point.node);
+                id += 1;
+            }
+        }
+    }
+
+    let finished_columns = AtomicUsize::new(0);
+    let column_edges: Vec<Vec<Edge>> = (0..360usize).into_par_iter().map(|i| {
+        let mut edges = vec![];
+        for j in 0..180 {
+            for point in &graph_grid[i][j] {
+                let possible_neighbours = possible_neighbours(&graph_grid, point);
+                for (neighbour_id, dist) in strategy.select_neighbours(&point.node, point.max_distance, &possible_neighbours) {
+                    edges.push(Edge { src: point.node.id, tgt: neighbour_id, dist });
+                    edges.push(Edge { src: neighbour_id, tgt: point.node.id, dist });
+                }
+            }
+        }
+        print!("\rConnecting... {}/360 longitudes", finished_columns.fetch_add(1, Ordering::Relaxed) + 1);
+        let _ = io::stdout().flush();
+        edges
+    }).collect();
+    println!();
+
+    // merge and remove edges which were found from both sides
+    let mut final_edges: Vec<Edge> = column_edges.into_iter().flatten().collect();
+    final_edges.par_sort_unstable_by_key(|edge| (edge.src, edge.tgt, edge.dist));
+    final_edges.dedup_by_key(|edge| (edge.src, edge.tgt));
+
+    (points, final_edges)
+}
+
+/**
+ * collects all points of the grid cells which could contain nodes in connection distance of the point.
+ * The circle of connection distance is a cap on the sphere; its width in longitude grows towards the poles,
+ * and if it contains a pole, all columns are checked, so nodes are connected over the pole too.
+ */
+fn possible_neighbours(graph_grid: &[Vec<Vec<OceanPoint>>], point: &OceanPoint) -> Vec<OceanPoint> {
+    let k = &point.node;
+    // angular radius of the circle of connection distance
+    let radius = (point.max_distance as f32 / EARTH_RADIUS).min(PI / 2.0);
+    let min_row = (k.lat - radius.to_degrees() + 89.99).floor().max(0.0) as usize;
+    let max_row = ((k.lat + radius.to_degrees() + 89.99).floor() as usize).min(179);
+
+    let cos_lat = k.lat.to_radians().cos();
+    let columns: Vec<usize> = if radius.sin() >= cos_lat {
+        (0..360).collect()
+    } else {
+        let half_width = (radius.sin() / cos_lat).asin().to_degrees();
+        let first = (k.lon - half_width + 180.0).floor() as i32;
+        let last = (k.lon + half_width + 180.0).floor() as i32;
+        (first..=last.min(first + 359)).map(|column| column.rem_euclid(360) as usize).collect()
+    };
+
+    let mut possible_neighbours: Vec<OceanPoint> = Vec::new();
+    for column in columns {
+        for cell in &graph_grid[column][min_row..=max_row] {
+            possible_neighbours.extend(cell);
+        }
+    }
+    possible_neighbours
+}
+
+/**
+ * numbers to compare connection strategies
+ */
+pub struct ConnectionStatistics {
+    pub n_edges: usize,
+    pub edges_per_node: f32,
+    /// average of graph distance divided by great circle distance
+    pub average_detour: f32,
+}
+
+impl Display for ConnectionStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} edges, {:.2} edges per node, average detour factor {:.4}", self.n_edges, self.edges_per_node, self.average_detour)
+    }
+}
+
+/**
+ * counts the edges and measures the detour factor against the great circle distance.
+ * For this, Dijkstras from random sources settle all nodes within a limited graph distance;
+ * nodes closer than a minimal distance are ignored, because short distances are dominated by the node positions.
+ */
+pub fn connection_statistics(nodes: &[Node], edges: &[Edge]) -> ConnectionStatistics {
+    const N_SOURCES: usize = 20;
+    const MAX_GRAPH_DISTANCE: u32 = 500000;
+    const MIN_DIRECT_DISTANCE: f32 = 50000.0;
+
+    let graph = Graph::new(nodes.to_vec(), edges.to_vec());
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut detour_sum = 0.0;
+    let mut n_detours = 0;
+    let mut dists = vec![u32::MAX; graph.n_nodes()];
+
+    for _ in 0..N_SOURCES.min(graph.n_nodes()) {
+        let src = rng.gen_range(0..graph.n_nodes());
+        let mut settled: Vec<usize> = vec![];
+        let mut priority_queue = BinaryHeap::new();
+        dists[src] = 0;
+        priority_queue.push(Reverse((0, src)));
+
+        while let Some(Reverse((dist, node_id))) = priority_queue.pop() {
+            if dist > dists[node_id] { continue }
+            settled.push(node_id);
+            let direct_distance = graph.get_node(src).distance_to_node(graph.get_node(node_id));
+            if direct_distance >= MIN_DIRECT_DISTANCE {
+                detour_sum += dist as f64 / direct_distance as f64;
+                n_detours += 1;
+            }
+            for edge in graph.get_outgoing_edges(node_id) {
+                let new_dist = dist + edge.dist;
+                if new_dist <= MAX_GRAPH_DISTANCE && new_dist < dists[edge.tgt] {
+                    dists[edge.tgt] = new_dist;
+                    priority_queue.push(Reverse((new_dist, edge.tgt)));
+                }
+            }
+        }
+        // reset only touched nodes to avoid initializing the whole vector again
+        for node_id in settled {
+            dists[node_id] = u32::MAX;
+            for edge in graph.get_outgoing_edges(node_id) {
+                dists[edge.tgt] = u32::MAX;
+            }
+        }
+    }
+
+    ConnectionStatistics {
+        n_edges: edges.len(),
+        edges_per_node: edges.len() as f32 / nodes.len().max(1) as f32,
+        average_detour: if n_detours > 0 { (detour_sum / n_detours as f64) as f32 } else { f32::NAN },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use graph_lib::Node;
+    use rand::{rngs::StdRng, SeedableRng, Rng};
+
+    use crate::connection::{ConnectionStrategy, OceanPoint, local_offset, possible_neighbours, connect_graph, connection_statistics};
+
+    /**
+     * grid of 20 x 20 points about every 11 km from 10°E 10°N, a little shifted, so no two points are exactly in one direction
+     */
+    fn small_grid() -> Vec<Vec<Vec<OceanPoint>>> {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut graph_grid: Vec<Vec<Vec<OceanPoint>>> = vec![vec![Vec::new(); 180]; 360];
+        for i in 0..20 {
+            for j in 0..20 {
+                let lon = 10.0 + i as f32 * 0.1 + rng.gen_range(0.0..0.01);
+                let lat = 10.0 + j as f32 * 0.1 + rng.gen_range(0.0..0.01);
+                let point = OceanPoint { node: Node { id: 0, lon, lat }, max_distance: 35000 };
+                graph_grid[(lon + 180.0).floor() as usize][(lat + 89.99).floor() as usize].push(point);
+            }
+        }
+        graph_grid
+    }
+
+    #[test]
+    fn test_strategies_on_small_grid() {
+        let (nodes, _) = connect_graph(small_grid(), &ConnectionStrategy::Radius);
+        let points: Vec<OceanPoint> = nodes.iter().map(|node| OceanPoint { node: *node, max_distance: 35000 }).collect();
+        // a point in the middle of the grid
+        let center = Node { id: 0, lon: 11.0, lat: 11.0 };
+        let k = nodes.iter().min_by(|a, b| a.distance_to_node(&center).total_cmp(&b.distance_to_node(&center))).unwrap();
+        let mut in_range: Vec<(usize, u32)> = nodes.iter()
+            .filter(|l| l.id != k.id)
+            .map(|l| (l.id, k.distance_to_node(l).ceil() as u32))
+            .filter(|(_, distance)| *distance < 35000)
+            .collect();
+        in_range.sort_by_key(|(id, distance)| (*distance, *id));
+        let cones = |neighbours: &[(usize, u32)], n_cones: usize| -> Vec<usize> {
+            let mut cones: Vec<usize> = neighbours.iter().map(|(id, _)| {
+                let (x, y) = local_offset(k, &nodes[*id]);
+                (((y.atan2(x) + std::f32::consts::PI) / (2.0 * std::f32::consts::PI / n_cones as f32)) as usize).min(n_cones - 1)
+            }).collect();
+            cones.sort();
+            cones
+        };
+
+        let quadrants = ConnectionStrategy::Quadrants.select_neighbours(k, 35000, &points);
+        assert_eq!(cones(&quadrants, 4), vec![0, 1, 2, 3]);
+        let nearest = ConnectionStrategy::KNearest(5).select_neighbours(k, 35000, &points);
+        assert_eq!(nearest, in_range[0..5].to_vec());
+        let yao = ConnectionStrategy::Yao(12).select_neighbours(k, 35000, &points);
+        assert_eq!(cones(&yao, 12), (0..12).collect::<Vec<usize>>());
+        // the closest neighbour is in one of the cones
+        assert!(yao.contains(&in_range[0]));
+        let mut radius = ConnectionStrategy::Radius.select_neighbours(k, 35000, &points);
+        radius.sort_by_key(|(id, distance)| (*distance, *id));
+        assert_eq!(radius, in_range);
+    }
+
+    #[test]
+    fn test_connection_statistics() {
+        let mut detours = vec![];
+        for strategy in [ConnectionStrategy::Quadrants, ConnectionStrategy::KNearest(8), ConnectionStrategy::Yao(12), ConnectionStrategy::Radius] {
+            let (nodes, edges) = connect_graph(small_grid(), &strategy);
+            assert!(edges.iter().all(|edge| edges.iter().any(|back| back.src == edge.tgt && back.tgt == edge.src)), "{} not symmetric", strategy);
+            let statistics = connection_statistics(&nodes, &edges);
+            assert_eq!(statistics.n_edges, edges.len());
+            assert_eq!(statistics.edges_per_node, edges.len() as f32 / 400.0);
+            assert!(statistics.average_detour >= 1.0 && statistics.average_detour < 1.3, "{}: {}", strategy, statistics);
+            detours.push(statistics.average_detour);
+        }
+        // the radius graph contains the edges of all other strategies, so its ways are the shortest
+        assert!(detours[0..3].iter().all(|detour| *detour >= detours[3]));
+        assert!(detours[0] > detours[2]);
+    }
+
+    #[test]
+    fn test_strategy_from_param() {
+        for param in ["quadrants", "knn:6", "yao:16", "radius"] {
+            assert_eq!(ConnectionStrategy::from_param(param).unwrap().to_string(), param);
+        }
+        assert!(ConnectionStrategy::from_param("yao:2").is_err() && ConnectionStrategy::from_param("knn").is_err());
+    }
+
+    #[test]
+    fn test_quadrants_over_antimeridian() {
+        // the node over the antimeridian is north east of k, the other one north west
+        let k = Node { id: 0, lon: 179.9, lat: 0.0 };
+        let possible_neighbours = [
+            OceanPoint { node: Node { id: 1, lon: -179.9, lat: 0.1 }, max_distance: 30000 },
+            OceanPoint { node: Node { id: 2, lon: 179.7, lat: 0.1 }, max_distance: 30000 },
+        ];
+        let mut neighbours = ConnectionStrategy::Quadrants.select_neighbours(&k, 30000, &possible_neighbours);
+        neighbours.sort();
+        assert_eq!(neighbours.iter().map(|(id, _)| *id).collect::<Vec<usize>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_local_offset_near_pole() {
+        // l is farther from the pole than k, but on the other side of the pole from k's view, so it is north east of k
+        let k = Node { id: 0, lon: 0.0, lat: 89.5 };
+        let l = Node { id: 1, lon: 100.0, lat: 89.0 };
+        let (x, y) = local_offset(&k, &l);
+        assert!(x > 0.0 && y > 0.0);
+        // the same directions as in a local plane far from the poles
+        let (x, y) = local_offset(&Node { id: 0, lon: 10.0, lat: 45.0 }, &Node { id: 1, lon: 10.1, lat: 44.9 });
+        assert!(x > 0.0 && y < 0.0);
+        assert!(((x / y).abs() - (45.0f32.to_radians().cos())).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_neighbours_over_pole() {
+        // k and l are 44 km apart over the northpole, m is at the same latitude as k, but far away
+        let mut graph_grid: Vec<Vec<Vec<OceanPoint>>> = vec![vec![Vec::new(); 180]; 360];
+        let k = OceanPoint { node: Node { id: 0, lon: 0.5, lat: 89.8 }, max_distance: 50000 };
+        graph_grid[180][179].push(k);
+        graph_grid[0][179].push(OceanPoint { node: Node { id: 1, lon: -179.5, lat: 89.8 }, max_distance: 50000 });
+        graph_grid[180][130].push(OceanPoint { node: Node { id: 2, lon: 0.5, lat: 40.8 }, max_distance: 50000 });
+        graph_grid[270][130].push(OceanPoint { node: Node { id: 3, lon: 90.5, lat: 40.8 }, max_distance: 50000 });
+
+        let ids = |neighbours: Vec<OceanPoint>| neighbours.iter().map(|point| point.node.id).collect::<Vec<usize>>();
+        assert_eq!(ids(possible_neighbours(&graph_grid, &k)), vec![1, 0]);
+        assert_eq!(ids(possible_neighbours(&graph_grid, &graph_grid[180][130][0])), vec![2]);
+        let neighbours = ConnectionStrategy::Yao(8).select_neighbours(&k.node, k.max_distance, &possible_neighbours(&graph_grid, &k));
+        assert_eq!(neighbours.iter().map(|(id, _)| *id).collect::<Vec<usize>>(), vec![1]);
+    }
+}
@@ -1,188 +1,279 @@
-use std::{time::SystemTime, error::Error, io::{Write, stdout, self}, collections::HashMap};
+use std::{time::SystemTime, error::Error, io::{Write, stdout}, sync::atomic::{AtomicU32, Ordering}};
 
-use graph_lib::{Coordinates, Node, Edge, file_interface::print_graph_to_file, distance_between, island::{GridCell, read_geojsons, GRID_DIVISIONS, Island}, random_point::random_point_in_water};
+use graph_lib::{Node, wrap_lon, file_interface::print_graph_with_layers_to_file, island::{IslandIndex, GRID_DIVISIONS, Island}, random_point::{random_point_in_water, random_point_in_water_in_region}, coast_distance::CoastDistance, region::Region};
+use rand::{Rng, rngs::StdRng, SeedableRng};
+use rayon::prelude::{IntoParallelRefIterator, IndexedParallelIterator, ParallelIterator};
 
+use crate::connection::{OceanPoint, ConnectionStrategy, connect_graph, connection_statistics};
+use crate::waterways::{add_corridors, read_waterways, CorridorGraph};
 
-pub fn generate_graph(filename_out: &str, import_prefix: &str) -> Result<(), Box<dyn Error>> {
+/// connection radius in m for nodes with the highest density
+const MAX_DISTANCE: u32 = 30000;
+/// up to this distance to the coast in m, nodes are generated with the highest density
+const NEAR_SHORE_DISTANCE: f32 = 10000.0;
+/// from this distance to the coast in m on, nodes are generated with the lowest density
+const OPEN_OCEAN_DISTANCE: f32 = 200000.0;
+/// density in open ocean relative to the density near shore
+const OPEN_OCEAN_DENSITY: f32 = 0.1;
+/// nodes farther from the coast in m get an unlimited clearance
+const MAX_CLEARANCE: f32 = 50000.0;
+
+/**
+ * defines how the random points are distributed over the ocean
+ */
+pub enum NodeDensity {
+    /// same density everywhere
+    Uniform,
+    /// dense near the coast and in straits, sparse in open ocean
+    CoastAdaptive,
+}
+
+pub fn generate_graph(filename_out: &str, import_prefix: &str, density: NodeDensity, strategy: ConnectionStrategy, seed: u64) -> Result<(), Box<dyn Error>> {
     const NUMBER_OF_NODES: u32 = 4000000;
 
-    println!("1/5: Read GeoJSONs parallel ...");
+    println!("1/5: Read island index or build it from GeoJSONs ...");
     let now = SystemTime::now();
-    let coastlines: Vec<Vec<Vec<f32>>> = read_geojsons(import_prefix);
-    println!("1/5: Finished: Imported {} coastlines in {} sek", coastlines.len(), now.elapsed().unwrap().as_secs());
+    let island_index = IslandIndex::of_prefix(import_prefix);
+    println!("1/5: Finished: Imported {} coastlines in {} sek", island_index.islands.len(), now.elapsed().unwrap().as_secs());
 
-    println!("2/5: Precalculations for islands/continents ...");
+    println!("2/5: Precalculations of the distance to the coast ...");
     let now = SystemTime::now();
-    let mut island_grid: Vec<Vec<GridCell>> = GRID_DIVISIONS.iter().map(|e| vec![GridCell::WATER; *e]).collect();
-    let islands: Vec<Island> = coastlines.iter().map(|e| Island::new(e.to_owned())).collect();
-    islands.iter().for_each(|island| island.add_to_grid(&mut island_grid));
+    // the distance to the coast is needed for the clearance of the nodes, and for the density of adaptive nodes
+    let coast_distance = CoastDistance::new(&island_index.islands);
     let n_grid_cells = GRID_DIVISIONS.into_iter().reduce(|e, f| e + f).unwrap();
-    println!("2/5: Finished precalculations of islands and put into {} grid cells in {} sek", n_grid_cells, now.elapsed().unwrap().as_secs());
+    println!("2/5: Finished precalculations of {} islands in {} grid cells in {} sek", island_index.islands.len(), n_grid_cells, now.elapsed().unwrap().as_secs());
 
-    println!("3/5: Generating {} random points in ocean ...", NUMBER_OF_NODES);
+    // a regional import gets the same density of nodes as the whole world
+    let region = Region::of_import(import_prefix)?;
+    let number_of_nodes = match &region {
+        Some(region) => ((NUMBER_OF_NODES as f32 * region.area_fraction()).ceil() as u32).max(1),
+        None => NUMBER_OF_NODES,
+    };
+    println!("3/5: Generating {} random points in ocean with seed {} ...", number_of_nodes, seed);
     let now = SystemTime::now();
-    let graph_grid = generate_random_points_in_ocean(&island_grid, NUMBER_OF_NODES);
+    let density_coast_distance = match density {
+        NodeDensity::Uniform => None,
+        NodeDensity::CoastAdaptive => Some(&coast_distance),
+    };
+    let graph_grid = generate_random_points_in_ocean(&island_index, number_of_nodes, density_coast_distance, region.as_ref(), seed);
     println!("3/5 Finished generating points in {} min", now.elapsed().unwrap().as_secs() as f32 / 60.0);
 
-    println!("4/5: Connecting graph ...");
+    println!("4/5: Connecting graph with strategy {} ...", strategy);
     let now = SystemTime::now();
-    let (nodes, edges) = connect_graph(graph_grid);
+    let (nodes, edges) = connect_graph(graph_grid, &strategy);
     println!("4/5 Finished graph creating {} edges in {} min", edges.len(), now.elapsed().unwrap().as_secs() as f32 / 60.0);
+    println!("4/5 Strategy {}: {}", strategy, connection_statistics(&nodes, &edges));
+    let n_ocean_nodes = nodes.len();
+    let waterways = read_waterways(import_prefix)?;
+    let mut corridor_graph = add_corridors(nodes, edges, &waterways, MAX_DISTANCE as f32, &island_index.islands, &coast_distance);
+    println!(
+        "4/5 Added {} waterways as corridors: {} connected to the ocean at both ends, {} at one end, {} skipped without connection",
+        waterways.len(), corridor_graph.through, corridor_graph.dead_ends, corridor_graph.unconnected
+    );
+    corridor_graph.layers.node_clearance = node_clearance(&corridor_graph, n_ocean_nodes, &island_index.islands, &coast_distance);
 
     println!("5/5: Writing graph into {} ...", filename_out);
     let now = SystemTime::now();
-    print_graph_to_file(&nodes, &edges, filename_out);
+    print_graph_with_layers_to_file(&corridor_graph.nodes, &corridor_graph.edges, &corridor_graph.layers, filename_out);
     println!("5/5 Finished file write {} sek", now.elapsed().unwrap().as_secs());
     Ok(())
 }
 
-fn generate_random_points_in_ocean(island_grid: &Vec<Vec<GridCell>>, number_of_points: u32) -> Vec<Vec<Vec<Node>>> {
-    let mut grid: Vec<Vec<Vec<Node>>> = vec![vec![Vec::new(); 180]; 360];
-    let mut lat: f32;
-    let mut lon: f32;
-    let mut new_node: Node;
-    let mut rng = rand::thread_rng();
-    let mut counter = 0;
-
-    while counter < number_of_points {
-        // first we generate just a uniformly distributed random 3D vectors
-        (lon, lat) = random_point_in_water(&mut rng, island_grid);
-
-        new_node = Node { id: 0, lat, lon };
-        grid[(lon + 179.99).floor() as usize][(lat + 89.99).floor() as usize]
-            .push(new_node);
-        counter = counter + 1;
-        if counter % 1000 == 0 {
-            print!("\rGenerating... {}/{} points", counter, number_of_points);
-            stdout().flush().unwrap();
+/**
+ * distance of every node to the closest coastline in m, infinity if farther than MAX_CLEARANCE.
+ * Nodes of corridors lie between the banks of canals and rivers, which are no coastlines; they get half of the width, if known.
+ */
+fn node_clearance(corridor_graph: &CorridorGraph, n_ocean_nodes: usize, islands: &[Island], coast_distance: &CoastDistance) -> Vec<f32> {
+    let mut clearance: Vec<f32> = corridor_graph.nodes[..n_ocean_nodes].par_iter().map(|node| {
+        let distance = coast_distance.distance_to_coast(islands, node.lon, node.lat, MAX_CLEARANCE);
+        if distance >= MAX_CLEARANCE { f32::INFINITY } else { distance }
+    }).collect();
+    clearance.resize(corridor_graph.nodes.len(), f32::INFINITY);
+    for (edge_id, edge) in corridor_graph.edges.iter().enumerate().filter(|(_, edge)| edge.src >= n_ocean_nodes) {
+        if let Some(width) = corridor_graph.layers.corridor_of_edge(edge_id).and_then(|corridor| corridor.limits.width) {
+            clearance[edge.src] = clearance[edge.src].min(width / 2.0);
         }
-        //print!("[{},{}],", lon, lat);
     }
-    println!("");
-    
-    return grid;
+    clearance
 }
 
+/**
+ * generates one set of points and connects it with different strategies to compare edge counts and detours
+ */
+pub fn compare_connection_strategies(import_prefix: &str, number_of_nodes: u32, density: NodeDensity, seed: u64) -> Result<(), Box<dyn Error>> {
+    let strategies = [
+        ConnectionStrategy::Quadrants, ConnectionStrategy::KNearest(6), ConnectionStrategy::KNearest(8),
+        ConnectionStrategy::Yao(8), ConnectionStrategy::Yao(12), ConnectionStrategy::Yao(16), ConnectionStrategy::Radius
+    ];
 
-fn connect_graph(mut graph_grid: Vec<Vec<Vec<Node>>>) -> (Vec<Node>, Vec<Edge>) {
-    let mut points: Vec<Node> = Vec::new();
-    let mut edges_set = HashMap::new();
-    let mut final_edges: Vec<Edge> = Vec::new();
-    let mut id = 0;
-    let max_distance = 30000;
-
-    // set ids according to the grid, so from west to east and then from north to south 
-    for i in 0..360 {
-        for j in 0..180 {
-            for k in 0..graph_grid[i][j].len() {
-                graph_grid[i][j][k].id = id;
-                points.push(graph_grid[i][j][k].clone());
-                id = id + 1;
-            }
-        }
-    }
+    println!("1/3: Read island index or build it from GeoJSONs ...");
+    let now = SystemTime::now();
+    let island_index = IslandIndex::of_prefix(import_prefix);
+    let coast_distance = match density {
+        NodeDensity::Uniform => None,
+        NodeDensity::CoastAdaptive => Some(CoastDistance::new(&island_index.islands)),
+    };
+    println!("1/3: Finished in {} sek", now.elapsed()?.as_secs());
 
-    // search for closest north-east, north-west, south-east and south-west neighbours and connect them
-    for i in 0..360 {
-        for j in 0..180 {
-            for k in &graph_grid[i][j] {
-                // calclate how many grids to the right/left shold be considered
-                let distance_to_right_end = k.distance_to(&Coordinates(i as f32 - 179.0, k.lat));
-                let distance_to_left_end = k.distance_to(&Coordinates(i as f32 - 180.0, k.lat));
-                let distance_to_north_end = k.distance_to(&Coordinates(k.lon, j as f32 - 90.0));
-                let distance_to_south_end = k.distance_to(&Coordinates(k.lon, j as f32 - 89.0));
-                let grid_with = distance_between(i as f32 - 179.0, k.lat, i as f32 - 180.0, k.lat);
-                
-                let grids_right = (((max_distance as f32 - distance_to_right_end) / grid_with).ceil() as usize).min(180); 
-                let grids_left = (((max_distance as f32 - distance_to_left_end) / grid_with).ceil() as usize).min(180);
-                let check_north = distance_to_north_end < max_distance as f32 && j > 0;
-                let check_south = distance_to_south_end < max_distance as f32 && j < 179;
-                
-                // add all possible grids to a vector to check enough but not too much
-                let mut possible_neighbours: Vec<Node> = Vec::new();
-                possible_neighbours.extend(&graph_grid[i][j]);
-                if check_north { possible_neighbours.extend(&graph_grid[i][j - 1]) }
-                if check_south { possible_neighbours.extend(&graph_grid[i][j + 1]); }
-
-                for r in 1..(grids_right + 1) {
-                    possible_neighbours.extend(&graph_grid[(i + r).rem_euclid(360)][j]);
-                    if check_north { possible_neighbours.extend(&graph_grid[(i + r).rem_euclid(360)][j - 1]) }    // if grid is not the most northern
-                    if check_south { possible_neighbours.extend(&graph_grid[(i + r).rem_euclid(360)][j + 1]); } // if grid is not the most southern
-                    
-                }
-                for l in 1..(grids_left + 1) {
-                    possible_neighbours.extend(&graph_grid[(360 + i - l).rem_euclid(360)][j]);
-                    if check_north { possible_neighbours.extend(&graph_grid[(360 + i - l).rem_euclid(360)][j - 1]) }  // if grid is not the most northern
-                    if check_south { possible_neighbours.extend(&graph_grid[(360 + i - l).rem_euclid(360)][j + 1]); }   // if grid is not the most southern
-                    // println!("left: {}, {}, {}, {}, {}", grids_right, grids_left, distance_to_right_end, distance_to_left_end, grid_with);
-                }
+    println!("2/3: Generating {} random points in ocean with seed {} ...", number_of_nodes, seed);
+    let now = SystemTime::now();
+    let graph_grid = generate_random_points_in_ocean(&island_index, number_of_nodes, coast_distance.as_ref(), Region::of_import(import_prefix)?.as_ref(), seed);
+    println!("2/3: Finished in {} sek", now.elapsed()?.as_secs());
 
-                let mut neighbour_ne = (None, max_distance);
-                let mut neighbour_se = (None, max_distance);
-                let mut neighbour_sw = (None, max_distance);
-                let mut neighbour_nw = (None, max_distance);
-
-                // iterate over possible grids
-                for l in &possible_neighbours {
-                    if k.id != l.id {
-                        let distance_to_node = k.distance_to_node(l).ceil() as u32;
-                        if distance_to_node < max_distance {
-                            if k.lon < l.lon && k.lat < l.lat {             //NORTH EAST
-                                if distance_to_node < neighbour_ne.1 {
-                                    neighbour_ne = (Some(l.id), distance_to_node);
-                                }
-                            } else if k.lon < l.lon && k.lat > l.lat {      // SOUTH EAST
-                                if distance_to_node < neighbour_se.1 {
-                                    neighbour_se = (Some(l.id), distance_to_node);
-                                }
-                            } else if k.lon > l.lon && k.lat > l.lat {      // SOUTH WEST
-                                if distance_to_node < neighbour_sw.1 {
-                                    neighbour_sw = (Some(l.id), distance_to_node);
-                                }
-                            } else if k.lon > l.lon && k.lat < l.lat {      // NORTH WEST
-                                if distance_to_node < neighbour_nw.1 {
-                                    neighbour_nw = (Some(l.id), distance_to_node);
-                                }
-                            }
-                        }
-                    }
-                }
+    println!("3/3: Connecting with {} strategies ...", strategies.len());
+    let mut results = vec![];
+    for strategy in strategies {
+        let now = SystemTime::now();
+        let (nodes, edges) = connect_graph(graph_grid.clone(), &strategy);
+        results.push((strategy, connection_statistics(&nodes, &edges), now.elapsed()?.as_secs()));
+    }
+    println!("Strategy\tEdges\tEdges per node\tAverage detour\tTime (sek)");
+    for (strategy, statistics, time) in results {
+        println!("{}\t{}\t{:.2}\t{:.4}\t{}", strategy, statistics.n_edges, statistics.edges_per_node, statistics.average_detour, time);
+    }
+    Ok(())
+}
 
-                if neighbour_ne.0.is_some() {       //create Edge for north east
-                    edges_set.insert((k.id, neighbour_ne.0.unwrap()), neighbour_ne.1);
-                    edges_set.insert((neighbour_ne.0.unwrap(), k.id), neighbour_ne.1);
-                }
+/**
+ * generates random points in water; if a coast distance index is given, points are thinned out by their distance to the coast.
+ * If a region is given, only points inside of it are generated.
+ * The points are generated in parallel by a fixed number of random streams, seeded by the given seed.
+ * Because the streams and their order don't depend on the number of threads, the result is the same for every thread count.
+ */
+pub fn generate_random_points_in_ocean(
+    island_index: &IslandIndex, number_of_points: u32, coast_distance: Option<&CoastDistance>, region: Option<&Region>, seed: u64
+) -> Vec<Vec<Vec<OceanPoint>>> {
+    const N_RANDOM_STREAMS: u32 = 256;
+    let mut seed_rng = StdRng::seed_from_u64(seed);
+    let stream_seeds: Vec<u64> = (0..N_RANDOM_STREAMS).map(|_| seed_rng.gen()).collect();
+    let counter = AtomicU32::new(0);
 
-                if neighbour_se.0.is_some() {       //create Edge for south east
-                    edges_set.insert((k.id, neighbour_se.0.unwrap()), neighbour_se.1);
-                    edges_set.insert((neighbour_se.0.unwrap(), k.id), neighbour_se.1);
-                }
+    let streams: Vec<Vec<OceanPoint>> = stream_seeds.par_iter().enumerate().map(|(stream, stream_seed)| {
+        let mut rng = StdRng::seed_from_u64(*stream_seed);
+        let points_in_stream = number_of_points / N_RANDOM_STREAMS + if (stream as u32) < number_of_points % N_RANDOM_STREAMS {1} else {0};
+        let mut points = Vec::with_capacity(points_in_stream as usize);
 
-                if neighbour_sw.0.is_some() {       //create Edge for south west
-                    edges_set.insert((k.id, neighbour_sw.0.unwrap()), neighbour_sw.1);
-                    edges_set.insert((neighbour_sw.0.unwrap(), k.id), neighbour_sw.1);
-                }
+        while points.len() < points_in_stream as usize {
+            // first we generate just a uniformly distributed random 3D vectors
+            let (lon, lat) = match region {
+                Some(region) => random_point_in_water_in_region(&mut rng, island_index, region),
+                None => random_point_in_water(&mut rng, island_index),
+            };
 
-                if neighbour_nw.0.is_some() {       //create Edge for north west
-                    edges_set.insert((k.id, neighbour_nw.0.unwrap()), neighbour_nw.1);
-                    edges_set.insert((neighbour_nw.0.unwrap(), k.id), neighbour_nw.1);
+            let mut max_distance = MAX_DISTANCE;
+            if let Some(coast_distance) = coast_distance {
+                let density = relative_density(coast_distance.distance_to_coast(&island_index.islands, lon, lat, OPEN_OCEAN_DISTANCE));
+                if rng.gen::<f32>() >= density {
+                    continue;
                 }
+                // the expected distance to the next neighbours grows with 1/sqrt(density)
+                max_distance = (MAX_DISTANCE as f32 / density.sqrt()).ceil() as u32;
+            }
+            points.push(OceanPoint { node: Node { id: 0, lat, lon }, max_distance });
+
+            let generated = counter.fetch_add(1, Ordering::Relaxed) + 1;
+            if generated.is_multiple_of(10000) {
+                print!("\rGenerating... {}/{} points", generated, number_of_points);
+                stdout().flush().unwrap();
             }
         }
-        print!("\rConnecting... {}/360 latitudes", i + 1);
-        let _ = io::stdout().flush();
+        points
+    }).collect();
+    println!();
+
+    // merge in order of the streams to be independent of the thread scheduling
+    let mut grid: Vec<Vec<Vec<OceanPoint>>> = vec![vec![Vec::new(); 180]; 360];
+    for point in streams.into_iter().flatten() {
+        let column = ((wrap_lon(point.node.lon) + 180.0).floor() as usize).min(359);
+        grid[column][(point.node.lat + 89.99).floor() as usize].push(point);
     }
+    grid
+}
+
+/**
+ * density relative to the one near shore, linearly decreasing from near shore to open ocean
+ */
+fn relative_density(coast_distance: f32) -> f32 {
+    if coast_distance <= NEAR_SHORE_DISTANCE {
+        1.0
+    } else if coast_distance >= OPEN_OCEAN_DISTANCE {
+        OPEN_OCEAN_DENSITY
+    } else {
+        1.0 - (1.0 - OPEN_OCEAN_DENSITY) * (coast_distance - NEAR_SHORE_DISTANCE) / (OPEN_OCEAN_DISTANCE - NEAR_SHORE_DISTANCE)
+    }
+}
 
-    for ((src, tgt), dist) in edges_set {
-        final_edges.push(Edge {
-            src,
-            tgt,
-            dist,
+
+#[cfg(test)]
+mod test {
+    use graph_lib::{Node, Graph, island::IslandIndex};
+    use rand::{Rng, rngs::StdRng, SeedableRng};
+    use rayon::ThreadPoolBuilder;
+
+    use crate::{generate_graph::{generate_random_points_in_ocean, relative_density, MAX_DISTANCE, OPEN_OCEAN_DENSITY}, connection::{connect_graph, ConnectionStrategy, OceanPoint}};
+
+    #[test]
+    fn test_same_graph_for_different_thread_counts() {
+        let island_index = IslandIndex::new(&[]);
+        let generate = |threads: usize| ThreadPoolBuilder::new().num_threads(threads).build().unwrap().install(|| {
+            let graph_grid = generate_random_points_in_ocean(&island_index, 20000, None, None, 42);
+            connect_graph(graph_grid, &ConnectionStrategy::Yao(8))
         });
+
+        let (nodes_single, edges_single) = generate(1);
+        let (nodes_parallel, edges_parallel) = generate(4);
+
+        assert_eq!(nodes_single.len(), 20000);
+        assert_eq!(nodes_single.len(), nodes_parallel.len());
+        assert!(nodes_single.iter().zip(&nodes_parallel).all(|(a, b)| a.lon == b.lon && a.lat == b.lat));
+        assert_eq!(edges_single.len(), edges_parallel.len());
+        assert!(edges_single.iter().zip(&edges_parallel).all(|(a, b)| a.src == b.src && a.tgt == b.tgt && a.dist == b.dist));
     }
 
-    final_edges.sort_by(|a, b| a.src.cmp(&b.src));
+    #[test]
+    fn test_connect_over_antimeridian() {
+        // points about every 5 km from 179°E to 179°W, like on the way of the Pacific sample route
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut graph_grid: Vec<Vec<Vec<OceanPoint>>> = vec![vec![Vec::new(); 180]; 360];
+        for i in 0..40 {
+            for j in 0..20 {
+                let lon = 179.0 + i as f32 * 0.05 + rng.gen_range(0.0..0.02);
+                let lon = if lon >= 180.0 { lon - 360.0 } else { lon };
+                let lat = 38.0 + j as f32 * 0.05 + rng.gen_range(0.0..0.02);
+                let point = OceanPoint { node: Node { id: 0, lon, lat }, max_distance: MAX_DISTANCE };
+                graph_grid[(lon + 180.0).floor() as usize][(lat + 89.99).floor() as usize].push(point);
+            }
+        }
+
+        for strategy in [ConnectionStrategy::Quadrants, ConnectionStrategy::Yao(8)] {
+            let (nodes, edges) = connect_graph(graph_grid.clone(), &strategy);
+            let crossing = |src: &Node, tgt: &Node| (src.lon - tgt.lon).abs() > 180.0;
+            assert!(edges.iter().any(|edge| crossing(&nodes[edge.src], &nodes[edge.tgt])), "no edge over the antimeridian with {}", strategy);
+            assert!(edges.iter().all(|edge| edge.dist < MAX_DISTANCE));
+
+            // all nodes are reachable from the first one
+            let graph = Graph::new(nodes, edges);
+            let mut reached = vec![false; graph.n_nodes()];
+            let mut stack = vec![0];
+            reached[0] = true;
+            while let Some(node_id) = stack.pop() {
+                for edge in graph.get_outgoing_edges(node_id) {
+                    if !reached[edge.tgt] {
+                        reached[edge.tgt] = true;
+                        stack.push(edge.tgt);
+                    }
+                }
+            }
+            assert!(reached.iter().all(|reached| *reached), "graph not connected with {}", strategy);
+        }
+    }
 
-    // println!("");
-    return (points, final_edges);
+    #[test]
+    fn test_relative_density() {
+        assert_eq!(relative_density(0.0), 1.0);
+        assert_eq!(relative_density(10000.0), 1.0);
+        assert!((relative_density(105000.0) - (1.0 + OPEN_OCEAN_DENSITY) / 2.0).abs() < 1e-6);
+        assert_eq!(relative_density(200000.0), OPEN_OCEAN_DENSITY);
+        assert_eq!(relative_density(5000000.0), OPEN_OCEAN_DENSITY);
+    }
 }
@@ -0,0 +1,136 @@
+use std::{error::Error, time::SystemTime};
+use graph_lib::{Graph, Coordinates, lon_difference, wrap_lon, ice::{month_bit, MONTH_NAMES}, island::IslandIndex, land_mask::LandMask,
+    esri_ascii::RasterGrid, geojson_interface::read_coastlines_from_geojson, file_interface::{import_graph_from_file, print_graph_with_layers_to_file}};
+use rayon::prelude::*;
+
+/// in rasters of the ice concentration in percent, cells from 15 % on are covered by ice, like in the ice extent of the NSIDC
+const MIN_ICE_CONCENTRATION: f32 = 15.0;
+/// points checked along every edge, including both ends
+const SAMPLES_PER_EDGE: usize = 5;
+
+/**
+ * ice cover of one month: polygons of the ice extent or a raster of the ice concentration in percent
+ */
+pub enum IceCover {
+    Polygons(LandMask),
+    Raster(RasterGrid),
+}
+
+impl IceCover {
+    /**
+     * reads the ice cover of the month (1 to 12) from the folder: {month}.json with polygons or {month}.asc as ESRI ASCII grid,
+     * the month with two digits; None if there is no file for the month
+     */
+    pub fn read(folder: &str, month: u32) -> Result<Option<IceCover>, Box<dyn Error>> {
+        let path = |extension: &str| format!("{}/{:02}.{}", folder, month, extension);
+        if std::fs::exists(path("json"))? {
+            let polygons = read_coastlines_from_geojson(&path("json"))?;
+            Ok(Some(IceCover::Polygons(LandMask::new(IslandIndex::new(&polygons)))))
+        } else if std::fs::exists(path("asc"))? {
+            Ok(Some(IceCover::Raster(RasterGrid::read_esri_ascii(&path("asc"))?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn is_covered(&self, lon: f32, lat: f32) -> bool {
+        match self {
+            IceCover::Polygons(land_mask) => !land_mask.is_water(&Coordinates(lon, lat)),
+            // values above 100 are flags, e.g. for land or missing data
+            IceCover::Raster(grid) => grid.value_at(lon, lat).is_some_and(|concentration| (MIN_ICE_CONCENTRATION..=100.0).contains(&concentration)),
+        }
+    }
+
+    /**
+     * true if the edge is covered somewhere; points are sampled along the edge, also over the antimeridian
+     */
+    pub fn covers_edge(&self, src: &Coordinates, tgt: &Coordinates) -> bool {
+        let d_lon = lon_difference(src.0, tgt.0);
+        (0..SAMPLES_PER_EDGE).any(|step| {
+            let fraction = step as f32 / (SAMPLES_PER_EDGE - 1) as f32;
+            self.is_covered(wrap_lon(src.0 + d_lon * fraction), src.1 + (tgt.1 - src.1) * fraction)
+        })
+    }
+}
+
+/**
+ * months with ice of every node and edge, given the ice covers of the months January to December
+ */
+pub fn ice_layers(graph: &Graph, covers: &[Option<IceCover>]) -> (Vec<u16>, Vec<u16>) {
+    let months = || covers.iter().enumerate().filter_map(|(i, cover)| cover.as_ref().map(|cover| (month_bit(i as u32 + 1), cover)));
+    let node_ice_months = graph.nodes.par_iter().map(|node| {
+        months().filter(|(_, cover)| cover.is_covered(node.lon, node.lat)).fold(0, |bits, (bit, _)| bits | bit)
+    }).collect();
+    let edge_ice_months = graph.edges.par_iter().map(|edge| {
+        let (src, tgt) = (graph.get_node(edge.src), graph.get_node(edge.tgt));
+        months()
+            .filter(|(_, cover)| cover.covers_edge(&Coordinates(src.lon, src.lat), &Coordinates(tgt.lon, tgt.lat)))
+            .fold(0, |bits, (bit, _)| bits | bit)
+    }).collect();
+    (node_ice_months, edge_ice_months)
+}
+
+/**
+ * adds the months with ice of all nodes and edges to the graph from the monthly ice covers in the folder and writes the graph again
+ */
+pub fn add_ice(graph_name: &str, folder: &str) -> Result<(), Box<dyn Error>> {
+    println!("1/3: Read graph and ice covers ...");
+    let now = SystemTime::now();
+    let mut graph = import_graph_from_file(graph_name)?;
+    let covers = (1..=12).map(|month| IceCover::read(folder, month)).collect::<Result<Vec<Option<IceCover>>, Box<dyn Error>>>()?;
+    let missing: Vec<&str> = covers.iter().zip(MONTH_NAMES).filter(|(cover, _)| cover.is_none()).map(|(_, name)| name).collect();
+    if !missing.is_empty() {
+        println!("1/3: No ice cover for {}, these months are free of ice", missing.join(", "));
+    }
+    println!("1/3: Finished in {} sek", now.elapsed()?.as_secs());
+
+    println!("2/3: Check nodes and edges for ice ...");
+    let now = SystemTime::now();
+    let (node_ice_months, edge_ice_months) = ice_layers(&graph, &covers);
+    for (month, name) in MONTH_NAMES.iter().enumerate() {
+        let bit = month_bit(month as u32 + 1);
+        let n_edges = edge_ice_months.iter().filter(|months| *months & bit != 0).count();
+        if n_edges > 0 {
+            println!("2/3: {}: {} edges covered by ice", name, n_edges);
+        }
+    }
+    graph.layers.node_ice_months = node_ice_months;
+    graph.layers.edge_ice_months = edge_ice_months;
+    println!("2/3: Finished in {} sek", now.elapsed()?.as_secs());
+
+    println!("3/3: Write graph ...");
+    let now = SystemTime::now();
+    print_graph_with_layers_to_file(&graph.nodes, &graph.edges, &graph.layers, graph_name);
+    println!("3/3: Finished in {} sek", now.elapsed()?.as_secs());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use graph_lib::{Graph, Node, Edge, ice::month_bit};
+
+    use crate::ice::{IceCover, ice_layers};
+
+    #[test]
+    fn test_ice_layers() {
+        // polygons of ice from 0° to 2° in January, a raster of 1° cells from 0° to 4° in February: 80 % ice in the east, less than 15 % in the west
+        let folder = std::env::temp_dir().join("ship_routing_test_ice");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("01.json"), r#"{"type": "Polygon", "coordinates": [[[0, 0], [2, 0], [2, 2], [0, 2], [0, 0]]]}"#).unwrap();
+        std::fs::write(folder.join("02.asc"), "ncols 4\nnrows 1\nxllcorner 0\nyllcorner 0\ncellsize 1\n10 14.6 80 80\n").unwrap();
+        let folder_path = folder.to_str().unwrap();
+        let covers: Vec<Option<IceCover>> = (1..=12).map(|month| IceCover::read(folder_path, month).unwrap()).collect();
+        std::fs::remove_dir_all(&folder).unwrap();
+        assert!(covers[0].is_some() && covers[1].is_some() && covers[2].is_none());
+
+        let nodes = vec![Node { id: 0, lon: 1.0, lat: 0.5 }, Node { id: 1, lon: 3.5, lat: 0.5 }, Node { id: 2, lon: 5.0, lat: 0.5 }];
+        let edges = vec![
+            Edge { src: 0, tgt: 1, dist: 280000 }, Edge { src: 1, tgt: 0, dist: 280000 },
+            Edge { src: 1, tgt: 2, dist: 170000 }, Edge { src: 2, tgt: 1, dist: 170000 },
+        ];
+        let graph = Graph::new(nodes, edges);
+        let (node_ice_months, edge_ice_months) = ice_layers(&graph, &covers);
+        assert_eq!(node_ice_months, vec![month_bit(1), month_bit(2), 0]);
+        assert_eq!(edge_ice_months, vec![month_bit(1) | month_bit(2), month_bit(1) | month_bit(2), month_bit(2), month_bit(2)]);
+    }
+}
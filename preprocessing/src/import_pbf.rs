@@ -1,147 +1,421 @@
 use osmpbf::{Element, ElementReader};
-use std::{collections::{HashSet, HashMap, LinkedList}, time::SystemTime, error::Error};
-use std::fs;
+use std::{collections::{HashSet, HashMap, LinkedList}, time::SystemTime, error::Error, ops::Range};
 use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use geo::{Area, BooleanOps, Coord, LineString, Orient, Polygon, orient::Direction};
+use graph_lib::{geojson_interface::{write_coastlines_to_geojson, write_lines_to_geojson}, layers::Waterway, region::Region};
+
+use crate::node_index::{Checkpoints, NodeLocations};
+use crate::waterways::{WaterwayWay, navigable_waterway, estimate_widths, split_to_region, write_waterways};
 
 
 /**
- * filters ways for tag coastline and then searches for coordinates of referenced nodes
+ * where the coordinates of the coastline nodes are kept during the import
  */
-pub fn import_pbf(path: &str, prefix: &str) -> Result<(), Box<dyn Error>> {
-    println!("1/4: Read and filter OSM ways with tag 'coastline'...");
-    let now = SystemTime::now();
-    let waypoint_refs: Vec<Vec<i64>> = read_coastline(path);     //filter ways
-    println!("1/4: Ways with coastline tag:  {}", waypoint_refs.len());
-    println!("1/4: Finished in {} sek", now.elapsed()?.as_secs());
+pub enum ImportMode {
+    InMemory,
+    /**
+     * in a sorted file, with checkpoints after every stage to continue an interrupted import
+     */
+    OnDisk,
+}
+
+/**
+ * filters ways for tag coastline and navigable waterways and then searches for coordinates of referenced nodes;
+ * if a region is given, the coastlines are clipped to it
+ */
+pub fn import_pbf(path: &str, prefix: &str, mode: ImportMode, region: Option<&Region>) -> Result<(), Box<dyn Error>> {
+    match mode {
+        ImportMode::InMemory => {
+            println!("1/5: Read and filter OSM ways with tag 'coastline' and navigable waterways...");
+            let now = SystemTime::now();
+            let ways = read_ways(path)?;     //filter ways
+            println!("1/5: Ways with coastline tag:  {}, navigable waterways: {}", ways.coastlines.len(), ways.waterways.len());
+            println!("1/5: Finished in {} sek", now.elapsed()?.as_secs());
+
+            let connected = connect_coastlines_logged(ways.coastlines)?;
+            let (connected, waterways) = match region {
+                Some(region) => {
+                    let node_set: HashSet<i64> = connected.complete.iter().chain(&connected.open)
+                        .chain(ways.waterways.iter().map(|waterway| &waterway.refs)).flatten().copied().collect();
+                    keep_in_region(connected, ways.waterways, &read_nodes_in_bbox(path, |id| node_set.contains(&id), region)?, region)
+                },
+                None => (connected, ways.waterways),
+            };
+
+            println!("3/5: Read coordinates of points and merge data...");
+            let now = SystemTime::now();
+            let node_set: HashSet<i64> = connected.complete.iter().chain(&connected.open).chain(&connected.reversed)
+                .chain(waterways.iter().map(|waterway| &waterway.refs)).flatten().copied().collect();
+            let coordinates = read_coordinates(path, &node_set)?;
+            println!("3/5: Finished in {} sek", now.elapsed()?.as_secs());
+            repair_and_write(connected, waterways, |id| coordinates.get(&id).copied(), prefix, region)?;
+        },
+        ImportMode::OnDisk => {
+            let checkpoints = Checkpoints::new(&format!("./data/import/{}", prefix), path)?;
 
-    println!("2/4: Connecting coastlines...");
+            println!("1/5: Read and filter OSM ways with tag 'coastline' and navigable waterways...");
+            let now = SystemTime::now();
+            let OsmWays { coastlines, waterways } = checkpoints.stage("osm_ways", || Ok(read_ways(path)?))?;
+            println!("1/5: Ways with coastline tag:  {}, navigable waterways: {}", coastlines.len(), waterways.len());
+            println!("1/5: Finished in {} sek", now.elapsed()?.as_secs());
+
+            let connected = checkpoints.stage("connected", || connect_coastlines_logged(coastlines))?;
+            let (connected, waterways) = match region {
+                Some(region) => {
+                    let node_set: HashSet<i64> = connected.complete.iter().chain(&connected.open)
+                        .chain(waterways.iter().map(|waterway| &waterway.refs)).flatten().copied().collect();
+                    keep_in_region(connected, waterways, &read_nodes_in_bbox(path, |id| node_set.contains(&id), region)?, region)
+                },
+                None => (connected, waterways),
+            };
+
+            println!("3/5: Read coordinates of points into file and merge data...");
+            let now = SystemTime::now();
+            let mut node_ids: Vec<i64> = connected.complete.iter().chain(&connected.open).chain(&connected.reversed)
+                .chain(waterways.iter().map(|waterway| &waterway.refs)).flatten().copied().collect();
+            node_ids.par_sort_unstable();
+            node_ids.dedup();
+            let locations = NodeLocations::build(path, &node_ids, &checkpoints)?;
+            drop(node_ids);
+            println!("3/5: Coordinates of {} nodes found", locations.len());
+            println!("3/5: Finished in {} sek", now.elapsed()?.as_secs());
+            repair_and_write(connected, waterways, |id| locations.get(id), prefix, region)?;
+
+            drop(locations);
+            checkpoints.remove()?;
+        },
+    }
+    Ok(())
+}
+
+fn connect_coastlines_logged(waypoint_refs: Vec<Vec<i64>>) -> Result<ConnectedCoastlines, Box<dyn Error>> {
+    println!("2/5: Connecting coastlines...");
     let now = SystemTime::now();
-    let coastline = connect_coastlines(waypoint_refs);
-    println!("2/4: Number of continents and islands:  {}", coastline.len());
-    println!("2/4: Finished in {} sek", now.elapsed()?.as_secs());
+    let connected = connect_coastlines(waypoint_refs);
+    println!("2/5: Number of continents and islands:  {}", connected.complete.len());
+    println!("2/5: Open coastlines: {}, reversed parts: {}", connected.open.len(), connected.reversed.len());
+    println!("2/5: Finished in {} sek", now.elapsed()?.as_secs());
+    Ok(connected)
+}
 
-    println!("3/4: Read coordinates of points and merge data...");
+/**
+ * margin in degrees around the bounding box of a region, in which the nodes of coastlines and waterways are searched
+ */
+const REGION_MARGIN: f32 = 1.0;
+
+/**
+ * nodes of coastlines and waterways found for a region: the ids of the nodes within its bounding box and the margin,
+ * and the coordinates of the nodes near the meridian of its south west corner, to find the rings around the whole region
+ */
+struct RegionNodes {
+    inside: HashSet<i64>,
+    strip: HashMap<i64, (f32, f32)>,
+}
+
+/**
+ * id, lon and lat of a node, dense or not
+ */
+fn node_of(element: &Element) -> Option<(i64, f32, f32)> {
+    match element {
+        Element::DenseNode(node) => Some((node.id(), node.lon() as f32, node.lat() as f32)),
+        Element::Node(node) => Some((node.id(), node.lon() as f32, node.lat() as f32)),
+        _ => None,
+    }
+}
+
+/**
+ * the nodes of the region among the nodes needed
+ */
+fn read_nodes_in_bbox(path: &str, needed: impl Fn(i64) -> bool + Sync, region: &Region) -> Result<RegionNodes, Box<dyn Error>> {
+    println!("2/5: Find coastlines and waterways in the region {:?}...", region.get_bounding_box());
     let now = SystemTime::now();
-    let coordinates = read_coordinates(path, &coastline);
+    let [[min_lon, max_lon], [min_lat, max_lat]] = region.get_bounding_box();
+    let (lons, lats) = (min_lon - REGION_MARGIN..=max_lon + REGION_MARGIN, min_lat - REGION_MARGIN..=max_lat + REGION_MARGIN);
+    let strip_lons = min_lon - REGION_MARGIN..=min_lon + REGION_MARGIN;
+    let reader = ElementReader::from_path(path)?;
+    let (inside, strip) = reader.par_map_reduce(
+        |element| match node_of(&element).filter(|(id, _, _)| needed(*id)) {
+            Some((id, lon, lat)) => (
+                if lons.contains(&lon) && lats.contains(&lat) { LinkedList::from([id]) } else { LinkedList::new() },
+                if strip_lons.contains(&lon) && lat >= min_lat - REGION_MARGIN { LinkedList::from([(id, (lon, lat))]) } else { LinkedList::new() },
+            ),
+            None => (LinkedList::new(), LinkedList::new()),
+        },
+        || (LinkedList::new(), LinkedList::new()),
+        |mut a, mut b| {
+            a.0.append(&mut b.0);
+            a.1.append(&mut b.1);
+            a
+        }
+    )?;
+    println!("2/5: Finished in {} sek", now.elapsed()?.as_secs());
+    Ok(RegionNodes { inside: inside.into_iter().collect(), strip: strip.into_iter().collect() })
+}
 
-    let coastline_coordinates: Vec<Vec<Vec<f32>>> = coastline.into_iter().map(|way| way.into_iter().map(|point_ref| {
-        let coordinates = coordinates.get(&point_ref).unwrap();
-        vec![coordinates.0, coordinates.1]
-    }).collect()).collect();     // merge ways with coordinates and convert coordinates to vector
-    println!("3/4: Finished in {} sek", now.elapsed()?.as_secs());
+/**
+ * true if the ring goes around the point, i.e. an odd number of its edges crosses the meridian north of the point;
+ * only edges with both nodes in the strip are known, the edges of coastlines are much shorter than its margin
+ */
+fn surrounds(ring: &[i64], strip: &HashMap<i64, (f32, f32)>, (lon, lat): (f32, f32)) -> bool {
+    ring.windows(2).filter(|pair| match (strip.get(&pair[0]), strip.get(&pair[1])) {
+        (Some(&(lon1, lat1)), Some(&(lon2, lat2))) if (lon1 < lon) != (lon2 < lon) => lat1 + (lat2 - lat1) * (lon - lon1) / (lon2 - lon1) > lat,
+        _ => false,
+    }).count() % 2 == 1
+}
+
+/**
+ * keeps the coastlines and waterways with at least one node in the region and the rings around the whole region,
+ * e.g. the continent of a region far inland; so only their coordinates are read and kept in the following stages
+ */
+fn keep_in_region(connected: ConnectedCoastlines, waterways: Vec<WaterwayWay>, nodes: &RegionNodes, region: &Region) -> (ConnectedCoastlines, Vec<WaterwayWay>) {
+    let touches = |refs: &Vec<i64>| refs.iter().any(|id| nodes.inside.contains(id));
+    let [[min_lon, _], [min_lat, _]] = region.get_bounding_box();
+    let (n_coastlines, n_waterways) = (connected.complete.len() + connected.open.len(), waterways.len());
+    let (touching, around): (Vec<Vec<i64>>, Vec<Vec<i64>>) = connected.complete.into_iter()
+        .filter(|ring| touches(ring) || surrounds(ring, &nodes.strip, (min_lon, min_lat)))
+        .partition(touches);
+    let n_around = around.len();
+    let kept = ConnectedCoastlines {
+        complete: touching.into_iter().chain(around).collect(),
+        open: connected.open.into_iter().filter(touches).collect(),
+        reversed: connected.reversed.into_iter().filter(touches).collect(),
+    };
+    let waterways: Vec<WaterwayWay> = waterways.into_iter().filter(|waterway| touches(&waterway.refs)).collect();
+    println!(
+        "2/5: Kept {} of {} coastlines, {} of them around the region, and {} of {} waterways in the region",
+        kept.complete.len() + kept.open.len(), n_coastlines, n_around, waterways.len(), n_waterways
+    );
+    (kept, waterways)
+}
+
+/**
+ * merges the coastlines and waterways with the coordinates of their nodes, closes open coastlines, clips them to the region and writes them
+ */
+fn repair_and_write(
+    connected: ConnectedCoastlines, waterways: Vec<WaterwayWay>, coordinates: impl Fn(i64) -> Option<(f32, f32)>, prefix: &str, region: Option<&Region>
+) -> Result<(), Box<dyn Error>> {
+    let mut missing_nodes = 0;
+    let mut to_coordinates = |ways: Vec<Vec<i64>>| -> Vec<Vec<Vec<f32>>> {
+        ways.into_iter().map(|way| way.into_iter().filter_map(|point_ref| match coordinates(point_ref) {
+            Some(coordinates) => Some(vec![coordinates.0, coordinates.1]),
+            None => {
+                missing_nodes += 1;
+                None
+            }
+        }).collect()).collect()     // merge ways with coordinates and convert coordinates to vector
+    };
+    let mut coastline_coordinates = to_coordinates(connected.complete);
+    let open_coordinates = to_coordinates(connected.open);
+    let reversed_coordinates = to_coordinates(connected.reversed);
+    let (areas, lines): (Vec<WaterwayWay>, Vec<WaterwayWay>) = waterways.into_iter().partition(|waterway| waterway.area);
+    let area_coordinates = to_coordinates(areas.into_iter().map(|area| area.refs).collect());
+    let mut waterway_coordinates: Vec<Waterway> = vec![];
+    for line in lines {
+        let course = to_coordinates(vec![line.refs]).remove(0);
+        let parts = match region {
+            Some(region) => split_to_region(course, region),
+            None => vec![course],
+        };
+        waterway_coordinates.extend(parts.into_iter().filter(|part| part.len() > 1).map(|course| Waterway { corridor: line.corridor.clone(), course }));
+    }
+    if missing_nodes > 0 {
+        println!("Skipped {} nodes without coordinates", missing_nodes);
+    }
+
+    println!("4/5: Close open coastlines along the bounding box...");
+    let now = SystemTime::now();
+    let bbox = bounding_box(coastline_coordinates.iter().chain(&open_coordinates));
+    // in a region the coastlines are cut where they leave it, and closed along its border
+    let (open_coordinates, bbox) = match region {
+        Some(region) => {
+            let [[min_lon, max_lon], [min_lat, max_lat]] = region.get_bounding_box();
+            let bbox = [[min_lon.max(bbox[0][0]), max_lon.min(bbox[0][1])], [min_lat.max(bbox[1][0]), max_lat.min(bbox[1][1])]];
+            (open_coordinates.into_iter().flat_map(|fragment| split_at_bbox(fragment, bbox)).collect(), bbox)
+        },
+        None => (open_coordinates, bbox),
+    };
+    let repaired = close_along_bbox(open_coordinates, bbox);
+    println!("4/5: Closed {} rings along the bounding box {:?}", repaired.closed.len(), bbox);
+    for fragment in &repaired.unresolved {
+        println!(
+            "4/5: Unresolved coastline with {} points from {:?} to {:?}",
+            fragment.len(), fragment.first().unwrap_or(&vec![]), fragment.last().unwrap_or(&vec![])
+        );
+    }
+    let diagnostics: Vec<(&str, Vec<Vec<f32>>)> = repaired.unresolved.into_iter().map(|line| ("unresolved", line))
+        .chain(reversed_coordinates.into_iter().map(|line| ("reversed", line)))
+        .chain(repaired.border_segments.into_iter().map(|line| ("closed_along_bbox", line)))
+        .collect();
+    if !diagnostics.is_empty() {
+        let diagnostics_path = format!("./data/geojson/{}_diagnostics.json", prefix);
+        write_lines_to_geojson(&diagnostics_path, &diagnostics)?;
+        println!("4/5: Wrote {} problem segments to {}", diagnostics.len(), diagnostics_path);
+    }
+    coastline_coordinates.extend(repaired.closed);
+    if let Some(region) = region {
+        let n_coastlines = coastline_coordinates.len();
+        let clipped = clip_to_region(coastline_coordinates, region);
+        coastline_coordinates = clipped.polygons;
+        println!(
+            "4/5: Clipped {} coastlines to {} rings in the region {:?}, {} of them holes",
+            n_coastlines, coastline_coordinates.len(), region.get_bounding_box(), clipped.holes
+        );
+        region.write_geojson(prefix)?;
+    } else {
+        Region::remove_of_import(prefix)?;
+    }
+    println!("4/5: Finished in {} sek", now.elapsed()?.as_secs());
 
-    println!("4/4: Write GeoJSON ...");
+    println!("5/5: Write GeoJSON ...");
     let now = SystemTime::now();
-    print_geojson(coastline_coordinates, prefix, false);
-    println!("4/4: Finished in {} sek", now.elapsed()?.as_secs());
+    print_geojson(coastline_coordinates, prefix);
+    let estimated = estimate_widths(&mut waterway_coordinates, &area_coordinates);
+    write_waterways(&waterway_coordinates, prefix)?;
+    println!("5/5: Wrote {} waterways, {} with the width of their area", waterway_coordinates.len(), estimated);
+    println!("5/5: Finished in {} sek", now.elapsed()?.as_secs());
 
     Ok(())
 }
 
+/**
+ * rings of the land polygons clipped to a region and the number of holes among them
+ */
+pub struct ClippedCoastlines {
+    pub polygons: Vec<Vec<Vec<f32>>>,
+    pub holes: usize,
+}
+
+/**
+ * Intersects the closed coastlines with the region. Coastlines outside of the bounding box of the region are dropped
+ * before, so only the relevant ones are clipped. The results are closed rings with the land on the left like the coastlines of OSM:
+ * outer rings counter clockwise and holes, e.g. of a lake cut by the region border, clockwise.
+ */
+pub fn clip_to_region(coastlines: Vec<Vec<Vec<f32>>>, region: &Region) -> ClippedCoastlines {
+    let to_polygon = |ring: &[Vec<f32>]| Polygon::new(
+        LineString::from(ring.iter().map(|point| Coord { x: point[0] as f64, y: point[1] as f64 }).collect::<Vec<_>>()), vec![]
+    );
+    let region_polygon = to_polygon(&region.get_polygon());
+    let [[min_lon, max_lon], [min_lat, max_lat]] = region.get_bounding_box();
+
+    let clipped: Vec<(Vec<Vec<Vec<f32>>>, usize)> = coastlines.par_iter()
+        .filter(|coastline| coastline.len() > 3)
+        .filter(|coastline| {
+            let [[lon_from, lon_to], [lat_from, lat_to]] = bounding_box(std::iter::once(*coastline));
+            lon_from <= max_lon && lon_to >= min_lon && lat_from <= max_lat && lat_to >= min_lat
+        })
+        .map(|coastline| {
+            let polygon = to_polygon(coastline);
+            // a clockwise coastline surrounds water like an inland sea, so its clipped rings stay clockwise
+            let is_hole = polygon.signed_area() < 0.0;
+            let intersection = polygon.intersection(&region_polygon).orient(if is_hole { Direction::Reversed } else { Direction::Default });
+            let holes = intersection.iter().map(|polygon| polygon.interiors().len()).sum::<usize>()
+                + if is_hole { intersection.0.len() } else { 0 };
+            let rings = intersection.into_iter()
+                .flat_map(|polygon| {
+                    let (exterior, interiors) = polygon.into_inner();
+                    std::iter::once(exterior).chain(interiors)
+                })
+                .map(|ring| ring.coords().map(|coord| vec![coord.x as f32, coord.y as f32]).collect())
+                .collect();
+            (rings, holes)
+        })
+        .collect();
+    ClippedCoastlines {
+        holes: clipped.iter().map(|(_, holes)| holes).sum(),
+        polygons: clipped.into_iter().flat_map(|(rings, _)| rings).collect(),
+    }
+}
+
+/**
+ * coastlines are split by their number of points into these classes, sorted from big to small;
+ * the number is the index of the first coastline of the next class
+ */
+pub const SIZE_CLASSES: [(&str, usize); 4] = [("continents", 10), ("big_islands", 1000), ("islands", 20000), ("small_islands", usize::MAX)];
+
+/**
+ * ranges of the size classes in a list of coastlines, which is sorted from big to small
+ */
+pub fn size_class_ranges(n_coastlines: usize) -> Vec<(&'static str, Range<usize>)> {
+    let mut start = 0;
+    SIZE_CLASSES.iter().map(|(name, end)| {
+        let range = start.min(n_coastlines)..(*end).min(n_coastlines);
+        start = *end;
+        (*name, range)
+    }).collect()
+}
+
 /**
  * save list of coordinates in geojson formatted file;
  * writes in 4 file to enable multithreading
  */
-pub fn print_geojson(mut coastlines: Vec<Vec<Vec<f32>>>, prefix: &str, reduce: bool) {
+pub fn print_geojson(mut coastlines: Vec<Vec<Vec<f32>>>, prefix: &str) {
     coastlines.sort_by(|a,b| b.len().cmp(&a.len()));
-    if reduce {
-        coastlines = reduces_coastlines(coastlines);
-    }
-
-    const N_CONTINENTS: usize = 10;
-    const N_BIG_ISLANDS: usize = 1000;
-    const N_ISLANDS: usize = 20000;
-    
-    let empty_vec = vec![];
 
     // split islands in 4 lists to make parallel file writing possible
-    let continents = if coastlines.len() > N_CONTINENTS {&coastlines[..N_CONTINENTS]} else {&coastlines[..coastlines.len()]};
-    let big_islands = if coastlines.len() < N_CONTINENTS {&empty_vec} else {
-        if coastlines.len() > N_BIG_ISLANDS {&coastlines[N_CONTINENTS..N_BIG_ISLANDS]} else {&coastlines[N_CONTINENTS..coastlines.len()]}
-    };
-    let islands = if coastlines.len() < N_BIG_ISLANDS {&empty_vec} else {
-        if coastlines.len() > N_ISLANDS {&coastlines[N_BIG_ISLANDS..N_ISLANDS]} else {&coastlines[N_BIG_ISLANDS..coastlines.len()]}
-    };
-    let small_islands = if coastlines.len() < N_ISLANDS {&empty_vec} else {
-        &coastlines[N_ISLANDS..coastlines.len()]
-    };
-
-    let iterator_objects = [("continents", continents), ("big_islands", big_islands), ("islands", islands), ("small_islands", small_islands)];
-    iterator_objects.par_iter().for_each(|file| {
-        let filename = prefix.to_owned() + "_" + file.0;
+    size_class_ranges(coastlines.len()).par_iter().for_each(|(class_name, range)| {
+        let filename = prefix.to_owned() + "_" + class_name;
         let now = SystemTime::now();
-        let json = "{\"coordinates\":".to_owned() + &format!("{:?}", file.1) + ",\"type\":\"MultiLineString\"}";
-        fs::write(format!("./data/geojson/{}.json", filename), json).expect("Unable to write file");
+        write_coastlines_to_geojson(&format!("./data/geojson/{}.json", filename), &coastlines[range.clone()]).expect("Unable to write file");
         println!("Finished {} in {} sek", filename, now.elapsed().unwrap().as_secs());
     });
     println!("Exit parallel");
 }
 
 /**
- * for developement to allow faster file reads and faster point in polygon tests
+ * references to nodes of the ways with tag coastline and of navigable waterways
  */
-fn reduces_coastlines(mut coastlines: Vec<Vec<Vec<f32>>>) -> Vec<Vec<Vec<f32>>> {
-    return coastlines.par_iter_mut().filter(|a| a.len() > 400).map(|a| {
-            let mut reduced_line: Vec<Vec<f32>> = a.iter().step_by(100).map(|a| a.to_owned()).collect();
-            reduced_line.push(a.last().unwrap().to_owned());
-            return reduced_line;
-    }).collect()
+#[derive(Serialize, Deserialize)]
+pub struct OsmWays {
+    pub coastlines: Vec<Vec<i64>>,
+    pub waterways: Vec<WaterwayWay>,
 }
 
 /**
- *  reads and filters the ways with tag coastline
- * returns: list of references to nodes
+ *  reads and filters the ways with tag coastline and the navigable waterways
  */
-fn read_coastline(path: &str) -> Vec<Vec<i64>> {
-    let reader = ElementReader::from_path(path);
+fn read_ways(path: &str) -> Result<OsmWays, osmpbf::Error> {
+    let reader = ElementReader::from_path(path)?;
 
-    match reader.unwrap().par_map_reduce(
+    let (coastlines, waterways) = reader.par_map_reduce(
         |element| {
             match element {
                 Element::Way(way) => {
                     if way.tags().any(|key_value| key_value == ("natural", "coastline")) {
-                        LinkedList::from([way.refs().collect()])
+                        (LinkedList::from([way.refs().collect()]), LinkedList::new())
+                    } else if way.tags().any(|(key, value)| key == "waterway" || (key, value) == ("natural", "water")) {
+                        let tags: HashMap<&str, &str> = way.tags().collect();
+                        let refs: Vec<i64> = way.refs().collect();
+                        (LinkedList::new(), navigable_waterway(&tags, &refs).into_iter().collect())
                     } else {
-                        LinkedList::new()
+                        (LinkedList::new(), LinkedList::new())
                     }
                 },
-                _ => LinkedList::new(),
+                _ => (LinkedList::new(), LinkedList::new()),
             }
         },
-        || LinkedList::new(),
+        || (LinkedList::new(), LinkedList::new()),
         |mut a, mut b| {
-            a.append(&mut b);
+            a.0.append(&mut b.0);
+            a.1.append(&mut b.1);
             a
         }
-    ) {
-        Ok(ways) => return ways.into_iter().collect(),
-        Err(e) => {
-            println!("{}", e.to_string());
-            return vec![]
-        }
-    };
+    )?;
+    Ok(OsmWays { coastlines: coastlines.into_iter().collect(), waterways: waterways.into_iter().collect() })
 }
 
 
 /**
- *  read coordinates of point ids from ways as vectors
+ *  read coordinates of the given point ids
  */
-fn read_coordinates(path: &str, ways: &Vec<Vec<i64>>) -> HashMap<i64, (f32, f32)> {
-    let reader = ElementReader::from_path(path);
-    let node_set: HashSet<i64> = ways.to_owned().into_iter().reduce(|mut way_a, mut way_b| {
-        way_a.append(&mut way_b);
-        return way_a
-    }).unwrap().into_iter().collect();   //refs of nodes into HashSet
+fn read_coordinates(path: &str, node_set: &HashSet<i64>) -> Result<HashMap<i64, (f32, f32)>, osmpbf::Error> {
+    let reader = ElementReader::from_path(path)?;
 
-    match reader.unwrap().par_map_reduce(
+    let coordinates = reader.par_map_reduce(
         |element| {
-            match element {
-                Element::DenseNode(node) => {
-                    if node_set.contains(&node.id) {    // add coordinates to loop vector which will be returned
-                        LinkedList::from([(node.id, (node.lon() as f32, node.lat() as f32))])
-                    } else {
-                        LinkedList::new()
-                    }
+            match node_of(&element) {
+                Some((id, lon, lat)) if node_set.contains(&id) => {    // add coordinates to loop vector which will be returned
+                    LinkedList::from([(id, (lon, lat))])
                 },
                 _ => LinkedList::new(),
             }
@@ -151,70 +425,315 @@ fn read_coordinates(path: &str, ways: &Vec<Vec<i64>>) -> HashMap<i64, (f32, f32)
             a.append(&mut b);         // merge vectors of parallel operations
             return a;
         }
-    ) {
-        Ok(ways) => return ways.into_iter().collect(),
-        Err(e) => {
-            println!("{}", e.to_string());
-            return HashMap::new()
-        }
-    };
+    )?;
+    Ok(coordinates.into_iter().collect())
 }
 
 /**
- * connect list of coastline edges to islands and continents
+ * coastlines connected from ways; open ones couldn't be closed, reversed are the parts which were turned around
  */
-pub fn connect_coastlines(ways: Vec<Vec<i64>>) -> Vec<Vec<i64>> {
-    let mut start_nodes: HashMap<i64, Vec<i64>> = HashMap::new();  // refers to incomplete_coastlines
-    let mut end_nodes: HashMap<i64, Vec<i64>> = HashMap::new();    // refers to incomplete_coastlines
+#[derive(Serialize, Deserialize)]
+pub struct ConnectedCoastlines {
+    pub complete: Vec<Vec<i64>>,
+    pub open: Vec<Vec<i64>>,
+    pub reversed: Vec<Vec<i64>>,
+}
+
+/**
+ * connect list of coastline edges to islands and continents;
+ * if two parts meet with their starts or with their ends, the shorter one is reversed
+ */
+pub fn connect_coastlines(ways: Vec<Vec<i64>>) -> ConnectedCoastlines {
+    let mut fragments: Vec<Vec<i64>> = vec![];     // incomplete coastlines, empty if merged into another one
+    let mut endpoints: HashMap<i64, usize> = HashMap::new();      // first and last node of incomplete coastlines
     let mut complete_coastlines: Vec<Vec<i64>> = vec![];    // contains only full coastlines, where the last point has the same id as the first
+    let mut reversed: Vec<Vec<i64>> = vec![];
 
-    for i in 0..ways.len() {
-        if ways[i][0].to_owned() == ways[i][ways[i].len().to_owned()-1].to_owned() {
-            complete_coastlines.push(ways[i].to_owned());
-            continue;
-        }
-        let end_connection = start_nodes.remove(&ways[i][ways[i].len()-1]);
-        let start_connection = end_nodes.remove(&ways[i][0]);
-
-        // should not occur
-        let start_start_connection = start_nodes.remove(&ways[i][0]);
-        let end_end_connection = end_nodes.remove(&ways[i][ways[i].len()-1]);
-        
-        let mut new_coastline: Vec<i64> = vec![];
-
-        match (end_connection, start_connection, end_end_connection, start_start_connection) {
-            (Some(following_coastline), None, None, None) => {
-                new_coastline = ways[i].to_owned();
-                new_coastline.append(&mut following_coastline[1..].to_vec());
+    for mut coastline in ways.into_iter().filter(|way| way.len() > 1) {
+        loop {
+            if coastline.first() == coastline.last() {
+                complete_coastlines.push(coastline);
+                break;
             }
-            (None, Some(leading_coastline), None, None) => {
-                new_coastline = leading_coastline;
-                new_coastline.append(&mut ways[i][1..].to_vec());
-            }
-            (Some(following_coastline), Some(leading_coastline), None, None) => {
-                if following_coastline[0] == leading_coastline[0] {
-                    new_coastline = leading_coastline;
-                    new_coastline.append(&mut ways[i][1..].to_vec());
-                    complete_coastlines.push(new_coastline);
-                    continue;
+            let (first, last) = (coastline[0], coastline[coastline.len() - 1]);
+            let (other_i, at_end) = if let Some(other_i) = endpoints.remove(&last) {
+                (other_i, true)
+            } else if let Some(other_i) = endpoints.remove(&first) {
+                (other_i, false)
+            } else {
+                endpoints.insert(first, fragments.len());
+                endpoints.insert(last, fragments.len());
+                fragments.push(coastline);
+                break;
+            };
+            let mut other = std::mem::take(&mut fragments[other_i]);
+            let (other_first, other_last) = (other[0], other[other.len() - 1]);
+            let connecting_node = if at_end { last } else { first };
+            endpoints.remove(if other_first == connecting_node { &other_last } else { &other_first });
+
+            // the connection has to be from an end to a start, otherwise one of them is in the wrong direction
+            let same_direction = if at_end { other_first == last } else { other_last == first };
+            if !same_direction {
+                if other.len() < coastline.len() {
+                    reversed.push(other.to_owned());
+                    other.reverse();
+                } else {
+                    reversed.push(coastline.to_owned());
+                    coastline.reverse();
                 }
-                new_coastline = leading_coastline;
-                new_coastline.append(&mut ways[i][1..].to_vec());
-                new_coastline.append(&mut following_coastline[1..].to_vec());
             }
-            (None, None, None, None) => {
-                new_coastline = ways[i].to_owned();
-            }
-            (_, _, _, _) => {
-                println!("Start-start or an end-end connection was encountered. Should not occur!")
+            coastline = if coastline[coastline.len() - 1] == other[0] {
+                coastline.extend_from_slice(&other[1..]);
+                coastline
+            } else {
+                other.extend_from_slice(&coastline[1..]);
+                other
+            };
+        }
+    }
+    ConnectedCoastlines {
+        complete: complete_coastlines,
+        open: fragments.into_iter().filter(|fragment| !fragment.is_empty()).collect(),
+        reversed,
+    }
+}
+
+/**
+ * bounding box of all points: [[min_lon, max_lon], [min_lat, max_lat]]
+ */
+fn bounding_box<'a>(coastlines: impl Iterator<Item = &'a Vec<Vec<f32>>>) -> [[f32; 2]; 2] {
+    coastlines.flatten().fold([[180.0, -180.0], [90.0, -90.0]], |[[min_lon, max_lon], [min_lat, max_lat]], point| {
+        [[min_lon.min(point[0]), max_lon.max(point[0])], [min_lat.min(point[1]), max_lat.max(point[1])]]
+    })
+}
+
+/**
+ * parts of the coastline inside of the bounding box, which start and end on its border where the coastline crosses it
+ */
+pub fn split_at_bbox(coastline: Vec<Vec<f32>>, bbox: [[f32; 2]; 2]) -> Vec<Vec<Vec<f32>>> {
+    let [[min_lon, max_lon], [min_lat, max_lat]] = bbox;
+    let mut parts: Vec<Vec<Vec<f32>>> = vec![];
+    let mut continued = false;
+    for segment in coastline.windows(2) {
+        let (from, to) = (&segment[0], &segment[1]);
+        let (d_lon, d_lat) = (to[0] - from[0], to[1] - from[1]);
+        // clip the segment to the part between t_in and t_out of its length
+        let (mut t_in, mut t_out) = (0.0f32, 1.0f32);
+        let mut visible = true;
+        for (direction, space) in [
+            (-d_lon, from[0] - min_lon), (d_lon, max_lon - from[0]), (-d_lat, from[1] - min_lat), (d_lat, max_lat - from[1])
+        ] {
+            if direction == 0.0 {
+                visible &= space >= 0.0;
+            } else if direction < 0.0 {
+                t_in = t_in.max(space / direction);
+            } else {
+                t_out = t_out.min(space / direction);
             }
         }
-        if new_coastline.len() > 1 {
-            end_nodes.insert(*new_coastline.last().unwrap(), new_coastline.to_owned());
-            start_nodes.insert(*new_coastline.first().unwrap(),new_coastline);
+        if !visible || t_in > t_out {
+            continued = false;
+            continue;
+        }
+        let at = |t: f32| vec![from[0] + t * d_lon, from[1] + t * d_lat];
+        if !continued {
+            parts.push(vec![if t_in == 0.0 { from.to_owned() } else { at(t_in) }]);
         }
+        parts.last_mut().unwrap().push(if t_out == 1.0 { to.to_owned() } else { at(t_out) });
+        continued = t_out == 1.0;
     }
-    assert_eq!(end_nodes.len(), 0);     // if not zero, there is a coastline where beginning and ending are not connected
-    assert_eq!(start_nodes.len(), 0);   // if not zero, there is a coastline where beginning and ending are not connected
-    return complete_coastlines
-}
\ No newline at end of file
+    parts
+}
+
+/**
+ * maximal distance of an end of a coastline to the border of the bounding box, to be closed along the border;
+ * relative to the bigger side of the bounding box
+ */
+const BORDER_TOLERANCE: f32 = 0.01;
+
+/**
+ * result of closing open coastlines: closed rings, coastlines which couldn't be closed and the added segments along the border
+ */
+pub struct ClosedFragments {
+    pub closed: Vec<Vec<Vec<f32>>>,
+    pub unresolved: Vec<Vec<Vec<f32>>>,
+    pub border_segments: Vec<Vec<Vec<f32>>>,
+}
+
+/**
+ * coastline with position and point on the border for its start and its end
+ */
+type BorderFragment = (Vec<Vec<f32>>, f32, Vec<f32>, f32, Vec<f32>);
+
+/**
+ * Closes coastlines, which were cut at the border of a regional extract, along the bounding box.
+ * Both ends have to be close to the border. The land is on the left side of OSM coastlines,
+ * so from the end of a coastline the border is followed counter clockwise up to the next start of a coastline.
+ */
+pub fn close_along_bbox(fragments: Vec<Vec<Vec<f32>>>, bbox: [[f32; 2]; 2]) -> ClosedFragments {
+    let [[min_lon, max_lon], [min_lat, max_lat]] = bbox;
+    let (width, height) = (max_lon - min_lon, max_lat - min_lat);
+    let perimeter = 2.0 * (width + height);
+    let tolerance = BORDER_TOLERANCE * width.max(height);
+    // corners with their position on the border, counter clockwise from the south west corner
+    let corners = [
+        (width, vec![max_lon, min_lat]),
+        (width + height, vec![max_lon, max_lat]),
+        (2.0 * width + height, vec![min_lon, max_lat]),
+        (perimeter, vec![min_lon, min_lat]),
+    ];
+    let distance_on_border = |from: f32, to: f32| (to - from).rem_euclid(perimeter);
+    // position on the border and the point moved onto the border
+    let border_position = |point: &Vec<f32>| -> Option<(f32, Vec<f32>)> {
+        let (lon, lat) = (point[0].clamp(min_lon, max_lon), point[1].clamp(min_lat, max_lat));
+        let sides = [
+            (lat - min_lat, lon - min_lon, vec![lon, min_lat]),
+            (max_lon - lon, width + lat - min_lat, vec![max_lon, lat]),
+            (max_lat - lat, width + height + max_lon - lon, vec![lon, max_lat]),
+            (lon - min_lon, 2.0 * width + height + max_lat - lat, vec![min_lon, lat]),
+        ];
+        let (distance, position, snapped) = sides.into_iter().min_by(|a, b| a.0.total_cmp(&b.0)).unwrap();
+        if distance > tolerance { None } else { Some((position, snapped)) }
+    };
+
+    let mut result = ClosedFragments { closed: vec![], unresolved: vec![], border_segments: vec![] };
+    let mut closable: Vec<BorderFragment> = vec![];
+    for fragment in fragments {
+        match (fragment.first().and_then(border_position), fragment.last().and_then(border_position)) {
+            (Some((start_position, start)), Some((end_position, end))) => {
+                closable.push((fragment, start_position, start, end_position, end))
+            },
+            _ => result.unresolved.push(fragment),
+        }
+    }
+
+    let mut used = vec![false; closable.len()];
+    for first_i in 0..closable.len() {
+        if used[first_i] { continue }
+        let mut ring: Vec<Vec<f32>> = vec![];
+        let mut ring_segments: Vec<Vec<Vec<f32>>> = vec![];
+        let mut current_i = first_i;
+        loop {
+            used[current_i] = true;
+            let (fragment, _, _, end_position, end_point) = &closable[current_i];
+            ring.extend(fragment.iter().cloned());
+            // next start counter clockwise along the border, which is not used yet; at least the first one
+            let next_i = (0..closable.len())
+                .filter(|i| !used[*i] || *i == first_i)
+                .min_by(|a, b| {
+                    distance_on_border(*end_position, closable[*a].1).total_cmp(&distance_on_border(*end_position, closable[*b].1))
+                })
+                .unwrap();
+            let (_, start_position, start_point, _, _) = &closable[next_i];
+            let distance = distance_on_border(*end_position, *start_position);
+
+            let mut passed_corners: Vec<&(f32, Vec<f32>)> = corners.iter()
+                .filter(|(position, _)| {
+                    let corner_distance = distance_on_border(*end_position, *position);
+                    corner_distance > 0.0 && corner_distance < distance
+                })
+                .collect();
+            passed_corners.sort_by(|a, b| distance_on_border(*end_position, a.0).total_cmp(&distance_on_border(*end_position, b.0)));
+            let mut segment = vec![end_point.to_owned()];
+            segment.extend(passed_corners.into_iter().map(|(_, corner)| corner.to_owned()));
+            segment.push(start_point.to_owned());
+            for point in &segment {
+                if ring.last() != Some(point) { ring.push(point.to_owned()) }
+            }
+            ring_segments.push(segment);
+
+            if next_i == first_i { break }
+            current_i = next_i;
+        }
+        if ring.first() != ring.last() { ring.push(ring[0].to_owned()) }
+        result.closed.push(ring);
+        result.border_segments.append(&mut ring_segments);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use graph_lib::region::Region;
+
+    use std::collections::{HashMap, HashSet};
+    use crate::import_pbf::{connect_coastlines, close_along_bbox, clip_to_region, split_at_bbox, keep_in_region, ConnectedCoastlines, RegionNodes};
+
+    #[test]
+    fn test_connect_reversed_way() {
+        let ways = vec![vec![1, 2, 3], vec![5, 4, 3], vec![5, 6, 1], vec![10, 11], vec![12, 13, 12]];
+        let connected = connect_coastlines(ways);
+        assert_eq!(connected.complete.len(), 2);
+        assert_eq!(connected.complete[0], vec![5, 6, 1, 2, 3, 4, 5]);
+        assert_eq!(connected.reversed, vec![vec![5, 4, 3]]);
+        assert_eq!(connected.open, vec![vec![10, 11]]);
+    }
+
+    #[test]
+    fn test_keep_region_inland() {
+        // the region from (0, 0) to (1, 1) lies in the middle of ring 1 to 6 with edge 4 to 5 across its meridian in the north;
+        // ring 10 to 13 crosses the meridian in the south and ring 20 to 22 touches the region
+        let strip = HashMap::from([(4, (0.5, 10.0)), (5, (-0.5, 10.0)), (11, (0.5, -0.5)), (12, (-0.5, -0.5))]);
+        let nodes = RegionNodes { inside: HashSet::from([21]), strip };
+        let connected = ConnectedCoastlines {
+            complete: vec![vec![1, 2, 3, 4, 5, 6, 1], vec![10, 11, 12, 13, 10], vec![20, 21, 22, 20]],
+            open: vec![vec![30, 31]],
+            reversed: vec![],
+        };
+        let (kept, _) = keep_in_region(connected, vec![], &nodes, &Region::from_bbox(0.0, 0.0, 1.0, 1.0));
+        assert_eq!(kept.complete, vec![vec![20, 21, 22, 20], vec![1, 2, 3, 4, 5, 6, 1]]);
+        assert!(kept.open.is_empty());
+    }
+
+    #[test]
+    fn test_close_along_bbox() {
+        // a coastline from the east to the north border with land in the south west, and one in the middle
+        let fragments = vec![
+            vec![vec![10.0, 2.0], vec![6.0, 3.0], vec![5.0, 10.0]],
+            vec![vec![4.0, 4.0], vec![5.0, 5.0]],
+        ];
+        let result = close_along_bbox(fragments, [[0.0, 10.0], [0.0, 10.0]]);
+        assert_eq!(result.unresolved.len(), 1);
+        assert_eq!(result.closed, vec![vec![
+            vec![10.0, 2.0], vec![6.0, 3.0], vec![5.0, 10.0], vec![0.0, 10.0], vec![0.0, 0.0], vec![10.0, 0.0], vec![10.0, 2.0]
+        ]]);
+    }
+
+    #[test]
+    fn test_split_at_bbox() {
+        // a coastline coming from the east, leaving the box to the north and cutting off its north west corner
+        let coastline = vec![vec![12.0, 5.0], vec![8.0, 5.0], vec![8.0, 12.0], vec![-1.0, 12.0], vec![-1.0, 8.0], vec![3.0, 12.0]];
+        let parts = split_at_bbox(coastline, [[0.0, 10.0], [0.0, 10.0]]);
+        assert_eq!(parts, vec![
+            vec![vec![10.0, 5.0], vec![8.0, 5.0], vec![8.0, 10.0]],
+            vec![vec![0.0, 9.0], vec![1.0, 10.0]],
+        ]);
+        // the parts of an open coastline end on the border, so they can be closed along it
+        let closed = close_along_bbox(parts[..1].to_vec(), [[0.0, 10.0], [0.0, 10.0]]);
+        assert!(closed.unresolved.is_empty() && closed.closed.len() == 1);
+    }
+
+    #[test]
+    fn test_clip_to_region() {
+        // an island crossing the eastern border of the region with a lake, and an island outside
+        let coastlines = vec![
+            vec![vec![8.0, 2.0], vec![12.0, 2.0], vec![12.0, 4.0], vec![8.0, 4.0], vec![8.0, 2.0]],
+            vec![vec![9.0, 2.5], vec![9.0, 3.5], vec![11.0, 3.5], vec![11.0, 2.5], vec![9.0, 2.5]],
+            vec![vec![20.0, 2.0], vec![21.0, 2.0], vec![21.0, 3.0], vec![20.0, 2.0]],
+        ];
+        let clipped = clip_to_region(coastlines, &Region::from_bbox(0.0, 0.0, 10.0, 10.0));
+        assert_eq!(clipped.holes, 1);
+        assert_eq!(clipped.polygons.len(), 2);
+        // the lake stays clockwise
+        let lake = &clipped.polygons[1];
+        assert!(lake.iter().all(|point| point[0] >= 9.0 && point[0] <= 10.0));
+        assert!(lake.windows(2).map(|edge| edge[0][0] * edge[1][1] - edge[1][0] * edge[0][1]).sum::<f32>() < 0.0);
+        let polygon = &clipped.polygons[0];
+        assert_eq!(polygon.first(), polygon.last());
+        assert_eq!(polygon.len(), 5);
+        assert!(polygon.iter().all(|point| point[0] >= 8.0 && point[0] <= 10.0 && point[1] >= 2.0 && point[1] <= 4.0));
+        // counter clockwise
+        let area: f32 = polygon.windows(2).map(|edge| edge[0][0] * edge[1][1] - edge[1][0] * edge[0][1]).sum();
+        assert!(area > 0.0);
+    }
+}
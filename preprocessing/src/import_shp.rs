@@ -0,0 +1,129 @@
+use std::{error::Error, fs, path::Path, time::SystemTime};
+use shapefile::{Shape, PolygonRing, Reader, Point, PointM, PointZ};
+use graph_lib::region::Region;
+
+use crate::import_pbf::print_geojson;
+use crate::waterways::remove_waterways;
+
+
+/**
+ * reads land polygons of a shapefile (.shp with .shx and .dbf next to it) and saves their rings as coastlines;
+ * the output has the same format as the PBF import, so the graph generation works the same way
+ */
+pub fn import_shp(path: &str, prefix: &str) -> Result<(), Box<dyn Error>> {
+    println!("1/2: Read polygons of shapefile ...");
+    let now = SystemTime::now();
+    check_projection(path)?;
+    let coastlines = read_shapefile(path)?;
+    println!("1/2: Number of continents and islands:  {}", coastlines.len());
+    println!("1/2: Finished in {} sek", now.elapsed()?.as_secs());
+
+    println!("2/2: Write GeoJSON ...");
+    let now = SystemTime::now();
+    print_geojson(coastlines, prefix);
+    Region::remove_of_import(prefix)?;
+    remove_waterways(prefix)?;
+    println!("2/2: Finished in {} sek", now.elapsed()?.as_secs());
+
+    Ok(())
+}
+
+/**
+ * returns the rings of all polygons with the land on the left like the coastlines of OSM:
+ * outer rings counter clockwise and holes (lakes and inland seas) clockwise
+ */
+fn read_shapefile(path: &str) -> Result<Vec<Vec<Vec<f32>>>, Box<dyn Error>> {
+    let mut reader = Reader::from_path(path)?;
+    let mut coastlines: Vec<Vec<Vec<f32>>> = vec![];
+    let (mut holes, mut skipped_shapes) = (0, 0);
+
+    for shape_record in reader.iter_shapes_and_records() {
+        let (shape, _) = shape_record?;
+        let rings: Vec<(bool, Vec<[f64; 2]>)> = match shape {
+            Shape::Polygon(polygon) => polygon.into_inner().into_iter().map(ring_to_points(|point: &Point| [point.x, point.y])).collect(),
+            Shape::PolygonM(polygon) => polygon.into_inner().into_iter().map(ring_to_points(|point: &PointM| [point.x, point.y])).collect(),
+            Shape::PolygonZ(polygon) => polygon.into_inner().into_iter().map(ring_to_points(|point: &PointZ| [point.x, point.y])).collect(),
+            _ => {
+                skipped_shapes += 1;
+                continue;
+            }
+        };
+        for (is_outer, points) in rings {
+            if !is_outer {
+                holes += 1;
+            }
+            if let Some([lon, lat]) = points.iter().find(|[lon, lat]| lon.abs() > 180.0 || lat.abs() > 90.0) {
+                return Err(format!(
+                    "Coordinate ({}, {}) is no WGS84 coordinate, use the shapefile in EPSG:4326", lon, lat
+                ).into());
+            }
+            // outer rings of shapefiles are clockwise and holes counter clockwise, so both are reversed
+            coastlines.push(points.iter().rev().map(|[lon, lat]| vec![*lon as f32, *lat as f32]).collect());
+        }
+    }
+
+    if holes > 0 {
+        println!("Read {} holes in polygons like lakes and inland seas", holes);
+    }
+    if skipped_shapes > 0 {
+        println!("Skipped {} shapes, which are no polygons", skipped_shapes);
+    }
+    Ok(coastlines)
+}
+
+fn ring_to_points<P>(to_point: impl Fn(&P) -> [f64; 2]) -> impl Fn(PolygonRing<P>) -> (bool, Vec<[f64; 2]>) {
+    move |ring| match ring {
+        PolygonRing::Outer(points) => (true, points.iter().map(&to_point).collect()),
+        PolygonRing::Inner(points) => (false, points.iter().map(&to_point).collect()),
+    }
+}
+
+/**
+ * the .prj file is optional, but if it exists it has to describe geographic coordinates
+ */
+fn check_projection(path: &str) -> Result<(), Box<dyn Error>> {
+    let prj_path = Path::new(path).with_extension("prj");
+    if let Ok(projection) = fs::read_to_string(&prj_path) {
+        if projection.trim_start().starts_with("PROJCS") {
+            return Err(format!(
+                "{} defines a projected coordinate system, use the shapefile in EPSG:4326", prj_path.display()
+            ).into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use shapefile::{Point, Polygon, PolygonRing, Writer, dbase::{self, Record, TableWriterBuilder}};
+
+    use crate::import_shp::read_shapefile;
+
+    #[test]
+    fn test_read_polygon_with_hole() {
+        let path = std::env::temp_dir().join("ship_routing_test_land_polygons.shp");
+        let table_builder = TableWriterBuilder::new().add_numeric_field("FID".try_into().unwrap(), 10, 0);
+        let mut writer = Writer::from_path(&path, table_builder).unwrap();
+        let polygon = Polygon::with_rings(vec![
+            PolygonRing::Outer(vec![Point::new(10.0, 50.0), Point::new(12.0, 50.0), Point::new(12.0, 52.0), Point::new(10.0, 52.0), Point::new(10.0, 50.0)]),
+            PolygonRing::Inner(vec![Point::new(10.5, 50.5), Point::new(11.0, 50.5), Point::new(11.0, 51.0), Point::new(10.5, 50.5)]),
+        ]);
+        let mut record = Record::default();
+        record.insert("FID".to_string(), dbase::FieldValue::Numeric(Some(1.0)));
+        writer.write_shape_and_record(&polygon, &record).unwrap();
+        drop(writer);
+
+        let coastlines = read_shapefile(path.to_str().unwrap()).unwrap();
+        for extension in ["shp", "shx", "dbf"] {
+            std::fs::remove_file(path.with_extension(extension)).unwrap();
+        }
+        assert_eq!(coastlines.len(), 2);
+        assert_eq!(coastlines[0].len(), 5);
+        assert_eq!(coastlines[0].first(), coastlines[0].last());
+        // land on the left like OSM coastlines: positive signed area of the outer ring, negative of the hole
+        let area = |ring: &Vec<Vec<f32>>| -> f32 { ring.windows(2).map(|points| points[0][0] * points[1][1] - points[1][0] * points[0][1]).sum() };
+        assert!(area(&coastlines[0]) > 0.0);
+        assert_eq!(coastlines[1].len(), 4);
+        assert!(area(&coastlines[1]) < 0.0);
+    }
+}
@@ -0,0 +1,87 @@
+use actix_web::{get, post, web, App, HttpServer, Responder};
+use graph_lib::{Coordinates, land_mask::LandMask};
+use serde::{Serialize, Deserialize};
+
+#[derive(Deserialize)]
+struct Point {
+    lon: f32,
+    lat: f32,
+}
+
+#[derive(Deserialize)]
+struct Segment {
+    from_lon: f32,
+    from_lat: f32,
+    to_lon: f32,
+    to_lat: f32,
+}
+
+#[derive(Serialize)]
+struct WaterResult {
+    water: bool,
+}
+
+#[derive(Serialize)]
+struct CrossesLandResult {
+    crosses_land: bool,
+}
+
+#[derive(Serialize)]
+struct CoastDistanceResult {
+    /// in m
+    distance: f32,
+}
+
+/**
+ * GET /water?lon=..&lat=..
+ */
+#[get("/water")]
+async fn water(land_mask: web::Data<LandMask>, point: web::Query<Point>) -> impl Responder {
+    web::Json(WaterResult { water: land_mask.is_water(&Coordinates(point.lon, point.lat)) })
+}
+
+/**
+ * POST /water with a JSON array of points [lon, lat]; returns an array with true for every point in water
+ */
+#[post("/water")]
+async fn water_batch(land_mask: web::Data<LandMask>, points: web::Json<Vec<[f32; 2]>>) -> impl Responder {
+    let points: Vec<Coordinates> = points.iter().map(|point| Coordinates(point[0], point[1])).collect();
+    web::Json(land_mask.are_water(&points))
+}
+
+/**
+ * GET /crosses_land?from_lon=..&from_lat=..&to_lon=..&to_lat=..
+ */
+#[get("/crosses_land")]
+async fn crosses_land(land_mask: web::Data<LandMask>, segment: web::Query<Segment>) -> impl Responder {
+    let crosses_land = land_mask.segment_crosses_land(&Coordinates(segment.from_lon, segment.from_lat), &Coordinates(segment.to_lon, segment.to_lat));
+    web::Json(CrossesLandResult { crosses_land })
+}
+
+/**
+ * GET /coast_distance?lon=..&lat=..
+ */
+#[get("/coast_distance")]
+async fn coast_distance(land_mask: web::Data<LandMask>, point: web::Query<Point>) -> impl Responder {
+    web::Json(CoastDistanceResult { distance: land_mask.distance_to_coast(&Coordinates(point.lon, point.lat)) })
+}
+
+/**
+ * serves the land mask of the coastlines with the prefix as HTTP endpoints on localhost
+ */
+pub async fn serve_land_mask(prefix: &str, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let land_mask = web::Data::new(LandMask::of_prefix(prefix).ok_or(format!("No coastlines with prefix {} found", prefix))?);
+    println!("Serving land mask of {} on http://127.0.0.1:{}", prefix, port);
+    HttpServer::new(move || {
+        App::new()
+            .app_data(land_mask.clone())
+            .service(water)
+            .service(water_batch)
+            .service(crosses_land)
+            .service(coast_distance)
+    })
+    .bind(("127.0.0.1", port))?
+    .run()
+    .await?;
+    Ok(())
+}
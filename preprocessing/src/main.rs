@@ -2,20 +2,37 @@ use std::error::Error;
 use std::time::SystemTime;
 use graph_lib::file_interface::{print_graph_to_file, import_graph_from_file};
 use graph_lib::island::read_geojsons;
+use graph_lib::region::Region;
 use graph_lib::test_polygon_test::static_polygon_tests;
 use regex::Regex;
 
 mod import_pbf;
+mod import_shp;
+mod node_index;
 mod generate_graph;
+mod connection;
+mod simplify;
 mod tools;
+mod waterways;
+mod bathymetry;
+mod ice;
+mod metrics;
+mod land_server;
 
-use crate::import_pbf::{import_pbf, print_geojson};
-use crate::generate_graph::generate_graph;
+use crate::import_pbf::{import_pbf, print_geojson, size_class_ranges, ImportMode};
+use crate::import_shp::import_shp;
+use crate::generate_graph::{generate_graph, compare_connection_strategies, NodeDensity};
+use crate::connection::ConnectionStrategy;
+use crate::simplify::simplify_coastlines;
 use crate::tools::extract_black_sea;
+use crate::bathymetry::add_bathymetry;
+use crate::ice::add_ice;
+use crate::metrics::{add_metric, MetricKind};
+use crate::land_server::serve_land_mask;
 
 #[actix_web::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    const COMMANDS: &str = "import/transform/generate/test";
+    const COMMANDS: &str = "import/import_shp/transform/generate/bathymetry/ice/metric/compare_connections/serve/test";
     let command = std::env::args_os().nth(1)
         .ok_or(format!("need to specify the command, {}", COMMANDS))?;
 
@@ -23,29 +40,97 @@ async fn main() -> Result<(), Box<dyn Error>> {
         Some("import") => {
             let pbf_file = "data/".to_string() + &param_to_string(2, Some("planet.osm.pbf"), Some(Regex::new(r"osm.pbf$")))?;
             let export_prefix = param_to_string(3, Some("complete"), None)?;
+            let mode = match param_to_string(4, Some("in_memory"), Some(Regex::new(r"^(in_memory|on_disk)$")))?.as_str() {
+                "on_disk" => ImportMode::OnDisk,
+                _ => ImportMode::InMemory,
+            };
+            let region = param_to_region(5)?;
     
             let now = SystemTime::now();
             println!("Importing pbf file...");
-            import_pbf(&pbf_file, &export_prefix)?;
+            import_pbf(&pbf_file, &export_prefix, mode, region.as_ref())?;
+            println!("Import completed, overall time: {} sek", now.elapsed()?.as_secs());
+        }
+        Some("import_shp") => {
+            let shp_file = "data/".to_string() + &param_to_string(2, Some("land-polygons-complete-4326/land_polygons.shp"), Some(Regex::new(r"\.shp$")))?;
+            let export_prefix = param_to_string(3, Some("complete"), None)?;
+
+            let now = SystemTime::now();
+            println!("Importing shapefile...");
+            import_shp(&shp_file, &export_prefix)?;
             println!("Import completed, overall time: {} sek", now.elapsed()?.as_secs());
         }
         Some("generate") => {
             let filename_out = param_to_string(2, Some("graph"), None)?;
             let import_prefix = param_to_string(3, Some("complete"), None)?;
+            let density = param_to_density(4)?;
+            let strategy = ConnectionStrategy::from_param(&param_to_string(5, Some("quadrants"), None)?)?;
+            let seed = param_to_seed(6)?;
             
             let now = SystemTime::now();
             println!("Graph generation ...");
-            generate_graph(&filename_out, &import_prefix)?;
+            generate_graph(&filename_out, &import_prefix, density, strategy, seed)?;
             println!("Graph generation completed, overall time: {} sek", now.elapsed()?.as_secs());
         }
-        Some("transform") => {      // for developement
+        Some("bathymetry") => {
+            let graph_name = param_to_string(2, Some("graph"), None)?;
+            let grid_file = "data/".to_string() + &param_to_string(3, Some("gebco.asc"), Some(Regex::new(r"\.asc$")))?;
+            let factor = param_to_string(4, Some("1"), Some(Regex::new(r"^[1-9]\d*$")))?.parse::<usize>()?;
+
+            let now = SystemTime::now();
+            println!("Adding bathymetry ...");
+            add_bathymetry(&graph_name, &grid_file, factor)?;
+            println!("Bathymetry completed, overall time: {} sek", now.elapsed()?.as_secs());
+        }
+        Some("ice") => {
+            let graph_name = param_to_string(2, Some("graph"), None)?;
+            let folder = "data/".to_string() + &param_to_string(3, Some("ice"), None)?;
+
+            let now = SystemTime::now();
+            println!("Adding ice covers ...");
+            add_ice(&graph_name, &folder)?;
+            println!("Ice covers completed, overall time: {} sek", now.elapsed()?.as_secs());
+        }
+        Some("metric") => {
+            let graph_name = param_to_string(2, Some("graph"), None)?;
+            let number = |nth: usize, default: &str| -> Result<f32, Box<dyn Error>> {
+                Ok(param_to_string(nth, Some(default), Some(Regex::new(r"^\d+(\.\d+)?$")))?.parse::<f32>()?)
+            };
+            let kind = match param_to_string(3, Some("time"), Some(Regex::new(r"^(time|fuel|risk)$")))?.as_str() {
+                "fuel" => MetricKind::Fuel { speed: number(4, "14")?, tons_per_day: number(5, "30")? },
+                "risk" => MetricKind::Risk { zones_file: "data/".to_string() + &param_to_string(4, Some("zones.json"), None)? },
+                _ => MetricKind::Time { speed: number(4, "14")? },
+            };
+
+            let now = SystemTime::now();
+            println!("Adding metric {} ...", kind.name());
+            add_metric(&graph_name, &kind)?;
+            println!("Metric completed, overall time: {} sek", now.elapsed()?.as_secs());
+        }
+        Some("compare_connections") => {
+            let import_prefix = param_to_string(2, Some("complete"), None)?;
+            let number_of_nodes = param_to_string(3, Some("500000"), Some(Regex::new(r"^\d+$")))?.parse::<u32>()?;
+            let density = param_to_density(4)?;
+            let seed = param_to_seed(5)?;
+
+            let now = SystemTime::now();
+            println!("Comparing connection strategies ...");
+            compare_connection_strategies(&import_prefix, number_of_nodes, density, seed)?;
+            println!("Comparison completed, overall time: {} sek", now.elapsed()?.as_secs());
+        }
+        Some("serve") => {
+            let import_prefix = param_to_string(2, Some("complete"), None)?;
+            let port = param_to_string(3, Some("8080"), Some(Regex::new(r"^\d+$")))?.parse::<u16>()?;
+            serve_land_mask(&import_prefix, port).await?;
+        }
+        Some("transform") => {
             let import_prefix = std::env::args_os().nth(2).ok_or("specify an import prefix")?;
             let export_prefix = std::env::args_os().nth(3).ok_or("specify an export prefix")?;
-            let reduce = param_to_string(4, Some("complete"), Some(Regex::new(r"true|false|f$")))?.trim() == "true";
+            let tolerance = param_to_string(4, Some("0"), Some(Regex::new(r"^\d+(\.\d+)?$")))?.parse::<f32>()?;
 
             let now = SystemTime::now();
             println!("Transformation ...");
-            transform(import_prefix.to_str().unwrap(), export_prefix.to_str().unwrap(), reduce)?;
+            transform(import_prefix.to_str().unwrap(), export_prefix.to_str().unwrap(), tolerance)?;
             println!("Transformation completed, overall time: {} sek", now.elapsed()?.as_secs());
         }
         Some("bin_file") => {      // for developement
@@ -94,16 +179,82 @@ fn param_to_string(nth: usize, alt_str: Option<&str>, regex: Option<Result<regex
     };
 }
 
-fn transform(import_prefix: &str, export_prefix: &str, reduce: bool) -> Result<(), Box<dyn Error>> {
-    println!("1/2: Read GeoJSONs parallel ...");
+fn param_to_density(nth: usize) -> Result<NodeDensity, String> {
+    match param_to_string(nth, Some("adaptive"), Some(Regex::new(r"^(adaptive|uniform)$")))?.as_str() {
+        "uniform" => Ok(NodeDensity::Uniform),
+        _ => Ok(NodeDensity::CoastAdaptive),
+    }
+}
+
+/**
+ * region to clip the import to: 'bbox:min_lon,min_lat,max_lon,max_lat' or a GeoJSON file with a polygon in the data folder;
+ * the whole world if not given
+ */
+fn param_to_region(nth: usize) -> Result<Option<Region>, Box<dyn Error>> {
+    if std::env::args_os().nth(nth).is_none() {
+        return Ok(None);
+    }
+    let param = param_to_string(nth, None, Some(Regex::new(r"^(bbox:(-?\d+(\.\d+)?,){3}-?\d+(\.\d+)?|.+\.(geo)?json)$")))?;
+    match param.strip_prefix("bbox:") {
+        Some(bbox) => {
+            let values = bbox.split(',').map(|value| value.parse::<f32>()).collect::<Result<Vec<f32>, _>>()?;
+            let (min_lon, min_lat, max_lon, max_lat) = (values[0], values[1], values[2], values[3]);
+            if min_lon >= max_lon || min_lat >= max_lat || min_lon < -180.0 || max_lon > 180.0 || min_lat < -90.0 || max_lat > 90.0 {
+                return Err(format!("Bounding box {} is empty or outside of lon -180 to 180 and lat -90 to 90", bbox).into());
+            }
+            Ok(Some(Region::from_bbox(min_lon, min_lat, max_lon, max_lat)))
+        },
+        None => Ok(Some(Region::from_geojson(&("data/".to_string() + &param))?)),
+    }
+}
+
+/**
+ * seed for the random graph generation; a random one if not given, it is printed during generation to be able to repeat it
+ */
+fn param_to_seed(nth: usize) -> Result<u64, Box<dyn Error>> {
+    match std::env::args_os().nth(nth) {
+        Some(_) => Ok(param_to_string(nth, None, Some(Regex::new(r"^\d+$")))?.parse::<u64>()?),
+        None => Ok(rand::random()),
+    }
+}
+
+/**
+ * rewrites the coastlines with another prefix; simplified with the tolerance in m, if it is bigger than 0
+ */
+fn transform(import_prefix: &str, export_prefix: &str, tolerance: f32) -> Result<(), Box<dyn Error>> {
+    println!("1/3: Read GeoJSONs parallel ...");
     let now = SystemTime::now();
     let coastlines: Vec<Vec<Vec<f32>>> = read_geojsons(import_prefix);
-    println!("1/2: Finished in {} sek", now.elapsed()?.as_secs());
-    
-    println!("2/2: Write GeoJSON ...");
+    println!("1/3: Finished in {} sek", now.elapsed()?.as_secs());
+
+    println!("2/3: Simplify coastlines with tolerance of {} m ...", tolerance);
     let now = SystemTime::now();
-    print_geojson(coastlines, export_prefix, reduce);
-    println!("2/2: Finished in {} sek", now.elapsed()?.as_secs());
+    let coastlines = if tolerance > 0.0 {
+        let simplified = simplify_coastlines(&coastlines, tolerance);
+        // read_geojsons sorts from big to small, so the size classes are the same as in the import
+        for (class_name, range) in size_class_ranges(coastlines.len()) {
+            let before: usize = coastlines[range.clone()].iter().map(|coastline| coastline.len()).sum();
+            let after: usize = simplified[range].iter().map(|coastline| coastline.len()).sum();
+            println!(
+                "2/3: {:<14} {:>10} -> {:>10} points ({:.1}% removed)",
+                class_name, before, after, 100.0 * (before - after) as f64 / before.max(1) as f64
+            );
+        }
+        simplified
+    } else {
+        coastlines
+    };
+    println!("2/3: Finished in {} sek", now.elapsed()?.as_secs());
+
+    println!("3/3: Write GeoJSON ...");
+    let now = SystemTime::now();
+    print_geojson(coastlines, export_prefix);
+    // the graph generation of a regional import is restricted to its region
+    match Region::of_import(import_prefix)? {
+        Some(region) => region.write_geojson(export_prefix)?,
+        None => Region::remove_of_import(export_prefix)?,
+    }
+    println!("3/3: Finished in {} sek", now.elapsed()?.as_secs());
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,102 @@
+use std::{error::Error, time::SystemTime};
+use graph_lib::{Graph, layers::Metric, zones::PenaltyZones, file_interface::{import_graph_from_file, print_graph_with_layers_to_file}};
+
+const METERS_PER_NAUTICAL_MILE: f32 = 1852.0;
+
+/**
+ * metrics, which can be added to a graph; speeds in knots
+ */
+pub enum MetricKind {
+    /// time in s at the speed
+    Time { speed: f32 },
+    /// fuel in kg at the speed with the consumption in tons per day
+    Fuel { speed: f32, tons_per_day: f32 },
+    /// length in m times the multipliers of the penalty zones of the GeoJSON file plus the penalties of their borders
+    Risk { zones_file: String },
+}
+
+impl MetricKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetricKind::Time { .. } => "time",
+            MetricKind::Fuel { .. } => "fuel",
+            MetricKind::Risk { .. } => "risk",
+        }
+    }
+}
+
+/**
+ * weights of all edges of the graph in the metric
+ */
+pub fn metric_of_graph(graph: &Graph, kind: &MetricKind) -> Result<Metric, Box<dyn Error>> {
+    let hours = |dist: u32, speed: f32| dist as f32 / (speed * METERS_PER_NAUTICAL_MILE);
+    let weights: Vec<u32> = match kind {
+        MetricKind::Time { speed } => graph.edges.iter().map(|edge| (hours(edge.dist, *speed) * 3600.0).round() as u32).collect(),
+        MetricKind::Fuel { speed, tons_per_day } => graph.edges.iter()
+            .map(|edge| (hours(edge.dist, *speed) * tons_per_day / 24.0 * 1000.0).round() as u32)
+            .collect(),
+        MetricKind::Risk { zones_file } => {
+            let zones = PenaltyZones::of_geojson(graph, zones_file)?;
+            graph.edges.iter().enumerate().map(|(edge_id, edge)| zones.cost(edge_id, edge.dist)).collect()
+        },
+    };
+    let unit = match kind {
+        MetricKind::Time { .. } => "s",
+        MetricKind::Fuel { .. } => "kg",
+        MetricKind::Risk { .. } => "m",
+    };
+    let metric = Metric::new(kind.name(), unit, weights, &graph.edges);
+    Ok(match kind {
+        MetricKind::Time { speed } => metric.at_speed(*speed, None),
+        MetricKind::Fuel { speed, tons_per_day } => metric.at_speed(*speed, Some(*tons_per_day)),
+        MetricKind::Risk { .. } => metric,
+    })
+}
+
+/**
+ * adds the metric to the graph or replaces the metric with the same name and writes the graph again
+ */
+pub fn add_metric(graph_name: &str, kind: &MetricKind) -> Result<(), Box<dyn Error>> {
+    println!("1/3: Read graph ...");
+    let now = SystemTime::now();
+    let mut graph = import_graph_from_file(graph_name)?;
+    println!("1/3: Finished in {} sek", now.elapsed()?.as_secs());
+
+    println!("2/3: Calculate {} of all edges ...", kind.name());
+    let now = SystemTime::now();
+    let metric = metric_of_graph(&graph, kind)?;
+    graph.layers.set_metric(metric);
+    let names: Vec<&str> = graph.layers.metrics.iter().map(|metric| metric.name.as_str()).collect();
+    println!("2/3: The graph has the metrics distance, {}", names.join(", "));
+    println!("2/3: Finished in {} sek", now.elapsed()?.as_secs());
+
+    println!("3/3: Write graph ...");
+    let now = SystemTime::now();
+    print_graph_with_layers_to_file(&graph.nodes, &graph.edges, &graph.layers, graph_name);
+    println!("3/3: Finished in {} sek", now.elapsed()?.as_secs());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use graph_lib::{Graph, Node, Edge};
+
+    use crate::metrics::{MetricKind, metric_of_graph};
+
+    #[test]
+    fn test_metric_of_graph() {
+        // 10 nautical miles in both directions
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 0.1667, lat: 0.0 }];
+        let edges = vec![Edge { src: 0, tgt: 1, dist: 18520 }, Edge { src: 1, tgt: 0, dist: 18520 }];
+        let graph = Graph::new(nodes, edges);
+
+        let time = metric_of_graph(&graph, &MetricKind::Time { speed: 12.5 }).unwrap();
+        assert_eq!((time.name.as_str(), time.unit.as_str(), time.weights[0]), ("time", "s", 2880));
+        assert_eq!((time.speed, time.tons_per_day), (Some(12.5), None));
+        let fuel = metric_of_graph(&graph, &MetricKind::Fuel { speed: 10.0, tons_per_day: 48.0 }).unwrap();
+        assert_eq!(fuel.weights, vec![2000, 2000]);
+        assert_eq!(fuel.format(fuel.weights[0]), "fuel 2.0 t");
+        assert_eq!(fuel.tons_per_day, Some(48.0));
+        assert!(metric_of_graph(&graph, &MetricKind::Risk { zones_file: "missing.json".to_string() }).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use std::{error::Error, fs::{self, File}, hash::{DefaultHasher, Hash, Hasher}, io::{self, BufReader, BufWriter, Read, Write}, path::{Path, PathBuf}, sync::atomic::{AtomicUsize, Ordering}};
+use memmap2::Mmap;
+use osmpbf::{BlobDecode, BlobReader};
+use rayon::prelude::*;
+use serde::{Serialize, de::DeserializeOwned};
+
+/**
+ * id (i64), lon and lat (i32 in 10⁻⁷ degrees) in little endian
+ */
+const RECORD_SIZE: usize = 16;
+
+/**
+ * Directory for the intermediate results of an import, so an interrupted import can continue with the last finished stage.
+ * It belongs to one PBF file; if the file changed, the old results are removed.
+ */
+pub struct Checkpoints {
+    dir: PathBuf,
+}
+
+impl Checkpoints {
+    pub fn new(dir: &str, pbf_path: &str) -> Result<Checkpoints, Box<dyn Error>> {
+        let dir = PathBuf::from(dir);
+        let metadata = fs::metadata(pbf_path)?;
+        let source = format!("{} {} {:?}", pbf_path, metadata.len(), metadata.modified()?);
+        if fs::read_to_string(dir.join("source")).is_ok_and(|old_source| old_source != source) {
+            println!("Checkpoints in {} are from another file, starting from the beginning", dir.display());
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("source"), source)?;
+        Ok(Checkpoints { dir })
+    }
+
+    /**
+     * loads the result of a stage if it was finished before, otherwise computes and saves it
+     */
+    pub fn stage<T: Serialize + DeserializeOwned>(&self, name: &str, compute: impl FnOnce() -> Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
+        let path = self.dir.join(format!("{}.bin", name));
+        if path.exists() {
+            println!("Continue with saved result of stage {}", name);
+            return Ok(bincode::deserialize_from(BufReader::new(File::open(path)?))?);
+        }
+        let result = compute()?;
+        write_atomic(&path, |writer| bincode::serialize_into(writer, &result).map_err(io::Error::other))?;
+        Ok(result)
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    pub fn remove(self) -> Result<(), Box<dyn Error>> {
+        fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+}
+
+/**
+ * writes into a temporary file, which is renamed when finished; so a file exists only if it is complete
+ */
+fn write_atomic(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    write(&mut writer)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/**
+ * Coordinates of nodes in a file sorted by id, which is mapped into memory.
+ * The operating system keeps only the needed pages in RAM.
+ */
+pub struct NodeLocations {
+    mmap: Mmap,
+}
+
+impl NodeLocations {
+    /**
+     * Reads the coordinates of the given node ids (sorted) from the PBF file, the blocks are decoded in parallel.
+     * Every block writes its nodes into an own file, so after an interruption only the missing blocks are read again.
+     * At the end the files are appended to one file. The files are named by a hash of the node ids, which depend on the region,
+     * so an import continued with another region reads the nodes again.
+     */
+    pub fn build(pbf_path: &str, node_ids: &[i64], checkpoints: &Checkpoints) -> Result<NodeLocations, Box<dyn Error>> {
+        let mut hasher = DefaultHasher::new();
+        node_ids.hash(&mut hasher);
+        let name = format!("node_locations_{:016x}", hasher.finish());
+        let path = checkpoints.path(&format!("{}.bin", name));
+        if !path.exists() {
+            let blocks_dir = checkpoints.path(&name);
+            fs::create_dir_all(&blocks_dir)?;
+            let (read_blocks, skipped_blocks) = (AtomicUsize::new(0), AtomicUsize::new(0));
+            let n_blocks = BlobReader::from_path(pbf_path)?.enumerate().par_bridge().map(|(block_i, blob)| {
+                let block_path = blocks_dir.join(format!("{}.bin", block_i));
+                if block_path.exists() {
+                    skipped_blocks.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                let mut records: Vec<(i64, i32, i32)> = vec![];
+                if let BlobDecode::OsmData(block) = blob?.decode()? {
+                    for group in block.groups() {
+                        records.extend(group.dense_nodes()
+                            .filter(|node| node_ids.binary_search(&node.id()).is_ok())
+                            .map(|node| (node.id(), node.decimicro_lon(), node.decimicro_lat())));
+                        records.extend(group.nodes()
+                            .filter(|node| node_ids.binary_search(&node.id()).is_ok())
+                            .map(|node| (node.id(), node.decimicro_lon(), node.decimicro_lat())));
+                    }
+                }
+                records.sort_unstable();
+                write_atomic(&block_path, |writer| {
+                    for (id, lon, lat) in records {
+                        writer.write_all(&id.to_le_bytes())?;
+                        writer.write_all(&lon.to_le_bytes())?;
+                        writer.write_all(&lat.to_le_bytes())?;
+                    }
+                    Ok(())
+                })?;
+                let read = read_blocks.fetch_add(1, Ordering::Relaxed) + 1;
+                if read.is_multiple_of(1000) {
+                    println!("Read {} blocks", read);
+                }
+                Ok(())
+            }).collect::<Result<Vec<()>, Box<dyn Error + Send + Sync>>>().map_err(|error| error as Box<dyn Error>)?.len();
+            println!("Read {} blocks, {} blocks were read before", read_blocks.into_inner(), skipped_blocks.into_inner());
+
+            merge_blocks(&blocks_dir, n_blocks, &path)?;
+            fs::remove_dir_all(&blocks_dir)?;
+        }
+        NodeLocations::open(&path)
+    }
+
+    fn open(path: &Path) -> Result<NodeLocations, Box<dyn Error>> {
+        // SAFETY: the file is completely written before and not changed while it is mapped
+        let mmap = unsafe { Mmap::map(&File::open(path)?)? };
+        Ok(NodeLocations { mmap })
+    }
+
+    pub fn len(&self) -> usize {
+        self.mmap.len() / RECORD_SIZE
+    }
+
+    /**
+     * binary search for the id in the file, returns (lon, lat)
+     */
+    pub fn get(&self, id: i64) -> Option<(f32, f32)> {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let middle = (low + high) / 2;
+            let record = &self.mmap[middle * RECORD_SIZE..(middle + 1) * RECORD_SIZE];
+            let record_id = i64::from_le_bytes(record[0..8].try_into().unwrap());
+            if record_id == id {
+                let lon = i32::from_le_bytes(record[8..12].try_into().unwrap());
+                let lat = i32::from_le_bytes(record[12..16].try_into().unwrap());
+                return Some(((lon as f64 * 1e-7) as f32, (lat as f64 * 1e-7) as f32));
+            } else if record_id < id {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        None
+    }
+}
+
+/**
+ * appends the files of the blocks in their order; PBF files are usually sorted by id,
+ * otherwise the records are sorted in memory
+ */
+fn merge_blocks(blocks_dir: &Path, n_blocks: usize, path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut sorted = true;
+    let mut last_id = i64::MIN;
+    write_atomic(path, |writer| {
+        for block_i in 0..n_blocks {
+            let mut bytes = vec![];
+            File::open(blocks_dir.join(format!("{}.bin", block_i)))?.read_to_end(&mut bytes)?;
+            if let Some(first_record) = bytes.chunks_exact(RECORD_SIZE).next() {
+                sorted &= i64::from_le_bytes(first_record[0..8].try_into().unwrap()) > last_id;
+                last_id = i64::from_le_bytes(bytes[bytes.len() - RECORD_SIZE..][0..8].try_into().unwrap());
+            }
+            writer.write_all(&bytes)?;
+        }
+        Ok(())
+    })?;
+    if !sorted {
+        println!("Nodes of the PBF file are not sorted by id, sorting in memory");
+        let bytes = fs::read(path)?;
+        let mut records: Vec<&[u8]> = bytes.chunks_exact(RECORD_SIZE).collect();
+        records.par_sort_unstable_by_key(|record| i64::from_le_bytes(record[0..8].try_into().unwrap()));
+        write_atomic(path, |writer| {
+            for record in records {
+                writer.write_all(record)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::node_index::{merge_blocks, Checkpoints, NodeLocations};
+
+    #[test]
+    fn test_merge_unsorted_blocks() {
+        let dir = std::env::temp_dir().join("ship_routing_test_node_index");
+        std::fs::create_dir_all(&dir).unwrap();
+        let blocks: [&[(i64, i32, i32)]; 3] = [&[(5, 50, -50), (9, 90, -90)], &[], &[(2, 20, -20)]];
+        for (block_i, records) in blocks.iter().enumerate() {
+            let bytes: Vec<u8> = records.iter()
+                .flat_map(|(id, lon, lat)| [id.to_le_bytes().to_vec(), lon.to_le_bytes().to_vec(), lat.to_le_bytes().to_vec()].concat())
+                .collect();
+            std::fs::write(dir.join(format!("{}.bin", block_i)), bytes).unwrap();
+        }
+        let path = dir.join("node_locations.bin");
+        merge_blocks(&dir, 3, &path).unwrap();
+
+        let locations = NodeLocations::open(&path).unwrap();
+        assert_eq!(locations.len(), 3);
+        assert_eq!(locations.get(2), Some((2e-6, -2e-6)));
+        assert_eq!(locations.get(9), Some((9e-6, -9e-6)));
+        assert_eq!(locations.get(7), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_continue_with_other_nodes() {
+        let dir = std::env::temp_dir().join("ship_routing_test_node_checkpoints");
+        let pbf_path = std::env::temp_dir().join("ship_routing_test_node_checkpoints.pbf");
+        std::fs::write(&pbf_path, b"no pbf").unwrap();
+        let checkpoints = Checkpoints::new(dir.to_str().unwrap(), pbf_path.to_str().unwrap()).unwrap();
+
+        // the first import fails on the broken PBF and leaves the directory of its blocks, named by its nodes;
+        // with the finished file of these nodes, the import continues without reading the PBF
+        let first = NodeLocations::build(pbf_path.to_str().unwrap(), &[], &checkpoints);
+        assert!(first.is_err());
+        let finished = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.is_dir())
+            .unwrap();
+        std::fs::remove_dir_all(&finished).unwrap();
+        std::fs::write(finished.with_extension("bin"), [7i64.to_le_bytes(), [0; 8]].concat()).unwrap();
+        assert_eq!(NodeLocations::build(pbf_path.to_str().unwrap(), &[], &checkpoints).unwrap().len(), 1);
+
+        // with other nodes, e.g. of another region, the PBF is read again instead of using the finished file
+        assert!(NodeLocations::build(pbf_path.to_str().unwrap(), &[7, 8], &checkpoints).is_err());
+        checkpoints.remove().unwrap();
+        std::fs::remove_file(pbf_path).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+use graph_lib::{Coordinates, coast_distance::distance_to_segment};
+use rayon::prelude::*;
+
+const CELLS_PER_DEGREE: usize = 100;
+const N_COLUMNS: usize = 360 * CELLS_PER_DEGREE;
+const N_ROWS: usize = 180 * CELLS_PER_DEGREE;
+
+/**
+ * Visvalingam like simplification of closed coastlines: the point with the smallest deviation is removed first,
+ * as long as no point of the original coastline is further than the tolerance (in m) from the simplified one.
+ * A point is only removed if no other point of any coastline lies in the triangle of the point and its neighbours.
+ * So the removal can't let a coastline cross itself or another coastline, straits stay open and islands stay separated.
+ * Every coastline keeps at least 3 points.
+ */
+pub fn simplify_coastlines(coastlines: &[Vec<Vec<f32>>], tolerance: f32) -> Vec<Vec<Vec<f32>>> {
+    let points = PointIndex::new(coastlines);
+    let mut removed: Vec<Vec<bool>> = coastlines.iter().map(|coastline| vec![false; coastline.len()]).collect();
+    for ring_i in 0..coastlines.len() {
+        simplify_ring(coastlines, ring_i, tolerance, &points, &mut removed);
+    }
+    coastlines.iter().zip(removed).map(|(coastline, removed)| {
+        coastline.iter().zip(removed).filter(|(_, removed)| !removed).map(|(point, _)| point.to_owned()).collect()
+    }).collect()
+}
+
+fn simplify_ring(coastlines: &[Vec<Vec<f32>>], ring_i: usize, tolerance: f32, points: &PointIndex, removed: &mut [Vec<bool>]) {
+    let ring = &coastlines[ring_i];
+    if ring.len() < 5 || ring.first() != ring.last() {
+        return;
+    }
+    // the last point is the same as the first one, the first point is never removed
+    let n = ring.len() - 1;
+    let mut prev: Vec<usize> = (0..n).map(|i| (i + n - 1) % n).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+    let mut deviations: Vec<f32> = vec![f32::INFINITY; n];
+    let mut heap: BinaryHeap<Reverse<(u32, usize)>> = BinaryHeap::new();
+    for i in 1..n {
+        deviations[i] = deviation(ring, prev[i], next[i], tolerance);
+        if deviations[i].is_finite() {
+            heap.push(Reverse((deviations[i].to_bits(), i)));
+        }
+    }
+
+    let mut remaining = n;
+    while let Some(Reverse((deviation_bits, i))) = heap.pop() {
+        if remaining <= 3 {
+            break;
+        }
+        // outdated entry, the neighbours have changed since
+        if removed[ring_i][i] || deviation_bits != deviations[i].to_bits() {
+            continue;
+        }
+        let (a, b) = (prev[i], next[i]);
+        if points.any_in_triangle(coastlines, removed, (ring_i, [a, i, b])) {
+            continue;
+        }
+        removed[ring_i][i] = true;
+        remaining -= 1;
+        next[a] = b;
+        prev[b] = a;
+        for neighbour in [a, b] {
+            if neighbour == 0 { continue }
+            deviations[neighbour] = deviation(ring, prev[neighbour], next[neighbour], tolerance);
+            if deviations[neighbour].is_finite() {
+                heap.push(Reverse((deviations[neighbour].to_bits(), neighbour)));
+            }
+        }
+    }
+}
+
+/**
+ * maximal distance in m of the original points between a and b to the segment a-b;
+ * infinity if it is bigger than the tolerance or the segment crosses the date border
+ */
+fn deviation(ring: &[Vec<f32>], a: usize, b: usize, tolerance: f32) -> f32 {
+    let b_end = if b == 0 { ring.len() - 1 } else { b };
+    let (start, end) = (Coordinates::from_vec(&ring[a]), Coordinates::from_vec(&ring[b_end]));
+    if (start.0 - end.0).abs() > 180.0 {
+        return f32::INFINITY;
+    }
+    let mut max_deviation: f32 = 0.0;
+    for point in &ring[a + 1..b_end] {
+        let distance = distance_to_segment(point[0], point[1], point[1].to_radians().cos(), &start, &end);
+        if distance > tolerance {
+            return f32::INFINITY;
+        }
+        max_deviation = max_deviation.max(distance);
+    }
+    max_deviation
+}
+
+/**
+ * all points of all coastlines as (cell, coastline index, point index), sorted by grid cells of 0.01° x 0.01°;
+ * the grid is too fine for a vector with all cells, so the cells are found by binary search
+ */
+struct PointIndex {
+    points: Vec<(u32, u32, u32)>,
+}
+
+impl PointIndex {
+    fn new(coastlines: &[Vec<Vec<f32>>]) -> PointIndex {
+        let mut points: Vec<(u32, u32, u32)> = coastlines.par_iter().enumerate().flat_map_iter(|(ring_i, coastline)| {
+            coastline.iter().enumerate().map(move |(point_i, point)| {
+                (cell_of(point[0], point[1]) as u32, ring_i as u32, point_i as u32)
+            })
+        }).collect();
+        points.par_sort_unstable();
+        PointIndex { points }
+    }
+
+    fn points_in_cell(&self, cell: usize) -> &[(u32, u32, u32)] {
+        let start = self.points.partition_point(|(point_cell, _, _)| (*point_cell as usize) < cell);
+        let end = start + self.points[start..].partition_point(|(point_cell, _, _)| *point_cell as usize == cell);
+        &self.points[start..end]
+    }
+
+    /**
+     * checks if any remaining point, except the corners, lies in the triangle or on its border
+     */
+    fn any_in_triangle(&self, coastlines: &[Vec<Vec<f32>>], removed: &[Vec<bool>], (ring_i, corners): (usize, [usize; 3])) -> bool {
+        let ring = &coastlines[ring_i];
+        let [a, p, b] = corners.map(|i| [ring[i][0], ring[i][1]]);
+        let (min_lon, max_lon) = (a[0].min(p[0]).min(b[0]), a[0].max(p[0]).max(b[0]));
+        let (min_lat, max_lat) = (a[1].min(p[1]).min(b[1]), a[1].max(p[1]).max(b[1]));
+        let (min_cell, max_cell) = (cell_of(min_lon, min_lat), cell_of(max_lon, max_lat));
+        // the first and last point of a ring are the same
+        let last = ring.len() - 1;
+        let is_corner = |point_ring: usize, point_i: usize| {
+            point_ring == ring_i && (corners.contains(&point_i) || (point_i == last && corners.contains(&0)))
+        };
+
+        for row in min_cell / N_COLUMNS..=max_cell / N_COLUMNS {
+            for col in min_cell % N_COLUMNS..=max_cell % N_COLUMNS {
+                let cell = row * N_COLUMNS + col;
+                for (_, point_ring, point_i) in self.points_in_cell(cell) {
+                    let (point_ring, point_i) = (*point_ring as usize, *point_i as usize);
+                    if removed[point_ring][point_i] || is_corner(point_ring, point_i) {
+                        continue;
+                    }
+                    let point = &coastlines[point_ring][point_i];
+                    if point[0] < min_lon || point[0] > max_lon || point[1] < min_lat || point[1] > max_lat {
+                        continue;
+                    }
+                    if in_triangle([point[0], point[1]], a, p, b) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+#[inline]
+fn cell_of(lon: f32, lat: f32) -> usize {
+    let col = (((lon + 180.0) * CELLS_PER_DEGREE as f32) as usize).min(N_COLUMNS - 1);
+    let row = (((lat + 90.0) * CELLS_PER_DEGREE as f32) as usize).min(N_ROWS - 1);
+    row * N_COLUMNS + col
+}
+
+/**
+ * point in triangle test including the border, independent of the orientation of the triangle
+ */
+#[inline]
+fn in_triangle(point: [f32; 2], a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> bool {
+    let cross = |u: [f32; 2], v: [f32; 2]| (v[0] - u[0]) * (point[1] - u[1]) - (v[1] - u[1]) * (point[0] - u[0]);
+    let (d1, d2, d3) = (cross(a, b), cross(b, c), cross(c, a));
+    let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(has_negative && has_positive)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::simplify::simplify_coastlines;
+
+    #[test]
+    fn test_simplify_keeps_strait() {
+        // the dent of 111 m at (0.5, 0.999) is within the tolerance, the point on the southern side lies on the line
+        let coastline = vec![
+            vec![0.0, 0.0], vec![0.5, 0.0], vec![1.0, 0.0], vec![1.0, 1.0], vec![0.6, 1.0],
+            vec![0.5, 0.999], vec![0.4, 1.0], vec![0.0, 1.0], vec![0.0, 0.0]
+        ];
+        let simplified = simplify_coastlines(std::slice::from_ref(&coastline), 1000.0);
+        assert!(!simplified[0].contains(&vec![0.5, 0.0]));
+        assert!(!simplified[0].contains(&vec![0.5, 0.999]));
+
+        // an island in the dent prevents the removal, which would move the coastline over the island
+        let island = vec![vec![0.49, 0.9995], vec![0.51, 0.9995], vec![0.5, 0.9998], vec![0.49, 0.9995]];
+        let simplified = simplify_coastlines(&[coastline, island.clone()], 1000.0);
+        assert!(!simplified[0].contains(&vec![0.5, 0.0]));
+        assert!(simplified[0].contains(&vec![0.5, 0.999]));
+        assert_eq!(simplified[1], island);
+    }
+}
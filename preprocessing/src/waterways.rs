@@ -0,0 +1,359 @@
+use std::{collections::{HashMap, HashSet}, error::Error, fs, io};
+use graph_lib::{Node, Edge, Coordinates, distance_between, coast_distance::CoastDistance, geojson_interface::{read_waterways_from_geojson, write_waterways_to_geojson}, island::Island, layers::{Corridor, GraphLayers, Waterway, WaterwayLimits}, region::Region};
+use serde::{Serialize, Deserialize};
+
+/**
+ * maximal distance in m between two nodes of a corridor; the points of the waterway in between are skipped
+ */
+const CORRIDOR_NODE_DISTANCE: f32 = 5000.0;
+
+/**
+ * canal or river way of OSM with the ids of its nodes; areas are closed ways of navigable water
+ */
+#[derive(Serialize, Deserialize)]
+pub struct WaterwayWay {
+    pub refs: Vec<i64>,
+    pub corridor: Corridor,
+    pub area: bool,
+}
+
+fn waterways_path(prefix: &str) -> String {
+    format!("./data/geojson/{}_waterways.json", prefix)
+}
+
+pub fn write_waterways(waterways: &[Waterway], prefix: &str) -> Result<(), Box<dyn Error>> {
+    write_waterways_to_geojson(&waterways_path(prefix), waterways)
+}
+
+/**
+ * waterways of an import, empty if the import contains none
+ */
+pub fn read_waterways(prefix: &str) -> Result<Vec<Waterway>, Box<dyn Error>> {
+    if !fs::exists(waterways_path(prefix))? {
+        return Ok(vec![]);
+    }
+    read_waterways_from_geojson(&waterways_path(prefix))
+}
+
+/**
+ * removes the waterways of an earlier import with the same prefix
+ */
+pub fn remove_waterways(prefix: &str) -> io::Result<()> {
+    match fs::remove_file(waterways_path(prefix)) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/**
+ * Checks the tags of a way for a navigable waterway: waterway=canal or waterway=river, or an area of natural=water
+ * with water=canal, river or lock. Navigable means, it is open for ships (ship=yes or designated),
+ * has a CEMT class or a limit for the draught.
+ */
+pub fn navigable_waterway(tags: &HashMap<&str, &str>, refs: &[i64]) -> Option<WaterwayWay> {
+    let (kind, area) = match (tags.get("waterway").copied(), tags.get("natural").copied(), tags.get("water").copied()) {
+        (Some(kind @ ("canal" | "river")), _, _) => (kind.to_string(), false),
+        (_, Some("water"), Some(kind @ ("canal" | "river" | "lock"))) if refs.first() == refs.last() => (kind.to_string(), true),
+        _ => return None,
+    };
+    let limit = |keys: &[&str]| keys.iter().find_map(|key| tags.get(key).and_then(|value| parse_length(value)));
+    let limits = WaterwayLimits {
+        width: limit(&["width"]),
+        max_draught: limit(&["maxdraught", "maxdraft"]),
+        max_beam: limit(&["maxbeam", "maxwidth"]),
+        max_length: limit(&["maxlength"]),
+        max_height: limit(&["maxheight", "maxairdraft"]),
+    };
+    let navigable = matches!(tags.get("ship").copied(), Some("yes" | "designated")) || tags.contains_key("CEMT") || limits.max_draught.is_some();
+    if !navigable || refs.len() < 2 {
+        return None;
+    }
+    let name = tags.get("name").unwrap_or(&"").to_string();
+    Some(WaterwayWay { refs: refs.to_vec(), corridor: Corridor { name, kind, limits }, area })
+}
+
+/**
+ * length in m of an OSM value like "12.5", "12.5 m" or "40'"
+ */
+fn parse_length(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let number_end = value.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(value.len());
+    let number = value[..number_end].parse::<f32>().ok()?;
+    let unit = value[number_end..].trim();
+    match unit {
+        "" | "m" => Some(number),
+        "'" | "ft" => Some(number * 0.3048),
+        _ => None,
+    }
+}
+
+/**
+ * Waterways without width get the width of the navigable area around them, estimated by 2 * area / perimeter,
+ * which is close to the width of long, narrow areas. Returns the number of waterways with estimated width.
+ */
+pub fn estimate_widths(waterways: &mut [Waterway], areas: &[Vec<Vec<f32>>]) -> usize {
+    let area_widths: Vec<(Region, f32)> = areas.iter().map(|area| (Region::new(area.to_owned()), estimated_width(area))).collect();
+    let mut estimated = 0;
+    for waterway in waterways.iter_mut().filter(|waterway| waterway.corridor.limits.width.is_none()) {
+        let middle = &waterway.course[waterway.course.len() / 2];
+        if let Some((_, width)) = area_widths.iter().find(|(area, _)| area.contains(middle[0], middle[1])) {
+            waterway.corridor.limits.width = Some(*width);
+            estimated += 1;
+        }
+    }
+    estimated
+}
+
+fn estimated_width(ring: &[Vec<f32>]) -> f32 {
+    // local projection in m around the first point
+    let cos_lat = ring[0][1].to_radians().cos();
+    let to_m = |point: &Vec<f32>| ((point[0] - ring[0][0]) * 111_320.0 * cos_lat, (point[1] - ring[0][1]) * 110_540.0);
+    let (mut area, mut perimeter) = (0.0, 0.0);
+    for edge in ring.windows(2) {
+        let (a, b) = (to_m(&edge[0]), to_m(&edge[1]));
+        area += (a.0 * b.1 - b.0 * a.1) / 2.0;
+        perimeter += distance_between(edge[0][0], edge[0][1], edge[1][0], edge[1][1]);
+    }
+    2.0 * area.abs() / perimeter.max(1.0)
+}
+
+/**
+ * parts of the course inside of the region, the course is split where it leaves the region
+ */
+pub fn split_to_region(course: Vec<Vec<f32>>, region: &Region) -> Vec<Vec<Vec<f32>>> {
+    let mut parts: Vec<Vec<Vec<f32>>> = vec![vec![]];
+    for point in course {
+        if region.contains(point[0], point[1]) {
+            parts.last_mut().unwrap().push(point);
+        } else if !parts.last().unwrap().is_empty() {
+            parts.push(vec![]);
+        }
+    }
+    parts.into_iter().filter(|part| part.len() > 1).collect()
+}
+
+/**
+ * graph with the corridors and what happened to the waterways
+ */
+pub struct CorridorGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    pub layers: GraphLayers,
+    /// connected waterways with a connection to the ocean at two or more ends
+    pub through: usize,
+    /// connected waterways with only one connection to the ocean
+    pub dead_ends: usize,
+    /// connected waterways without connection to the ocean, which are not added
+    pub unconnected: usize,
+}
+
+/**
+ * Adds the waterways as chains of corridor edges to the graph. Waterways which meet at a point are connected there.
+ * Every open end of a waterway is connected to the closest node of the ocean within the connection distance,
+ * which can be reached without crossing a coastline. Waterways which can't reach the ocean are skipped.
+ * The corridor of each edge is saved in the layers.
+ */
+pub fn add_corridors(
+    nodes: Vec<Node>, edges: Vec<Edge>, waterways: &[Waterway], connection_distance: f32, islands: &[Island], coast_distance: &CoastDistance
+) -> CorridorGraph {
+    let key = |point: &Vec<f32>| (point[0].to_bits(), point[1].to_bits());
+    // corridors are equal with the same name, kind and limits
+    let corridor_key = |corridor: &Corridor| {
+        let limits = corridor.limits;
+        let limits = [limits.width, limits.max_draught, limits.max_beam, limits.max_length, limits.max_height].map(|limit| limit.map(f32::to_bits));
+        (corridor.name.clone(), corridor.kind.clone(), limits)
+    };
+    let ends: HashSet<(u32, u32)> = waterways.iter()
+        .flat_map(|waterway| [key(&waterway.course[0]), key(&waterway.course[waterway.course.len() - 1])])
+        .collect();
+
+    // corridor nodes with their position and corridor edges (node, node, distance, corridor)
+    let mut corridors: Vec<Corridor> = vec![];
+    let mut corridor_ids: HashMap<_, u32> = HashMap::new();
+    let mut points: Vec<(f32, f32)> = vec![];
+    let mut point_ids: HashMap<(u32, u32), usize> = HashMap::new();
+    let mut corridor_edges: Vec<(usize, usize, f32, u32)> = vec![];
+    for waterway in waterways {
+        let corridor = *corridor_ids.entry(corridor_key(&waterway.corridor)).or_insert_with(|| {
+            corridors.push(waterway.corridor.clone());
+            corridors.len() as u32 - 1
+        });
+        let mut point_id = |point: &Vec<f32>| *point_ids.entry(key(point)).or_insert_with(|| {
+            points.push((point[0], point[1]));
+            points.len() - 1
+        });
+        let mut last = point_id(&waterway.course[0]);
+        let mut distance = 0.0;
+        for (i, segment) in waterway.course.windows(2).enumerate() {
+            distance += distance_between(segment[0][0], segment[0][1], segment[1][0], segment[1][1]);
+            let is_last = i + 2 == waterway.course.len();
+            if is_last || distance >= CORRIDOR_NODE_DISTANCE || ends.contains(&key(&segment[1])) {
+                let next = point_id(&segment[1]);
+                if next != last {
+                    corridor_edges.push((last, next, distance, corridor));
+                }
+                last = next;
+                distance = 0.0;
+            }
+        }
+    }
+
+    // open ends are connected to the closest ocean node
+    let mut degree = vec![0; points.len()];
+    for (a, b, _, _) in &corridor_edges {
+        degree[*a] += 1;
+        degree[*b] += 1;
+    }
+    let ocean_grid = OceanGrid::new(&nodes);
+    let mut ocean_edges: Vec<(usize, usize, f32, u32)> = vec![];
+    for (point_i, point) in points.iter().enumerate().filter(|(point_i, _)| degree[*point_i] == 1) {
+        let reachable = |node: &Node| !coast_distance.crosses_coastline(islands, &Coordinates(point.0, point.1), &Coordinates(node.lon, node.lat));
+        if let Some((ocean_node, distance)) = ocean_grid.closest(&nodes, point.0, point.1, connection_distance, reachable) {
+            let corridor = corridor_edges.iter().find(|(a, b, _, _)| *a == point_i || *b == point_i).unwrap().3;
+            ocean_edges.push((point_i, ocean_node, distance, corridor));
+        }
+    }
+
+    // connected waterways are only added, if they reach the ocean
+    let mut component: Vec<usize> = (0..points.len()).collect();
+    fn find(component: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while component[root] != root {
+            root = component[root];
+        }
+        // path compression, so long waterways don't build long chains
+        let mut i = i;
+        while component[i] != root {
+            let next = component[i];
+            component[i] = root;
+            i = next;
+        }
+        root
+    }
+    for (a, b, _, _) in &corridor_edges {
+        let (a, b) = (find(&mut component, *a), find(&mut component, *b));
+        component[a] = b;
+    }
+    let mut ocean_connections = vec![0; points.len()];
+    for (point_i, _, _, _) in &ocean_edges {
+        let root = find(&mut component, *point_i);
+        ocean_connections[root] += 1;
+    }
+    let roots: Vec<usize> = (0..points.len()).map(|i| find(&mut component, i)).collect();
+    let (mut through, mut dead_ends, mut unconnected) = (0, 0, 0);
+    for root in (0..points.len()).filter(|i| roots[*i] == *i) {
+        match ocean_connections[root] {
+            0 => unconnected += 1,
+            1 => dead_ends += 1,
+            _ => through += 1,
+        }
+    }
+
+    let mut nodes = nodes;
+    let mut node_ids: Vec<Option<usize>> = vec![None; points.len()];
+    for (point_i, point) in points.iter().enumerate().filter(|(point_i, _)| ocean_connections[roots[*point_i]] > 0) {
+        node_ids[point_i] = Some(nodes.len());
+        nodes.push(Node { id: nodes.len(), lon: point.0, lat: point.1 });
+    }
+    let mut all_edges: Vec<(Edge, Option<u32>)> = edges.into_iter().map(|edge| (edge, None)).collect();
+    let new_edges = corridor_edges.iter().filter_map(|(a, b, distance, corridor)| Some((node_ids[*a]?, node_ids[*b]?, *distance, *corridor)))
+        .chain(ocean_edges.iter().filter_map(|(a, ocean_node, distance, corridor)| Some((node_ids[*a]?, *ocean_node, *distance, *corridor))));
+    for (a, b, distance, corridor) in new_edges {
+        let dist = distance.round() as u32;
+        all_edges.push((Edge { src: a, tgt: b, dist }, Some(corridor)));
+        all_edges.push((Edge { src: b, tgt: a, dist }, Some(corridor)));
+    }
+    all_edges.sort_by_key(|(edge, _)| edge.src);
+
+    let (edges, edge_corridor): (Vec<Edge>, Vec<Option<u32>>) = all_edges.into_iter().unzip();
+    CorridorGraph { nodes, edges, layers: GraphLayers { corridors, edge_corridor, ..Default::default() }, through, dead_ends, unconnected }
+}
+
+/**
+ * ocean nodes in grid cells of 1° x 1°, to find the closest one to a point
+ */
+struct OceanGrid {
+    cells: Vec<Vec<usize>>,
+}
+
+impl OceanGrid {
+    fn new(nodes: &[Node]) -> OceanGrid {
+        let mut cells = vec![vec![]; 360 * 180];
+        for node in nodes {
+            cells[OceanGrid::cell(node.lon as i32, node.lat as i32)].push(node.id);
+        }
+        OceanGrid { cells }
+    }
+
+    fn cell(col: i32, row: i32) -> usize {
+        ((row + 90).clamp(0, 179) * 360 + (col + 180).rem_euclid(360)) as usize
+    }
+
+    /**
+     * closest reachable node and its distance in the cell of the point and the cells around, if it is within the maximal distance
+     */
+    fn closest(&self, nodes: &[Node], lon: f32, lat: f32, max_distance: f32, reachable: impl Fn(&Node) -> bool) -> Option<(usize, f32)> {
+        let (col, row) = (lon.floor() as i32, lat.floor() as i32);
+        let mut candidates: Vec<(usize, f32)> = (-1..=1).flat_map(|d_row| (-1..=1).map(move |d_col| OceanGrid::cell(col + d_col, row + d_row)))
+            .flat_map(|cell| self.cells[cell].iter())
+            .map(|node_id| (*node_id, distance_between(lon, lat, nodes[*node_id].lon, nodes[*node_id].lat)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .collect();
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+        candidates.into_iter().find(|(node_id, _)| reachable(&nodes[*node_id]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use graph_lib::{Node, coast_distance::CoastDistance, island::Island, layers::{Corridor, Waterway, WaterwayLimits}};
+
+    use crate::waterways::{add_corridors, navigable_waterway};
+
+    #[test]
+    fn test_navigable_waterway() {
+        let tags = HashMap::from([("waterway", "canal"), ("name", "Kiel Canal"), ("maxdraught", "9.5 m"), ("maxheight", "131'")]);
+        let waterway = navigable_waterway(&tags, &[1, 2, 3]).unwrap();
+        assert_eq!(waterway.corridor.limits.max_draught, Some(9.5));
+        assert!((waterway.corridor.limits.max_height.unwrap() - 39.93).abs() < 0.01);
+        assert!(!waterway.area);
+
+        let tags = HashMap::from([("waterway", "canal")]);
+        assert!(navigable_waterway(&tags, &[1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn test_add_corridors() {
+        // a canal through an isthmus at lon 0 from the western to the eastern ocean node, and a river without connection
+        let nodes = vec![Node { id: 0, lon: -0.2, lat: 0.0 }, Node { id: 1, lon: 0.2, lat: 0.0 }];
+        let corridor = Corridor { name: "canal".to_string(), kind: "canal".to_string(), limits: WaterwayLimits::default() };
+        let waterways = vec![
+            Waterway { corridor: corridor.clone(), course: vec![vec![-0.1, 0.0], vec![-0.07, 0.0], vec![0.0, 0.0]] },
+            Waterway { corridor: corridor.clone(), course: vec![vec![0.0, 0.0], vec![0.1, 0.0]] },
+            Waterway { corridor, course: vec![vec![5.0, 5.0], vec![5.1, 5.0]] },
+        ];
+        let result = add_corridors(nodes, vec![], &waterways, 20000.0, &[], &CoastDistance::new(&[]));
+        assert_eq!((result.through, result.dead_ends, result.unconnected), (1, 0, 1));
+        // 3 corridor nodes, the point at -0.07 is skipped
+        assert_eq!(result.nodes.len(), 5);
+        assert_eq!(result.edges.len(), 8);
+        assert!(result.edges.windows(2).all(|edges| edges[0].src <= edges[1].src));
+        assert_eq!(result.layers.corridors.len(), 1);
+        assert!(result.layers.edge_corridor.iter().all(|corridor| *corridor == Some(0)));
+    }
+
+    #[test]
+    fn test_add_corridors_without_crossing_land() {
+        // the mouth of the river is closer to the ocean node behind the peninsula than to the one in front of it
+        let nodes = vec![Node { id: 0, lon: 0.05, lat: 0.0 }, Node { id: 1, lon: 0.0, lat: -0.1 }];
+        let peninsula = Island::new(vec![vec![0.02, -0.05], vec![0.02, 0.05], vec![0.03, 0.05], vec![0.03, -0.05], vec![0.02, -0.05]]);
+        let islands = vec![peninsula];
+        let corridor = Corridor { name: "river".to_string(), kind: "river".to_string(), limits: WaterwayLimits::default() };
+        let waterways = vec![Waterway { corridor, course: vec![vec![0.0, 0.0], vec![-0.5, 0.0]] }];
+        let result = add_corridors(nodes, vec![], &waterways, 20000.0, &islands, &CoastDistance::new(&islands));
+        assert_eq!((result.through, result.dead_ends), (0, 1));
+        assert!(result.edges.iter().any(|edge| edge.src == 1 && edge.tgt == 2));
+        assert!(!result.edges.iter().any(|edge| edge.src == 0 || edge.tgt == 0));
+    }
+}
@@ -1,16 +1,24 @@
 use std::time::SystemTime;
 use crate::binary_minheap::BinaryMinHeap;
-use graph_lib::{ShortestPathResult, Graph, Node};
+use graph_lib::{ShortestPathResult, Graph, Node, query::{QueryOptions, path_distance}};
 
 /**
  * Run a A* from the source coodinates to the target coordinates
 */
 pub fn run_a_star(src_node: &Node, tgt_node: &Node, graph: &Graph) -> ShortestPathResult {
+    run_a_star_with_options(src_node, tgt_node, graph, &QueryOptions::default())
+}
+
+/**
+ * Run a A*, which only uses the edges allowed by the options
+*/
+pub fn run_a_star_with_options(src_node: &Node, tgt_node: &Node, graph: &Graph, options: &QueryOptions) -> ShortestPathResult {
     let now = SystemTime::now();
     
-    // intialize heuristic and result
+    // intialize heuristic and result; with another metric than distance the direct distance is scaled to a lower bound
+    let heuristic_factor = options.heuristic_factor(graph);
     let mut heuristic_dists = HeuristicalDistances::init(
-        graph.n_nodes(), src_node.id, src_node.distance_to_node(tgt_node).floor() as u32
+        graph.n_nodes(), src_node.id, (src_node.distance_to_node(tgt_node) * heuristic_factor).floor() as u32
     );
     let mut priority_queue = BinaryMinHeap::with_capacity(graph.n_nodes());
     priority_queue.push(src_node.id, &heuristic_dists.g_plus_h);
@@ -22,14 +30,18 @@ pub fn run_a_star(src_node: &Node, tgt_node: &Node, graph: &Graph) -> ShortestPa
         if node_id == tgt_node.id {
             break;
         } else {
-            process_edges(graph, node_id, &mut heuristic_dists, &mut priority_queue, tgt_node);
+            process_edges(graph, node_id, &mut heuristic_dists, &mut priority_queue, tgt_node, options, heuristic_factor);
         }
         visited_nodes += 1;
     }
 
+    let path = heuristic_dists.build_path(graph, tgt_node.id);
     return ShortestPathResult {
-        distance: heuristic_dists.g_plus_h[tgt_node.id],
-        path: heuristic_dists.build_path(graph, tgt_node.id),
+        distance: match &path {
+            Some(path) if options.has_penalty() => path_distance(graph, path),
+            _ => heuristic_dists.g_plus_h[tgt_node.id],
+        },
+        path,
         calculation_time: now.elapsed().unwrap().as_millis(),
         visited_nodes
     }
@@ -38,14 +50,19 @@ pub fn run_a_star(src_node: &Node, tgt_node: &Node, graph: &Graph) -> ShortestPa
 /**
  * visits a node, which means it processes all its edges and updates the function g(a)+h(a) of all neighbours
 */
-fn process_edges(graph: &Graph, visiting_node_id: usize, heuristic_dists: &mut HeuristicalDistances, pq: &mut BinaryMinHeap, tgt_node: &Node) {
+fn process_edges(
+    graph: &Graph, visiting_node_id: usize, heuristic_dists: &mut HeuristicalDistances, pq: &mut BinaryMinHeap, tgt_node: &Node, options: &QueryOptions, heuristic_factor: f32
+) {
     let visiting_node_dist = heuristic_dists.g_plus_h[visiting_node_id] - heuristic_dists.heuristic[visiting_node_id];
-    for edge in graph.get_outgoing_edges(visiting_node_id) {
+    for (edge_id, edge) in graph.get_outgoing_edges_with_ids(visiting_node_id) {
+        let Some(cost) = options.edge_cost(graph, edge_id, edge) else {
+            continue;
+        };
         // if not calculated already, the heuristic is calculated here
         if heuristic_dists.heuristic[edge.tgt] == u32::MAX {
-            heuristic_dists.heuristic[edge.tgt] = graph.get_node(edge.tgt).distance_to_node(tgt_node).floor() as u32;
+            heuristic_dists.heuristic[edge.tgt] = (graph.get_node(edge.tgt).distance_to_node(tgt_node) * heuristic_factor).floor() as u32;
         }
-        let neighbour_node_dist = visiting_node_dist + edge.dist + heuristic_dists.heuristic[edge.tgt];
+        let neighbour_node_dist = visiting_node_dist.saturating_add(cost).saturating_add(heuristic_dists.heuristic[edge.tgt]);
 
         // if way over visiting node is the best so far, the neighbour will be updated
         if neighbour_node_dist < heuristic_dists.g_plus_h[edge.tgt] {
@@ -60,14 +77,14 @@ fn process_edges(graph: &Graph, visiting_node_id: usize, heuristic_dists: &mut H
 
 #[derive(Debug)]
 pub struct HeuristicalDistances {
-    g_plus_h: Vec<u32>,
-    preds: Vec<usize>,
-    heuristic: Vec<u32>
+    pub(crate) g_plus_h: Vec<u32>,
+    pub(crate) preds: Vec<usize>,
+    pub(crate) heuristic: Vec<u32>
 }
 
 impl HeuristicalDistances {
     /// Creates a new `HeuristicalDistances` instance for given graph size with dist to src 0.0 and else infinity
-    fn init(num_nodes: usize, src_id: usize, start_end_dist: u32) -> Self {
+    pub(crate) fn init(num_nodes: usize, src_id: usize, start_end_dist: u32) -> Self {
         let mut g_plus_h = vec![u32::MAX; num_nodes];
         let mut heuristic = vec![u32::MAX; num_nodes];
         heuristic[src_id] = start_end_dist;
@@ -1,11 +1,19 @@
 use std::time::SystemTime;
 use crate::binary_minheap::BinaryMinHeap;
-use graph_lib::{ShortestPathResult, Graph, Node};
+use graph_lib::{ShortestPathResult, Graph, Node, query::{QueryOptions, path_distance}};
 
 /**
  * Run a bidirectional Dijkstra from the source coodinates to the target coordinates
 */ 
 pub fn run_bidirectional_dijkstra(src_node: &Node, tgt_node: &Node, graph: &Graph, symmetric: bool) -> ShortestPathResult {
+    run_bidirectional_dijkstra_with_options(src_node, tgt_node, graph, symmetric, &QueryOptions::default())
+}
+
+/**
+ * Run a bidirectional Dijkstra, which only uses the edges allowed by the options; the backward search uses the costs
+ * of the edges towards the target, so they may differ in both directions
+*/
+pub fn run_bidirectional_dijkstra_with_options(src_node: &Node, tgt_node: &Node, graph: &Graph, symmetric: bool, options: &QueryOptions) -> ShortestPathResult {
     if src_node.id == tgt_node.id {
         return ShortestPathResult {calculation_time: 0, distance: 0, path: Some(vec![src_node.clone()]), visited_nodes: 0};
     }
@@ -39,7 +47,7 @@ pub fn run_bidirectional_dijkstra(src_node: &Node, tgt_node: &Node, graph: &Grap
 
         // if no faster solution can be found anymore, break here
         if symmetric {
-            if forward_dist + backward_dist >= result_dist {
+            if forward_dist.saturating_add(backward_dist) >= result_dist {
                 break;
             }
         } else {
@@ -50,16 +58,19 @@ pub fn run_bidirectional_dijkstra(src_node: &Node, tgt_node: &Node, graph: &Grap
 
         // process forward, similar to Dijkstra
         if forward_dist < result_dist {
-            for edge in graph.get_outgoing_edges(node_id_forward) {
-                let edge_tgt_dist = forward_dist + edge.dist;
+            for (edge_id, edge) in graph.get_outgoing_edges_with_ids(node_id_forward) {
+                let Some(cost) = options.edge_cost(graph, edge_id, edge) else {
+                    continue;
+                };
+                let edge_tgt_dist = forward_dist.saturating_add(cost);
                 if edge_tgt_dist < dijkstra_forward.dists[edge.tgt] {
                     dijkstra_forward.dists[edge.tgt] = edge_tgt_dist;
                     dijkstra_forward.preds[edge.tgt] = node_id_forward;
                     priority_queue_forward.insert_or_update(edge.tgt, &dijkstra_forward.dists);
                 }
                 
-                if dijkstra_backward.dists[edge.tgt] != u32::MAX && edge_tgt_dist + dijkstra_backward.dists[edge.tgt] < result_dist {
-                    result_dist = edge_tgt_dist + dijkstra_backward.dists[edge.tgt];
+                if dijkstra_backward.dists[edge.tgt] != u32::MAX && edge_tgt_dist.saturating_add(dijkstra_backward.dists[edge.tgt]) < result_dist {
+                    result_dist = edge_tgt_dist.saturating_add(dijkstra_backward.dists[edge.tgt]);
                     node_id_middle = Some(edge.tgt);
                 }
                 // println!("forward: {}, {}, {}, {}, {}", edge.src, forward_dist, edge.tgt, edge.dist, result_dist);
@@ -67,18 +78,21 @@ pub fn run_bidirectional_dijkstra(src_node: &Node, tgt_node: &Node, graph: &Grap
             visited_nodes += 1;
         }
 
-        // process backward, similar to Dijkstra
+        // process backward, similar to Dijkstra; the cost is of the edge in the direction of travel, which differs for a metric like the time
         if backward_dist < result_dist {
-            for edge in graph.get_outgoing_edges(node_id_backward) {
-                let edge_tgt_dist = backward_dist + edge.dist;
+            for (_, edge) in graph.get_outgoing_edges_with_ids(node_id_backward) {
+                let Some(cost) = graph.edge_between(edge.tgt, node_id_backward).and_then(|(reverse_id, reverse)| options.edge_cost(graph, reverse_id, reverse)) else {
+                    continue;
+                };
+                let edge_tgt_dist = backward_dist.saturating_add(cost);
                 if edge_tgt_dist < dijkstra_backward.dists[edge.tgt] {
                     dijkstra_backward.dists[edge.tgt] = edge_tgt_dist;
                     dijkstra_backward.preds[edge.tgt] = node_id_backward;
                     priority_queue_backward.insert_or_update(edge.tgt, &dijkstra_backward.dists);
                 }
                 
-                if dijkstra_forward.dists[edge.tgt] != u32::MAX && edge_tgt_dist + dijkstra_forward.dists[edge.tgt] < result_dist {
-                    result_dist = edge_tgt_dist + dijkstra_forward.dists[edge.tgt];
+                if dijkstra_forward.dists[edge.tgt] != u32::MAX && edge_tgt_dist.saturating_add(dijkstra_forward.dists[edge.tgt]) < result_dist {
+                    result_dist = edge_tgt_dist.saturating_add(dijkstra_forward.dists[edge.tgt]);
                     node_id_middle = Some(edge.tgt);
                 }
                 // println!("backward: {}, {}, {}, {}, {}", edge.src, backward_dist, edge.tgt, edge.dist, result_dist);
@@ -101,7 +115,10 @@ pub fn run_bidirectional_dijkstra(src_node: &Node, tgt_node: &Node, graph: &Grap
     };
 
     return ShortestPathResult {
-        distance: result_dist,
+        distance: match &result_path {
+            Some(path) if options.has_penalty() => path_distance(graph, path),
+            _ => result_dist,
+        },
         path: result_path,
         calculation_time: now.elapsed().unwrap().as_millis(),
         visited_nodes
@@ -1,9 +1,14 @@
 use std::time::SystemTime;
 use crate::binary_minheap::BinaryMinHeap;
-use graph_lib::{ShortestPathResult, Graph, Node};
+use graph_lib::{ShortestPathResult, Graph, Node, query::{QueryOptions, path_distance}};
 
 /// Run a Dijkstra from the source coodinates to the target coordinates
 pub fn run_dijkstra(src_node: &Node, tgt_node: &Node, graph: &Graph) -> ShortestPathResult {
+    run_dijkstra_with_options(src_node, tgt_node, graph, &QueryOptions::default())
+}
+
+/// Run a Dijkstra, which only uses the edges allowed by the options
+pub fn run_dijkstra_with_options(src_node: &Node, tgt_node: &Node, graph: &Graph, options: &QueryOptions) -> ShortestPathResult {
     let now = SystemTime::now();
     
     let mut dijkstra_dists = DijkstraDistances::init(graph.n_nodes(), src_node.id);
@@ -17,14 +22,18 @@ pub fn run_dijkstra(src_node: &Node, tgt_node: &Node, graph: &Graph) -> Shortest
         if node_id == tgt_node.id {
             break;
         } else {
-            process_edges(graph, node_id, &mut dijkstra_dists, &mut priority_queue);
+            process_edges(graph, node_id, &mut dijkstra_dists, &mut priority_queue, options);
         }
         visited_nodes += 1;
     }
 
+    let path = dijkstra_dists.build_path(graph, tgt_node.id);
     return ShortestPathResult {
-        distance: dijkstra_dists.dists[tgt_node.id],
-        path: dijkstra_dists.build_path(graph, tgt_node.id),
+        distance: match &path {
+            Some(path) if options.has_penalty() => path_distance(graph, path),
+            _ => dijkstra_dists.dists[tgt_node.id],
+        },
+        path,
         calculation_time: now.elapsed().unwrap().as_millis(),
         visited_nodes
     }
@@ -33,10 +42,13 @@ pub fn run_dijkstra(src_node: &Node, tgt_node: &Node, graph: &Graph) -> Shortest
 /**
  * Visits the node with given id and processes the outgoing edges of the node with id `node_id`
  */ 
-fn process_edges(graph: &Graph, node_id: usize, dijkstra_dists: &mut DijkstraDistances, pq: &mut BinaryMinHeap) {
+fn process_edges(graph: &Graph, node_id: usize, dijkstra_dists: &mut DijkstraDistances, pq: &mut BinaryMinHeap, options: &QueryOptions) {
     let node_dist = dijkstra_dists.dists[node_id];
-    for edge in graph.get_outgoing_edges(node_id) {
-        let dist = node_dist + edge.dist;
+    for (edge_id, edge) in graph.get_outgoing_edges_with_ids(node_id) {
+        let Some(cost) = options.edge_cost(graph, edge_id, edge) else {
+            continue;
+        };
+        let dist = node_dist.saturating_add(cost);
 
         // if path over visiting node is better than best found so far, update the neighbours distance
         if dist < dijkstra_dists.dists[edge.tgt] {
@@ -81,4 +93,154 @@ impl DijkstraDistances {
         path.reverse();
         return Some(path)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use graph_lib::{Graph, Node, Edge, Coordinates, layers::{GraphLayers, Metric}, query::QueryOptions, closures::{AvoidArea, Closures}, zones::{PenaltyZone, PenaltyZones}, distance_between, wrap_lon};
+
+    use crate::{
+        dijkstra::{run_dijkstra, run_dijkstra_with_options}, a_star::{run_a_star, run_a_star_with_options},
+        bidirectional_dijkstra::{run_bidirectional_dijkstra, run_bidirectional_dijkstra_with_options},
+    };
+
+    #[test]
+    fn test_dijkstra_skips_shallow_edges() {
+        // a short and shallow way from node 0 to 1 and a longer and deep way over node 2
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 1.0, lat: 0.0 }, Node { id: 2, lon: 0.5, lat: 0.5 }];
+        let edges = vec![
+            Edge { src: 0, tgt: 1, dist: 100 }, Edge { src: 0, tgt: 2, dist: 80 },
+            Edge { src: 1, tgt: 0, dist: 100 }, Edge { src: 1, tgt: 2, dist: 80 },
+            Edge { src: 2, tgt: 0, dist: 80 }, Edge { src: 2, tgt: 1, dist: 80 },
+        ];
+        let layers = GraphLayers { edge_depth: vec![8.0, 50.0, 8.0, 50.0, 50.0, 50.0], ..Default::default() };
+        let graph = Graph::with_layers(nodes, edges, layers);
+
+        let result = run_dijkstra_with_options(graph.get_node(0), graph.get_node(1), &graph, &QueryOptions::default());
+        assert_eq!(result.distance, 100);
+        let result = run_dijkstra_with_options(graph.get_node(0), graph.get_node(1), &graph, &QueryOptions::with_draft(7.5, 1.0));
+        assert_eq!(result.distance, 160);
+        let result = run_dijkstra_with_options(graph.get_node(0), graph.get_node(1), &graph, &QueryOptions::with_draft(60.0, 0.0));
+        assert!(result.path.is_none());
+    }
+
+    #[test]
+    fn test_routings_avoid_closed_area() {
+        // the direct way from node 0 to 1 crosses an area closed at runtime, the way over node 2 passes it in the north
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 1.0, lat: 0.0 }, Node { id: 2, lon: 0.5, lat: 0.5 }];
+        let edges = vec![
+            Edge { src: 0, tgt: 1, dist: 100 }, Edge { src: 0, tgt: 2, dist: 80 },
+            Edge { src: 1, tgt: 0, dist: 100 }, Edge { src: 1, tgt: 2, dist: 80 },
+            Edge { src: 2, tgt: 0, dist: 80 }, Edge { src: 2, tgt: 1, dist: 80 },
+        ];
+        let graph = Graph::new(nodes, edges);
+        let mut closures = Closures::default();
+        let area = AvoidArea::new("exercise", &[Coordinates(0.4, -0.1), Coordinates(0.6, -0.1), Coordinates(0.6, 0.1), Coordinates(0.4, 0.1)]);
+        let id = closures.add_area(&graph, area);
+        let options = QueryOptions::default().with_closures(Arc::new(closures.clone()));
+
+        let (src, tgt) = (graph.get_node(0), graph.get_node(1));
+        assert_eq!(run_dijkstra_with_options(src, tgt, &graph, &options).distance, 160);
+        assert_eq!(run_a_star_with_options(src, tgt, &graph, &options).distance, 160);
+        assert_eq!(run_bidirectional_dijkstra_with_options(src, tgt, &graph, true, &options).distance, 160);
+
+        closures.remove_area(&graph, id);
+        closures.close_nodes(&graph, &[2]);
+        let options = QueryOptions::default().with_closures(Arc::new(closures));
+        assert_eq!(run_dijkstra_with_options(src, tgt, &graph, &options).distance, 100);
+    }
+
+    #[test]
+    fn test_routings_with_penalty_zone() {
+        // the direct way from node 0 to 1 crosses an expensive zone, the way over node 2 is longer, but cheaper;
+        // the edges have their real length, so the heuristic of A* is a lower bound
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 1.0, lat: 0.0 }, Node { id: 2, lon: 0.5, lat: 0.5 }];
+        let edge = |src: usize, tgt: usize| {
+            let (a, b) = (&nodes[src], &nodes[tgt]);
+            Edge { src, tgt, dist: distance_between(a.lon, a.lat, b.lon, b.lat) as u32 }
+        };
+        let edges = vec![edge(0, 1), edge(0, 2), edge(1, 0), edge(1, 2), edge(2, 0), edge(2, 1)];
+        let (direct, detour) = (edges[0].dist, edges[1].dist + edges[3].dist);
+        let graph = Graph::new(nodes, edges);
+        let polygon = [Coordinates(0.4, -0.1), Coordinates(0.6, -0.1), Coordinates(0.6, 0.1), Coordinates(0.4, 0.1)];
+        let zones = PenaltyZones::new(&graph, vec![PenaltyZone::new("High Risk Area", &polygon, 2.0, 0)]);
+        let options = QueryOptions::default().with_zones(Arc::new(zones));
+
+        let (src, tgt) = (graph.get_node(0), graph.get_node(1));
+        for result in [
+            run_dijkstra_with_options(src, tgt, &graph, &options), run_a_star_with_options(src, tgt, &graph, &options),
+            run_bidirectional_dijkstra_with_options(src, tgt, &graph, true, &options),
+        ] {
+            assert_eq!(result.distance, detour);
+            assert!(options.zones.as_ref().unwrap().distances_in_zones(&graph, &result.path.unwrap()).is_empty());
+        }
+        // with a small multiplier the direct way stays the cheapest
+        let zones = Arc::new(PenaltyZones::new(&graph, vec![PenaltyZone::new("ECA", &polygon, 1.2, 0)]));
+        let result = run_a_star_with_options(src, tgt, &graph, &QueryOptions::default().with_zones(zones.clone()));
+        assert_eq!(result.distance, direct);
+        assert_eq!(zones.distances_in_zones(&graph, &result.path.unwrap()), vec![("ECA".to_string(), direct)]);
+    }
+
+    #[test]
+    fn test_routings_with_metric() {
+        // the time in s at 10 kn, the direct way from node 0 to 1 is slower than the way over node 2, e.g. against a current;
+        // only the way from 0 to 1 is against it, or both ways between them
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 1.0, lat: 0.0 }, Node { id: 2, lon: 0.5, lat: 0.5 }];
+        let edge = |src: usize, tgt: usize| {
+            let (a, b) = (&nodes[src], &nodes[tgt]);
+            Edge { src, tgt, dist: distance_between(a.lon, a.lat, b.lon, b.lat) as u32 }
+        };
+        let edges = vec![edge(0, 1), edge(0, 2), edge(1, 0), edge(1, 2), edge(2, 0), edge(2, 1)];
+        let detour = edges[1].dist + edges[3].dist;
+        for against_current in [vec![0], vec![0, 2]] {
+            let mut weights: Vec<u32> = edges.iter().map(|edge| (edge.dist as f32 / 5.144) as u32).collect();
+            for edge_id in against_current {
+                weights[edge_id] *= 2;
+            }
+            let metric = Metric::new("time", "s", weights, &edges);
+            let graph = Graph::with_layers(nodes.clone(), edges.clone(), GraphLayers { metrics: vec![metric], ..Default::default() });
+            let options = QueryOptions::default().with_metric(&graph, "time").unwrap();
+
+            let (src, tgt) = (graph.get_node(0), graph.get_node(1));
+            for result in [
+                run_dijkstra_with_options(src, tgt, &graph, &options), run_a_star_with_options(src, tgt, &graph, &options),
+                run_bidirectional_dijkstra_with_options(src, tgt, &graph, true, &options),
+            ] {
+                assert_eq!(result.distance, detour);
+                assert_eq!(result.path.unwrap().len(), 3);
+            }
+            assert_eq!(run_dijkstra(src, tgt, &graph).path.unwrap().len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_pacific_route_over_antimeridian() {
+        // a chain of nodes on the way of the sample route from 177°E to 155°W, and a long way around the world to the west
+        let (start, end) = ((177.23738, 38.280342), (-155.37436 + 360.0, 32.565235));
+        let mut nodes: Vec<Node> = (0..=30).map(|i| {
+            let fraction = i as f32 / 30.0;
+            Node { id: i, lon: wrap_lon(start.0 + (end.0 - start.0) * fraction), lat: start.1 + (end.1 - start.1) * fraction }
+        }).collect();
+        nodes.push(Node { id: 31, lon: 10.0, lat: 35.0 });
+        let mut edges = vec![];
+        let mut connect = |src: &Node, tgt: &Node| {
+            let dist = distance_between(src.lon, src.lat, tgt.lon, tgt.lat).ceil() as u32;
+            edges.push(Edge { src: src.id, tgt: tgt.id, dist });
+            edges.push(Edge { src: tgt.id, tgt: src.id, dist });
+        };
+        for i in 0..30 {
+            connect(&nodes[i], &nodes[i + 1]);
+        }
+        connect(&nodes[0], &nodes[31]);
+        connect(&nodes[31], &nodes[30]);
+        edges.sort_by_key(|edge| (edge.src, edge.tgt));
+        let graph = Graph::new(nodes, edges);
+
+        let direct = distance_between(start.0, start.1, end.0, end.1);
+        let (src, tgt) = (graph.get_node(0), graph.get_node(30));
+        for result in [run_dijkstra(src, tgt, &graph), run_a_star(src, tgt, &graph), run_bidirectional_dijkstra(src, tgt, &graph, true)] {
+            assert_eq!(result.path.unwrap().len(), 31);
+            assert!((result.distance as f32) < direct * 1.01);
+        }
+    }
+}
@@ -9,22 +9,91 @@ mod binary_minheap_map;
 mod ch;
 mod test_routing;
 mod ws_a_star;
+mod verify;
+mod time_dependent;
 
-use graph_lib::{Coordinates, Graph, file_interface::import_graph_from_file };
+use std::sync::{Arc, Mutex, OnceLock};
+use graph_lib::{Coordinates, Graph, file_interface::import_graph_from_file, land_mask::LandMask, closures::{AvoidArea, Closures}, zones::PenaltyZones, layers::DISTANCE_METRIC, currents::{CurrentField, METERS_PER_SECOND_PER_KNOT}, weather::{WeatherField, SeaKeeping}, voyage::Voyage, vessel::{VesselProfile, read_vessel_profiles, default_vessel_profiles}, query::{QueryOptions, EXCLUDE_PENALTY, DEFAULT_CLEARANCE_PENALTY}, ice::{MONTH_NAMES, node_in_ice, parse_departure_month, parse_departure_day} };
 use test_routing::{test_samples, test_random_samples, test_random_samples_compare_routings};
+use time_dependent::{run_time_dependent_routing, ignored_options};
+use verify::{default_chokepoints, read_chokepoints, verify_chokepoints, write_report};
 
-use crate::{bidirectional_dijkstra::run_bidirectional_dijkstra, ch::{new_ch_precalculations, continue_ch_precalculations, run_ch_a_star, run_ch_dijkstra}, a_star::run_a_star, dijkstra::run_dijkstra};
+use crate::{bidirectional_dijkstra::{run_bidirectional_dijkstra, run_bidirectional_dijkstra_with_options}, ch::{new_ch_precalculations, continue_ch_precalculations, run_ch_a_star, run_ch_dijkstra}, a_star::{run_a_star, run_a_star_with_options}, dijkstra::{run_dijkstra, run_dijkstra_with_options}};
 
 static mut GRAPH: Option<Graph> = None;
 static mut CH_GRAPH: Option<Graph> = None;
 static mut ROUTING: Routing = Routing::ChAStar;
+static LAND_MASK: OnceLock<LandMask> = OnceLock::new();
+/// closures at runtime; every change replaces them, so running routings keep the closures they started with
+static CLOSURES: Mutex<Option<Arc<Closures>>> = Mutex::new(None);
+static ZONES: OnceLock<Arc<PenaltyZones>> = OnceLock::new();
+static CURRENTS: OnceLock<CurrentField> = OnceLock::new();
+static WEATHER: OnceLock<WeatherField> = OnceLock::new();
+static VESSELS: OnceLock<Vec<VesselProfile>> = OnceLock::new();
+
+/// path of (lat, lon), warnings, the distance in km in every penalty zone on the way, the sum of the metric, if it isn't the distance,
+/// with a speed the arrival at every point of the path in hours after the departure and with a weather field
+/// the wind speed in m/s and wave height in m at every point at the arrival
+type RouteResult = (Vec<[f32;2]>, Option<String>, Vec<(String, f32)>, Option<String>, Vec<f32>, Vec<[f32;2]>);
 
 pub enum Routing {
     DI, BD, ASTAR, CH, ChAStar
 }
 
+/**
+ * route between the coordinates; with a draft the route only uses edges with a water depth of at least draft plus safety margin in m.
+ * With a minimal clearance in m, nodes closer to the coast are penalized or, if exclude is set, only used without other way.
+ * With a departure (month or date like 2024-07-15), edges covered by ice in that month are avoided.
+ * Returns the path and a warning, if the route comes closer to the coast than the clearance, e.g. in narrow straits,
+ * or an explanation of the ice zones, which the route avoids. Closed nodes, edges and avoid areas are never used.
+ * Edges in penalty zones cost more; the distance in km inside every zone, which the route passes, is returned as well.
+ * With a metric of the graph like time or fuel, its weights are optimized instead of the distance.
+ * With a speed through water in knots, the route with the earliest arrival is calculated with the currents at the departure hour
+ * instead: the hour of the departure date, if the fields know their start date, otherwise the hour after the start of the fields. With a weather field, the speed is reduced in waves and edges with waves
+ * higher than the max wave height in m (default 6) are avoided.
+ * With the name of a vessel profile, its draft is used without a draft, canals and rivers too small for it are avoided
+ * and the metric is scaled to it.
+ */
 #[tauri::command]
-fn route(coordinates: [[f32;2];2]) -> Vec<[f32;2]> {
+#[allow(clippy::too_many_arguments)]
+fn route(
+    coordinates: [[f32;2];2], draft: Option<f32>, safety_margin: Option<f32>, min_clearance: Option<f32>, exclude: Option<bool>, departure: Option<String>,
+    metric: Option<String>, speed: Option<f32>, departure_hour: Option<f32>, max_wave_height: Option<f32>, vessel: Option<String>
+) -> RouteResult {
+    let mut options = match draft {
+        Some(draft) => QueryOptions::with_draft(draft, safety_margin.unwrap_or(0.0)),
+        None => QueryOptions::default(),
+    };
+    let mut warnings = vec![];
+    if let (Some(name), Some(graph)) = (vessel, unsafe { GRAPH.as_ref() }) {
+        match VESSELS.get().and_then(|vessels| vessels.iter().find(|vessel| vessel.name == name)) {
+            Some(vessel) => options = options.with_vessel(graph, Arc::new(vessel.clone()), safety_margin.unwrap_or(0.0)),
+            None => warnings.push(format!("Unknown vessel {}", name)),
+        }
+    }
+    if let Some(min_clearance) = min_clearance {
+        let penalty = if exclude.unwrap_or(false) { EXCLUDE_PENALTY } else { DEFAULT_CLEARANCE_PENALTY };
+        options = options.with_clearance(min_clearance, penalty);
+    }
+    if let Some(month) = departure.as_deref().and_then(parse_departure_month) {
+        options = options.with_month(month);
+    }
+    if let Some(closures) = CLOSURES.lock().unwrap().clone().filter(|closures| !closures.is_empty()) {
+        options = options.with_closures(closures);
+    }
+    if let Some(zones) = ZONES.get() {
+        options = options.with_zones(zones.clone());
+    }
+    let mut zone_distances = vec![];
+    let mut metric_sum = None;
+    let mut arrival_hours = vec![];
+    let mut conditions = vec![];
+    if let (Some(metric), Some(graph)) = (metric, unsafe { GRAPH.as_ref() }) {
+        match options.clone().with_metric(graph, &metric) {
+            Ok(with_metric) => options = with_metric,
+            Err(error) => warnings.push(error),
+        }
+    }
 
     let src_coordinates = Coordinates(coordinates[0][1], coordinates[0][0]);
     let tgt_coordinates = Coordinates(coordinates[1][1], coordinates[1][0]);
@@ -36,14 +105,52 @@ fn route(coordinates: [[f32;2];2]) -> Vec<[f32;2]> {
             _ => (GRAPH.as_ref().unwrap().closest_node(&src_coordinates), GRAPH.as_ref().unwrap().closest_node(&tgt_coordinates)),
         };
         // println!("Routing from {:?} to {:?}", src_node, tgt_node);
+        // the restrictions are ignored on the CH graph, which the UI has to tell the user
+        if (!options.is_unrestricted() || speed.is_some_and(|speed| speed > 0.0)) && matches!(ROUTING, Routing::CH | Routing::ChAStar) {
+            warnings.push("Contraction hierarchies only find the shortest route; the draft, clearance, ice, closures, penalty zones, metric, vessel and speed are ignored".to_string());
+        }
 
-        let dijkstra_result = match ROUTING {
-            Routing::DI => run_dijkstra(src_node, tgt_node, GRAPH.as_ref().unwrap()),
-            Routing::BD => run_bidirectional_dijkstra(src_node, tgt_node, GRAPH.as_ref().unwrap(), true),
-            Routing::ASTAR => run_a_star(src_node, tgt_node, GRAPH.as_ref().unwrap()),
-            Routing::CH => run_ch_dijkstra(src_node, tgt_node, CH_GRAPH.as_ref().unwrap()),
-            Routing::ChAStar => run_ch_a_star(src_node, tgt_node, CH_GRAPH.as_ref().unwrap())
+        // with a speed the time dependent routing uses the currents and the weather, without them a constant speed
+        let voyage = speed.filter(|speed| *speed > 0.0 && matches!(ROUTING, Routing::DI | Routing::BD | Routing::ASTAR)).map(|speed| {
+            // the hour is of the departure date, if the fields know their start date
+            let mut voyage = Voyage::new(speed * METERS_PER_SECOND_PER_KNOT, departure_hour.unwrap_or(0.0))
+                .with_departure_day(departure.as_deref().and_then(parse_departure_day));
+            if let Some(currents) = CURRENTS.get() {
+                voyage = voyage.with_currents(currents);
+            }
+            if let Some(weather) = WEATHER.get() {
+                let sea_keeping = match max_wave_height {
+                    Some(max_wave_height) => SeaKeeping::default().with_max_wave_height(max_wave_height),
+                    None => SeaKeeping::default(),
+                };
+                voyage = voyage.with_weather(weather, sea_keeping);
+            }
+            voyage
+        });
+        let ignored = ignored_options(&options);
+        if voyage.is_some() && !ignored.is_empty() {
+            warnings.push(format!("The earliest arrival with a speed ignores the {}", ignored.join(", ")));
+        }
+        let run_routing = |options: &QueryOptions| match (&ROUTING, &voyage) {
+            (Routing::DI | Routing::BD | Routing::ASTAR, Some(voyage)) => {
+                let result = run_time_dependent_routing(src_node, tgt_node, GRAPH.as_ref().unwrap(), options, voyage, !matches!(ROUTING, Routing::DI));
+                (result.result, result.arrival_times, result.conditions)
+            },
+            (Routing::DI, None) => (run_dijkstra_with_options(src_node, tgt_node, GRAPH.as_ref().unwrap(), options), vec![], vec![]),
+            (Routing::BD, None) => (run_bidirectional_dijkstra_with_options(src_node, tgt_node, GRAPH.as_ref().unwrap(), true, options), vec![], vec![]),
+            (Routing::ASTAR, None) => (run_a_star_with_options(src_node, tgt_node, GRAPH.as_ref().unwrap(), options), vec![], vec![]),
+            (Routing::CH, _) => (run_ch_dijkstra(src_node, tgt_node, CH_GRAPH.as_ref().unwrap()), vec![], vec![]),
+            (Routing::ChAStar, _) => (run_ch_a_star(src_node, tgt_node, CH_GRAPH.as_ref().unwrap()), vec![], vec![]),
         };
+        let (dijkstra_result, arrival_times, route_conditions) = run_routing(&options);
+        arrival_hours = arrival_times.iter().map(|seconds| *seconds as f32 / 3600.0).collect();
+        conditions = route_conditions.iter().map(|conditions| [conditions.wind_speed(), conditions.wave_height]).collect();
+        if let Some(highest) = route_conditions.iter().map(|conditions| conditions.wave_height).reduce(f32::max) {
+            println!("Highest waves on the route {:.1} m", highest);
+        }
+        if let Some(hours) = arrival_hours.last() {
+            println!("Arrival after {:.1} h", hours);
+        }
         
         match &dijkstra_result.path {
             Some(current_path) => {
@@ -54,11 +161,62 @@ fn route(coordinates: [[f32;2];2]) -> Vec<[f32;2]> {
             None => ()
         }
         println!("Shortest path: {}", dijkstra_result);
-        
+        // the CH graph has no layers
+        if matches!(ROUTING, Routing::DI | Routing::BD | Routing::ASTAR) {
+            let graph = GRAPH.as_ref().unwrap();
+            if let Some(path) = dijkstra_result.path.as_ref() {
+                warnings.extend(options.clearance_warning(graph, path));
+                metric_sum = options.metric_of_path(graph, path);
+                // the time dependent routing doesn't use the zones
+                if let (Some(zones), None) = (options.zones.as_ref(), &voyage) {
+                    zone_distances = zones.distances_in_zones(graph, path).into_iter()
+                        .map(|(name, distance)| (name, distance as f32 / 1000.0))
+                        .collect();
+                }
+            }
+            // the ice zones are explained by the route, which ignores the ice
+            if let Some(month) = options.month.filter(|_| options.avoids_ice(graph)) {
+                let month_name = MONTH_NAMES[month as usize - 1];
+                match &dijkstra_result.path {
+                    Some(_) => warnings.extend(run_routing(&options.without_ice()).0.path.and_then(|path| options.avoided_ice(graph, &path))),
+                    None if node_in_ice(graph, src_node.id, month) || node_in_ice(graph, tgt_node.id, month) => {
+                        warnings.push(format!("The start or destination is covered by ice in {}", month_name));
+                    },
+                    None => warnings.push(format!("No route without ice in {}", month_name)),
+                }
+            }
+            if dijkstra_result.path.is_none() && options.closed_corridors.contains(&true) {
+                warnings.push(format!("The vessel doesn't fit through {}", options.closed_corridor_names(graph).join(", ")));
+            }
+            if let Some(closures) = options.closures.as_ref().filter(|_| dijkstra_result.path.is_none()) {
+                if closures.is_node_closed(src_node.id) || closures.is_node_closed(tgt_node.id) {
+                    warnings.push("The start or destination is in a closed area".to_string());
+                } else {
+                    warnings.push("No route around the closed areas".to_string());
+                }
+            }
+            for (name, distance) in &zone_distances {
+                println!("{:.0} km in {}", distance, name);
+            }
+            if let Some(metric_sum) = &metric_sum {
+                println!("Route with {}", metric_sum);
+            }
+        }
+        for warning in &warnings {
+            println!("Warning: {}", warning);
+        }
     }
 
 
-    shortest_path.into()
+    (shortest_path, if warnings.is_empty() { None } else { Some(warnings.join("\n")) }, zone_distances, metric_sum, arrival_hours, conditions)
+}
+
+/**
+ * true if the coordinates (lat, lon) are in water; None as long as the land mask is not loaded or if there are no coastlines
+ */
+#[tauri::command]
+fn is_water(coordinates: [f32;2]) -> Option<bool> {
+    LAND_MASK.get().map(|land_mask| land_mask.is_water(&Coordinates(coordinates[1], coordinates[0])))
 }
 
 fn main() {
@@ -120,6 +278,21 @@ fn main() {
                     import_ch_graph(&filename);
                     test_samples(unsafe { GRAPH.as_ref().unwrap() }, unsafe { CH_GRAPH.as_ref().unwrap()})
                 },
+                Some("verify") => {
+                    import_basic_graph(&filename);
+                    let chokepoints = match std::env::args_os().nth(3) {
+                        Some(catalogue) => read_chokepoints(&("data/".to_string() + catalogue.to_str().unwrap())).expect("Error reading chokepoints"),
+                        None => default_chokepoints(),
+                    };
+                    let results = verify_chokepoints(unsafe { GRAPH.as_ref().unwrap() }, &chokepoints);
+                    let report_path = format!("data/graph/{}_verify.tsv", filename);
+                    let failed = write_report(&report_path, &chokepoints, &results).expect("Error writing report");
+                    println!("Report written to {}", report_path);
+                    if failed > 0 {
+                        println!("{} of {} chokepoints failed", failed, chokepoints.len());
+                        std::process::exit(1);
+                    }
+                },
                 Some("ch_precalc") => unsafe {
                     import_basic_graph(&filename);
                     let node_limit = match std::env::args_os().nth(3) {
@@ -196,9 +369,132 @@ fn import_ch_graph(filename: &str) {
     println!("Finished importing");
 }
 
+/**
+ * names of the metrics, which the routings can optimize; distance is the default, the CH graph only has distance
+ */
+#[tauri::command]
+fn metrics() -> Vec<String> {
+    let names = unsafe { GRAPH.as_ref() }.map(|graph| graph.layers.metrics.iter().map(|metric| metric.name.clone()).collect()).unwrap_or_default();
+    [vec![DISTANCE_METRIC.to_string()], names].concat()
+}
+
+/**
+ * vessel profiles, which can be selected for the routings
+ */
+#[tauri::command]
+fn vessels() -> Vec<VesselProfile> {
+    VESSELS.get().cloned().unwrap_or_default()
+}
+
+/**
+ * closes the area of the polygon of (lat, lon) for all following routings; returns the id of the area
+ */
+#[tauri::command]
+fn add_avoid_area(name: String, polygon: Vec<[f32;2]>) -> Result<usize, String> {
+    if polygon.len() < 3 {
+        return Err("An avoid area needs at least 3 points".to_string());
+    }
+    let area = AvoidArea::new(&name, &polygon.iter().map(|point| Coordinates(point[1], point[0])).collect::<Vec<Coordinates>>());
+    let id = change_closures(|closures, graph| closures.add_area(graph, area))?;
+    println!("Avoid area {} ({}) added", id, name);
+    Ok(id)
+}
+
+/**
+ * opens the avoid area with the id again; false if there is no such area
+ */
+#[tauri::command]
+fn remove_avoid_area(id: usize) -> Result<bool, String> {
+    change_closures(|closures, graph| closures.remove_area(graph, id))
+}
+
+/**
+ * closes the nodes with all their edges
+ */
+#[tauri::command]
+fn close_nodes(ids: Vec<usize>) -> Result<(), String> {
+    change_closures(|closures, graph| closures.close_nodes(graph, &ids))
+}
+
+/**
+ * closes the edges in both directions
+ */
+#[tauri::command]
+fn close_edges(ids: Vec<usize>) -> Result<(), String> {
+    change_closures(|closures, graph| closures.close_edges(graph, &ids))
+}
+
+/**
+ * opens all closed nodes, edges and areas
+ */
+#[tauri::command]
+fn clear_closures() -> Result<(), String> {
+    change_closures(|closures, _| closures.clear())
+}
+
+/**
+ * changes a copy of the closures of the basic graph and replaces them; the CH graph has no closures
+ */
+fn change_closures<T>(change: impl FnOnce(&mut Closures, &Graph) -> T) -> Result<T, String> {
+    let graph = unsafe { GRAPH.as_ref() }.ok_or("Closures need the routing on the basic graph (di, bd or a*)")?;
+    let mut current = CLOSURES.lock().unwrap();
+    let mut closures = current.as_deref().cloned().unwrap_or_default();
+    let result = change(&mut closures, graph);
+    println!("{} edges closed", closures.n_closed_edges());
+    *current = Some(Arc::new(closures));
+    Ok(result)
+}
+
+/**
+ * starts the Leaflet UI; the land mask of the coastlines with the prefix of the 3rd parameter (default=complete) is loaded in the background,
+ * like the penalty zones of the GeoJSON file of the 4th parameter (default=zones.json) in the data folder for the basic graph
+ * the current field in the folder of the 5th parameter (default=currents) and the weather in the folder of the 6th parameter
+ * (default=weather) in the data folder; the vessel profiles are read from the JSON file of the 7th parameter (default=vessels.json)
+ * in the data folder, without it typical vessels are offered
+ */
 fn run_tauri() {
+    let vessels_file = "data/".to_string() + &param_to_string(7, Some("vessels.json")).unwrap();
+    let vessels = read_vessel_profiles(&vessels_file).unwrap_or_else(|error| {
+        println!("No vessel profiles loaded from {}, typical vessels are offered: {}", vessels_file, error);
+        default_vessel_profiles()
+    });
+    let _ = VESSELS.set(vessels);
+    let coastline_prefix = param_to_string(3, Some("complete")).unwrap();
+    let zones_file = "data/".to_string() + &param_to_string(4, Some("zones.json")).unwrap();
+    if let Some(graph) = unsafe { GRAPH.as_ref() } {
+        std::thread::spawn(move || match PenaltyZones::of_geojson(graph, &zones_file) {
+            Ok(zones) => {
+                println!("{} penalty zones of {} loaded", zones.zones().len(), zones_file);
+                let _ = ZONES.set(Arc::new(zones));
+            },
+            Err(error) => println!("No penalty zones loaded from {}: {}", zones_file, error),
+        });
+    }
+    let currents_folder = "data/".to_string() + &param_to_string(5, Some("currents")).unwrap();
+    std::thread::spawn(move || match CurrentField::read(&currents_folder) {
+        Ok(currents) => {
+            println!("Currents of {} time steps loaded from {}", currents.n_steps(), currents_folder);
+            let _ = CURRENTS.set(currents);
+        },
+        Err(error) => println!("No currents loaded from {}, routes with a speed ignore the currents: {}", currents_folder, error),
+    });
+    let weather_folder = "data/".to_string() + &param_to_string(6, Some("weather")).unwrap();
+    std::thread::spawn(move || match WeatherField::read(&weather_folder) {
+        Ok(weather) => {
+            println!("Weather of {} forecast times loaded from {}", weather.n_steps(), weather_folder);
+            let _ = WEATHER.set(weather);
+        },
+        Err(error) => println!("No weather loaded from {}, routes with a speed ignore wind and waves: {}", weather_folder, error),
+    });
+    std::thread::spawn(move || match LandMask::of_prefix(&coastline_prefix) {
+        Some(land_mask) => {
+            let _ = LAND_MASK.set(land_mask);
+            println!("Land mask of {} loaded, markers on land are refused", coastline_prefix);
+        },
+        None => println!("No coastlines with prefix {} found, markers on land are not checked", coastline_prefix),
+    });
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![route])
+        .invoke_handler(tauri::generate_handler![route, is_water, metrics, vessels, add_avoid_area, remove_avoid_area, close_nodes, close_edges, clear_closures])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -1,4 +1,4 @@
-use graph_lib::{Graph, Coordinates, ShortestPathResult, random_point::random_point_in_water, island::{read_geojsons, GridCell, GRID_DIVISIONS, Island}, Node};
+use graph_lib::{Graph, Coordinates, ShortestPathResult, random_point::random_point_in_water, island::IslandIndex, Node};
 
 use crate::{dijkstra::run_dijkstra, a_star::run_a_star, bidirectional_dijkstra::run_bidirectional_dijkstra, ch::{run_ch_dijkstra, run_ch_a_star}};
 
@@ -15,18 +15,14 @@ pub fn test_random_samples(graph: &Graph, routing: fn(src: &Node, tgt: &Node, gr
     let mut summed_visited_nodes = 0;
     let number_of_tests = 1000;
     let mut rng = rand::thread_rng();
-    println!("Import coastline");
-    let coastlines = read_geojsons("complete");
-    println!("Precalulations of islands");
-    let islands: Vec<Island> = coastlines.iter().map(|e| Island::new(e.to_owned())).collect();
-    let mut island_grid: Vec<Vec<GridCell>> = GRID_DIVISIONS.iter().map(|e| vec![GridCell::WATER; *e]).collect();
-    islands.iter().for_each(|island| island.add_to_grid(&mut island_grid));
+    println!("Import island index of the coastlines");
+    let island_index = IslandIndex::of_prefix("complete");
 
     println!("Start tests");
     for i in 0..number_of_tests {
-        let (lon, lat) = &random_point_in_water(&mut rng, &island_grid);
+        let (lon, lat) = &random_point_in_water(&mut rng, &island_index);
         let start_node = graph.closest_node(&Coordinates(*lon, *lat));
-        let (lon, lat) = &random_point_in_water(&mut rng, &island_grid);
+        let (lon, lat) = &random_point_in_water(&mut rng, &island_index);
         let end_node = graph.closest_node(&Coordinates(*lon, *lat));
         let result = routing(start_node, end_node, &graph);
         println!("Finished {}th query in {} ms", i, result.calculation_time);
@@ -50,18 +46,14 @@ pub fn test_random_samples_compare_routings(
     let mut different_results = 0;
     let number_of_tests = 1000;
     let mut rng = rand::thread_rng();
-    println!("Import coastline");
-    let coastlines = read_geojsons("complete");
-    println!("Precalulations of islands");
-    let islands: Vec<Island> = coastlines.iter().map(|e| Island::new(e.to_owned())).collect();
-    let mut island_grid: Vec<Vec<GridCell>> = GRID_DIVISIONS.iter().map(|e| vec![GridCell::WATER; *e]).collect();
-    islands.iter().for_each(|island| island.add_to_grid(&mut island_grid));
+    println!("Import island index of the coastlines");
+    let island_index = IslandIndex::of_prefix("complete");
 
     println!("Start tests");
     for i in 0..number_of_tests {
-        let (lon, lat) = &random_point_in_water(&mut rng, &island_grid);
+        let (lon, lat) = &random_point_in_water(&mut rng, &island_index);
         let start_node = graph1.closest_node(&Coordinates(*lon, *lat));
-        let (lon, lat) = &random_point_in_water(&mut rng, &island_grid);
+        let (lon, lat) = &random_point_in_water(&mut rng, &island_index);
         let end_node = graph1.closest_node(&Coordinates(*lon, *lat));
         let result1 = routing1(start_node, end_node, &graph1);
         let result2 = routing2(start_node, end_node, &graph2);
@@ -126,7 +118,7 @@ pub fn test_samples(graph: &Graph, ch_graph: &Graph) {
         end: Coordinates(-155.37436, 32.565235),
         // optimal_route_length: 37105730,
         // optimal_route_node_count: 3851,
-        description: "from 177°W to 155°E".to_string()
+        description: "from 177°E to 155°W, over the antimeridian".to_string()
     };
     // let coords_ = Route {              // from  to 
     //     start: Coordinates(),
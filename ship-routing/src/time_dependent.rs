@@ -0,0 +1,182 @@
+use std::time::SystemTime;
+use crate::{binary_minheap::BinaryMinHeap, a_star::HeuristicalDistances};
+use graph_lib::{ShortestPathResult, Graph, Node, query::{QueryOptions, path_distance}, voyage::Voyage, weather::Conditions};
+
+/**
+ * Result of a time dependent routing: the route, the arrival time at every node of the path in s after the departure
+ * and, with a weather field, the conditions at every node at the arrival
+ */
+pub struct TimeDependentResult {
+    pub result: ShortestPathResult,
+    pub arrival_times: Vec<u32>,
+    pub conditions: Vec<Conditions>,
+}
+
+/**
+ * the options, which the time dependent routing ignores, for a warning to the user
+ */
+pub fn ignored_options(options: &QueryOptions) -> Vec<&'static str> {
+    [
+        ("metric", options.metric.is_some()),
+        ("penalty zones", options.zones.as_ref().is_some_and(|zones| !zones.is_empty())),
+        ("clearance", options.min_clearance.is_some() && options.clearance_penalty > 1.0),
+    ].into_iter().filter(|(_, ignored)| *ignored).map(|(name, _)| name).collect()
+}
+
+/**
+ * Run a time dependent Dijkstra or, with a_star, A* for the earliest arrival of the voyage. The time of an edge depends on
+ * the current and the waves along it at the time the vessel gets there, edges with too high waves are skipped, see
+ * Voyage::traversal_time; edges restricted by the options are skipped as well, their penalties and metric are ignored,
+ * see ignored_options.
+ * The heuristic of A* is the direct distance at the speed plus the strongest current of the field.
+ * The vessel can't wait at a node, so the route is only the earliest if leaving later never arrives earlier (FIFO). The
+ * currents and the weather are interpolated between their time steps, which keeps this approximately, as long as they change
+ * little within the time of an edge; it isn't checked.
+ */
+pub fn run_time_dependent_routing(
+    src_node: &Node, tgt_node: &Node, graph: &Graph, options: &QueryOptions, voyage: &Voyage, a_star: bool
+) -> TimeDependentResult {
+    let now = SystemTime::now();
+    let max_speed_over_ground = voyage.max_speed_over_ground();
+    let heuristic_of = |node: &Node| if a_star { (node.distance_to_node(tgt_node) / max_speed_over_ground).floor() as u32 } else { 0 };
+
+    // g is the earliest arrival in s after the departure, the heuristic is 0 for Dijkstra
+    let mut arrivals = HeuristicalDistances::init(graph.n_nodes(), src_node.id, heuristic_of(src_node));
+    let mut priority_queue = BinaryMinHeap::with_capacity(graph.n_nodes());
+    priority_queue.push(src_node.id, &arrivals.g_plus_h);
+    let mut visited_nodes = 0;
+
+    while !priority_queue.is_empty() {
+        let node_id = priority_queue.pop(&arrivals.g_plus_h);
+        if node_id == tgt_node.id {
+            break;
+        }
+        let arrival = arrivals.g_plus_h[node_id] - arrivals.heuristic[node_id];
+        let node = graph.get_node(node_id);
+        for (edge_id, edge) in graph.get_outgoing_edges_with_ids(node_id) {
+            if !options.edge_allowed(graph, edge_id) {
+                continue;
+            }
+            let neighbour = graph.get_node(edge.tgt);
+            let Some(time) = voyage.traversal_time(node, neighbour, edge.dist, arrival) else {
+                continue;
+            };
+            if arrivals.heuristic[edge.tgt] == u32::MAX {
+                arrivals.heuristic[edge.tgt] = heuristic_of(neighbour);
+            }
+            let arrival_plus_h = arrival.saturating_add(time).saturating_add(arrivals.heuristic[edge.tgt]);
+            if arrival_plus_h < arrivals.g_plus_h[edge.tgt] {
+                arrivals.g_plus_h[edge.tgt] = arrival_plus_h;
+                arrivals.preds[edge.tgt] = node_id;
+                priority_queue.insert_or_update(edge.tgt, &arrivals.g_plus_h);
+            }
+        }
+        visited_nodes += 1;
+    }
+
+    let path = arrivals.build_path(graph, tgt_node.id);
+    let arrival_times: Vec<u32> = path.iter().flatten().map(|node| arrivals.g_plus_h[node.id] - arrivals.heuristic[node.id]).collect();
+    let conditions = path.iter().flatten().zip(&arrival_times).filter_map(|(node, arrival)| voyage.conditions_at(node, *arrival)).collect();
+    TimeDependentResult {
+        result: ShortestPathResult {
+            distance: path.as_ref().map_or(u32::MAX, |path| path_distance(graph, path)),
+            path,
+            calculation_time: now.elapsed().unwrap().as_millis(),
+            visited_nodes,
+        },
+        arrival_times,
+        conditions,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use graph_lib::{Graph, Node, Edge, query::QueryOptions, esri_ascii::RasterGrid, currents::CurrentField, weather::{WeatherGrid, WeatherField, SeaKeeping}, voyage::Voyage, distance_between};
+
+    use crate::time_dependent::{run_time_dependent_routing, ignored_options};
+
+    #[test]
+    fn test_route_with_current() {
+        // the direct way from node 0 to 1 is against a current of 2 m/s near the equator, the way over node 2 is longer;
+        // after 100 hours the current is gone
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 1.0, lat: 0.0 }, Node { id: 2, lon: 0.5, lat: 0.5 }];
+        let edge = |src: usize, tgt: usize| {
+            let (a, b) = (&nodes[src], &nodes[tgt]);
+            Edge { src, tgt, dist: distance_between(a.lon, a.lat, b.lon, b.lat) as u32 }
+        };
+        let edges = vec![edge(0, 1), edge(0, 2), edge(1, 0), edge(1, 2), edge(2, 0), edge(2, 1)];
+        let graph = Graph::new(nodes, edges);
+        let grid = |value: i32| {
+            let text = format!("ncols 6\nnrows 1\nxllcorner -1\nyllcorner -0.25\ncellsize 0.5\n{}\n", vec![value.to_string(); 6].join(" "));
+            RasterGrid::from_reader(text.as_bytes()).unwrap()
+        };
+        let currents = CurrentField::new(vec![(0.0, grid(-2), grid(0)), (99.0, grid(-2), grid(0)), (100.0, grid(0), grid(0))]);
+
+        let (src, tgt) = (graph.get_node(0), graph.get_node(1));
+        for a_star in [false, true] {
+            assert!(ignored_options(&QueryOptions::default()).is_empty());
+            assert_eq!(ignored_options(&QueryOptions::default().with_clearance(3704.0, 3.0)), vec!["clearance"]);
+            let voyage = Voyage::new(3.0, 0.0).with_currents(&currents);
+            let with_current = run_time_dependent_routing(src, tgt, &graph, &QueryOptions::default(), &voyage, a_star);
+            assert_eq!(with_current.result.path.as_ref().unwrap().len(), 3);
+            assert_eq!(with_current.arrival_times.len(), 3);
+            assert!(with_current.arrival_times.windows(2).all(|times| times[0] < times[1]));
+
+            assert!(with_current.conditions.is_empty());
+
+            let voyage = Voyage::new(3.0, 100.0).with_currents(&currents);
+            let without_current = run_time_dependent_routing(src, tgt, &graph, &QueryOptions::default(), &voyage, a_star);
+            assert_eq!(without_current.result.distance, graph.edges[0].dist);
+            assert_eq!(without_current.arrival_times, vec![0, (graph.edges[0].dist as f32 / 3.0).round() as u32]);
+        }
+    }
+
+    #[test]
+    fn test_route_through_weather() {
+        // the direct way from node 0 over node 3 to node 1 leads through a storm at node 3 for the first 24 hours,
+        // the way over node 2 is longer; the wind comes from the east
+        let nodes = vec![
+            Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 1.0, lat: 0.0 },
+            Node { id: 2, lon: 0.5, lat: 0.5 }, Node { id: 3, lon: 0.5, lat: 0.0 },
+        ];
+        let edge = |src: usize, tgt: usize| {
+            let (a, b) = (&nodes[src], &nodes[tgt]);
+            Edge { src, tgt, dist: distance_between(a.lon, a.lat, b.lon, b.lat) as u32 }
+        };
+        let edges = vec![edge(0, 2), edge(0, 3), edge(1, 2), edge(1, 3), edge(2, 0), edge(2, 1), edge(3, 0), edge(3, 1)];
+        let graph = Graph::new(nodes, edges);
+        let grid = |storm: f32| {
+            let mut text = "lon,lat,wind_u,wind_v,wave_height\n".to_string();
+            for (lon, lat) in [(0.0, 0.0), (0.5, 0.0), (1.0, 0.0), (0.0, 0.5), (0.5, 0.5), (1.0, 0.5)] {
+                let wave_height = if (lon, lat) == (0.5, 0.0) { storm } else { 2.0 };
+                text += &format!("{},{},-10,0,{}\n", lon, lat, wave_height);
+            }
+            WeatherGrid::from_reader(text.as_bytes()).unwrap()
+        };
+        let weather = WeatherField::new(vec![(0.0, grid(8.0)), (24.0, grid(8.0)), (25.0, grid(2.0))]);
+
+        let (src, tgt) = (graph.get_node(0), graph.get_node(1));
+        for a_star in [false, true] {
+            let voyage = Voyage::new(5.0, 0.0).with_weather(&weather, SeaKeeping::default());
+            let in_storm = run_time_dependent_routing(src, tgt, &graph, &QueryOptions::default(), &voyage, a_star);
+            let path = in_storm.result.path.unwrap();
+            assert_eq!(path[1].id, 2);
+            assert_eq!(in_storm.conditions.len(), 3);
+            assert!(in_storm.conditions.iter().all(|conditions| conditions.wave_height == 2.0));
+
+            // after the storm the direct way against the wind, with 5 % loss of speed in the waves of 2 m
+            let voyage = Voyage::new(5.0, 30.0).with_weather(&weather, SeaKeeping::default());
+            let after_storm = run_time_dependent_routing(src, tgt, &graph, &QueryOptions::default(), &voyage, a_star);
+            assert_eq!(after_storm.result.path.unwrap()[1].id, 3);
+            let calm = (graph.edges[1].dist + graph.edges[7].dist) as f32 / 5.0;
+            assert!(*after_storm.arrival_times.last().unwrap() as f32 > calm * 1.04);
+
+            // a vessel, which copes with waves of 8 m, avoids the storm anyway, because it would lose 60 % of its speed
+            let voyage = Voyage::new(5.0, 0.0).with_weather(&weather, SeaKeeping::default().with_max_wave_height(8.0));
+            let through_storm = run_time_dependent_routing(src, tgt, &graph, &QueryOptions::default(), &voyage, a_star);
+            assert_eq!(through_storm.result.path.unwrap()[1].id, 2);
+            let direct = graph.edges[1].dist as f32 / 4.75 + graph.edges[7].dist as f32 / 2.0;
+            assert!((*through_storm.arrival_times.last().unwrap() as f32) < direct);
+        }
+    }
+}
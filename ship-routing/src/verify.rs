@@ -0,0 +1,162 @@
+use std::{fs, io::Write, error::Error};
+use graph_lib::{Graph, Coordinates};
+
+use crate::bidirectional_dijkstra::run_bidirectional_dijkstra;
+
+/**
+ * maximal distance in m of a point of a chokepoint to the closest node, otherwise the chokepoint is outside of the graph
+ */
+const SNAP_DISTANCE: f32 = 50000.0;
+
+/**
+ * Passage through a strait or canal: a route from start to end has to be shorter than the maximal distance,
+ * otherwise the passage is disconnected and the route takes a detour.
+ */
+pub struct Chokepoint {
+    pub name: String,
+    pub start: Coordinates,
+    pub end: Coordinates,
+    /// in m
+    pub max_distance: u32,
+}
+
+/**
+ * (name, start lon, start lat, end lon, end lat, maximal distance in km)
+ */
+const CHOKEPOINTS: [(&str, f32, f32, f32, f32, u32); 14] = [
+    ("Strait of Gibraltar", -6.8, 35.9, -4.5, 36.1, 300),
+    ("Bosporus", 29.3, 41.4, 28.9, 40.8, 150),
+    ("Dardanelles", 27.0, 40.6, 25.9, 39.9, 250),
+    ("Strait of Malacca", 97.5, 6.0, 104.2, 1.2, 1400),
+    ("Panama Canal", -79.9, 9.5, -79.5, 8.7, 200),
+    ("Suez Canal", 32.3, 31.5, 32.6, 29.8, 350),
+    ("Kiel Canal", 8.6, 54.0, 10.3, 54.5, 300),
+    ("Strait of Dover", 2.2, 51.6, 1.0, 50.5, 250),
+    ("Strait of Hormuz", 55.5, 26.3, 57.5, 25.3, 450),
+    ("Bab-el-Mandeb", 42.8, 13.5, 44.0, 12.3, 350),
+    ("Bering Strait", -169.5, 64.8, -168.5, 66.8, 400),
+    ("Strait of Magellan", -70.5, -52.5, -74.5, -52.8, 600),
+    ("Vilkitsky Strait (Northern Sea Route)", 93.0, 77.5, 112.0, 77.5, 900),
+    ("Parry Channel (Northwest Passage)", -80.0, 74.2, -105.0, 74.6, 1300),
+];
+
+/**
+ * catalogue of the most important straits and canals
+ */
+pub fn default_chokepoints() -> Vec<Chokepoint> {
+    CHOKEPOINTS.iter().map(|(name, start_lon, start_lat, end_lon, end_lat, max_km)| Chokepoint {
+        name: name.to_string(),
+        start: Coordinates(*start_lon, *start_lat),
+        end: Coordinates(*end_lon, *end_lat),
+        max_distance: max_km * 1000,
+    }).collect()
+}
+
+/**
+ * reads a catalogue with one chokepoint per line: name, start lon, start lat, end lon, end lat and maximal distance in km,
+ * separated by tabs; empty lines and lines starting with # are skipped
+ */
+pub fn read_chokepoints(filepath: &str) -> Result<Vec<Chokepoint>, Box<dyn Error>> {
+    fs::read_to_string(filepath)?.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split('\t').map(|field| field.trim()).collect();
+            if fields.len() != 6 {
+                return Err(format!("Expected 6 fields separated by tabs in line: {}", line).into());
+            }
+            let number = |i: usize| fields[i].parse::<f32>().map_err(|_| format!("No number {} in line: {}", fields[i], line));
+            Ok(Chokepoint {
+                name: fields[0].to_string(),
+                start: Coordinates(number(1)?, number(2)?),
+                end: Coordinates(number(3)?, number(4)?),
+                max_distance: (number(5)? * 1000.0) as u32,
+            })
+        })
+        .collect()
+}
+
+pub enum VerifyStatus {
+    Passed,
+    /// the route is longer than the maximal distance
+    Detour,
+    NoRoute,
+    /// a point is too far from the nodes, e.g. for regional graphs
+    OutsideOfGraph,
+}
+
+pub struct VerifyResult {
+    pub status: VerifyStatus,
+    /// in m, None without route
+    pub distance: Option<u32>,
+}
+
+/**
+ * routes through every chokepoint with bidirectional Dijkstra and compares the distance with the expected maximal distance
+ */
+pub fn verify_chokepoints(graph: &Graph, chokepoints: &[Chokepoint]) -> Vec<VerifyResult> {
+    chokepoints.iter().map(|chokepoint| {
+        let src_node = graph.closest_node(&chokepoint.start);
+        let tgt_node = graph.closest_node(&chokepoint.end);
+        if src_node.distance_to(&chokepoint.start) > SNAP_DISTANCE || tgt_node.distance_to(&chokepoint.end) > SNAP_DISTANCE {
+            return VerifyResult { status: VerifyStatus::OutsideOfGraph, distance: None };
+        }
+        let result = run_bidirectional_dijkstra(src_node, tgt_node, graph, true);
+        match result.path {
+            None => VerifyResult { status: VerifyStatus::NoRoute, distance: None },
+            Some(_) if result.distance > chokepoint.max_distance => VerifyResult { status: VerifyStatus::Detour, distance: Some(result.distance) },
+            Some(_) => VerifyResult { status: VerifyStatus::Passed, distance: Some(result.distance) },
+        }
+    }).collect()
+}
+
+/**
+ * writes the measured and expected distances as tab separated table; returns the number of failed chokepoints
+ */
+pub fn write_report(filepath: &str, chokepoints: &[Chokepoint], results: &[VerifyResult]) -> Result<usize, Box<dyn Error>> {
+    let mut report = String::from("Chokepoint\tExpected max (km)\tMeasured (km)\tStatus\n");
+    let mut failed = 0;
+    for (chokepoint, result) in chokepoints.iter().zip(results) {
+        let status = match result.status {
+            VerifyStatus::Passed => "passed",
+            VerifyStatus::Detour => "FAILED: detour",
+            VerifyStatus::NoRoute => "FAILED: no route",
+            VerifyStatus::OutsideOfGraph => "skipped: outside of graph",
+        };
+        if matches!(result.status, VerifyStatus::Detour | VerifyStatus::NoRoute) {
+            failed += 1;
+        }
+        let measured = result.distance.map(|distance| format!("{:.1}", distance as f32 / 1000.0)).unwrap_or("-".to_string());
+        report += &format!("{}\t{:.1}\t{}\t{}\n", chokepoint.name, chokepoint.max_distance as f32 / 1000.0, measured, status);
+    }
+    print!("{}", report);
+    fs::File::create(filepath)?.write_all(report.as_bytes())?;
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod test {
+    use graph_lib::{Graph, Node, Edge, Coordinates};
+
+    use crate::verify::{verify_chokepoints, Chokepoint, VerifyStatus};
+
+    #[test]
+    fn test_verify_chokepoints() {
+        // a strait from node 0 to 1 and an island at node 2, which is disconnected
+        let nodes = vec![Node { id: 0, lon: 0.0, lat: 0.0 }, Node { id: 1, lon: 0.5, lat: 0.0 }, Node { id: 2, lon: 0.0, lat: 0.3 }];
+        let edges = vec![Edge { src: 0, tgt: 1, dist: 55600 }, Edge { src: 1, tgt: 0, dist: 55600 }];
+        let graph = Graph::new(nodes, edges);
+        let chokepoint = |end: Coordinates, max_distance: u32| Chokepoint { name: "strait".to_string(), start: Coordinates(0.0, 0.0), end, max_distance };
+        let chokepoints = [
+            chokepoint(Coordinates(0.5, 0.0), 60000),
+            chokepoint(Coordinates(0.5, 0.0), 50000),
+            chokepoint(Coordinates(0.0, 0.3), 60000),
+            chokepoint(Coordinates(10.0, 10.0), 60000),
+        ];
+        let results = verify_chokepoints(&graph, &chokepoints);
+        assert!(matches!(results[0].status, VerifyStatus::Passed));
+        assert_eq!(results[0].distance, Some(55600));
+        assert!(matches!(results[1].status, VerifyStatus::Detour));
+        assert!(matches!(results[2].status, VerifyStatus::NoRoute));
+        assert!(matches!(results[3].status, VerifyStatus::OutsideOfGraph));
+    }
+}